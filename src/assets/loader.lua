@@ -0,0 +1,38 @@
+-- Live loader for the {{TARGET}} bundle served by {{NAME}} (v{{VERSION}})
+-- Runs the bundle, then runs it again every time it is rebuilt.
+
+local BUNDLE_URL = {{BUNDLE_URL}}
+local POLL_URL = {{POLL_URL}}
+local RETRY_DELAY = 2
+
+local httpRequest = request or http_request or (syn and syn.request) or (http and http.request)
+
+local function get(url)
+	if httpRequest then
+		local response = httpRequest({ Url = url, Method = "GET" })
+		return response.StatusCode, response.Body
+	end
+	return 200, game:HttpGet(url)
+end
+
+local hash = ""
+while true do
+	-- Answers with the new hash once the bundle changes, or with nothing on timeout
+	local ok, status, body = pcall(get, POLL_URL .. "?hash=" .. hash)
+	if ok and status == 200 and body ~= "" then
+		hash = body
+		local fetched, bundleStatus, source = pcall(get, BUNDLE_URL)
+		if fetched and bundleStatus == 200 then
+			local chunk, err = loadstring(source, {{CHUNK_NAME}})
+			if chunk then
+				task.spawn(chunk)
+			else
+				warn("[{{NAME}}] Failed to load bundle: " .. tostring(err))
+			end
+		else
+			warn("[{{NAME}}] Failed to fetch bundle: " .. tostring(source or bundleStatus))
+		end
+	elseif not ok or (status ~= 200 and status ~= 204) then
+		task.wait(RETRY_DELAY)
+	end
+end
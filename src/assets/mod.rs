@@ -16,9 +16,17 @@ pub const TREE_HEADER: &str = r"-- Instance Tree Manifest
 
 pub const RUNTIME_BODY: &str = include_str!("runtime.lua");
 
+pub const SERVE_LOADER: &str = include_str!("loader.lua");
+
+pub const ANALYZER_PAGE: &str = include_str!("analyzer.html");
+
 // -- Darklua Configurations --
 
 pub const DARKLUA_DEV: &str = include_str!("darklua/dev.json");
 pub const DARKLUA_DEV_COMPAT: &str = include_str!("darklua/dev-compat.json");
 pub const DARKLUA_REL: &str = include_str!("darklua/rel.json");
 pub const DARKLUA_REL_COMPAT: &str = include_str!("darklua/rel-compat.json");
+
+// -- Project File --
+
+pub const PROJECT_TEMPLATE: &str = include_str!("project.toml");
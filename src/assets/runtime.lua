@@ -1,5 +1,7 @@
-local __rbx, __lua, __env, __start
+local __rbx, __lua, __named, __props, __refs, __env, __start
 do
+	local CollectionService = game:GetService("CollectionService")
+
 	local instances = {}
 	local modules = {}
 	local currentlyLoading = {}
@@ -48,16 +50,29 @@ do
 		end
 	end
 
-	function __rbx(name, className, path, parentPath)
+	function __rbx(name, className, id, parentId, attributes, tags)
 		local rbx = Instance.new(className)
 		rbx.Name = name
-		rbx.Parent = instances[parentPath]
-		instances[path] = rbx
+
+		if attributes then
+			for key, value in pairs(attributes) do
+				rbx:SetAttribute(key, value)
+			end
+		end
+
+		if tags then
+			for _, tag in ipairs(tags) do
+				CollectionService:AddTag(rbx, tag)
+			end
+		end
+
+		rbx.Parent = instances[parentId]
+		instances[id] = rbx
 		return rbx
 	end
 
-	function __lua(name, className, path, parentPath, callback)
-		local rbx = __rbx(name, className, path, parentPath)
+	function __lua(name, className, id, parentId, callback, attributes, tags)
+		local rbx = __rbx(name, className, id, parentId, attributes, tags)
 
 		modules[rbx] = {
 			callback = callback,
@@ -76,15 +91,62 @@ do
 		}
 	end
 
-	function __env(path)
-		return modules[instances[path]].globals
-	end
+	local namedPaths = {}
+
+	function __named(path, callback)
+		namedPaths[path] = true
+
+		-- Errors keep the path of the innermost script, even once rethrown by its requirers
+		local function rewrite(err)
+			if type(err) ~= "string" then
+				return err
+			end
+			local prefix = string.match(err, "^(.-): ")
+			if prefix and namedPaths[prefix] then
+				return err
+			end
+			return path .. ": " .. err
+		end
 
-	function __start()
-		for rbx, module in pairs(modules) do
-			if rbx.ClassName == "LocalScript" and not rbx.Disabled then
-				task.spawn(module.callback)
+		return function()
+			local ok, result = xpcall(callback, rewrite)
+			if not ok then
+				error(result, 0)
 			end
+			return result
+		end
+	end
+
+	local function assign(rbx, key, value)
+		local ok, err = pcall(function()
+			rbx[key] = value
+		end)
+		if not ok then
+			warn("Failed to set " .. rbx:GetFullName() .. "." .. key .. ": " .. tostring(err))
+		end
+	end
+
+	function __props(id, props)
+		local rbx = instances[id]
+		for key, value in pairs(props) do
+			assign(rbx, key, value)
+		end
+	end
+
+	function __refs(id, refs)
+		local rbx = instances[id]
+		for key, target in pairs(refs) do
+			assign(rbx, key, instances[target])
+		end
+	end
+
+	function __env(id)
+		return modules[instances[id]].globals
+	end
+
+	function __start(ids)
+		for _, id in ipairs(ids) do
+			task.spawn(modules[instances[id]].callback)
 		end
 	end
 end
\ No newline at end of file
@@ -0,0 +1,133 @@
+//! Offline HTML treemaps of what a bundle is made of.
+//!
+//! The analyzer is a single static page: the instance tree, every node sized
+//! by the bytes its registration and properties take in the final bundle, and
+//! the original source of every script are embedded as JSON next to the
+//! script that draws the treemap, so it works without a network connection.
+
+use rbx_dom_weak::WeakDom;
+use serde::Serialize;
+
+use super::error::BundlerError;
+use super::report::BundleLayout;
+use super::writer::script_source;
+use crate::assets;
+
+/// A node of the treemap.
+#[derive(Debug, Serialize)]
+struct TreemapNode {
+    name: String,
+    class: String,
+    path: String,
+    /// Bytes of the node's own registration and properties, excluding children.
+    size: usize,
+    /// Original source of a bundled script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    children: Vec<TreemapNode>,
+}
+
+impl TreemapNode {
+    fn part(name: &str, size: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            class: "Bundle".to_string(),
+            path: name.to_string(),
+            size,
+            source: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.size + self.children.iter().map(TreemapNode::total).sum::<usize>()
+    }
+}
+
+/// The data embedded into the analyzer page.
+#[derive(Debug, Serialize)]
+struct AnalyzerData<'a> {
+    file: &'a str,
+    target: &'a str,
+    total_bytes: usize,
+    tree: TreemapNode,
+}
+
+/// Renders the analyzer page of a final bundle.
+pub(crate) fn render_analyzer(
+    layout: &BundleLayout,
+    source: &str,
+    dom: &WeakDom,
+    target: &str,
+    file: &str,
+) -> Result<String, BundlerError> {
+    // Instances always come after their parent, so children are attached in reverse
+    let mut nodes: Vec<Option<TreemapNode>> = layout
+        .tree
+        .nodes
+        .iter()
+        .map(|node| {
+            let instance = dom.get_by_ref(node.referent);
+            Some(TreemapNode {
+                name: instance.map_or_else(|| node.path.clone(), |inst| inst.name.clone()),
+                class: node.class.clone(),
+                path: node.path.clone(),
+                size: node.end - node.start,
+                source: node.source_bytes.and(instance).map(script_source),
+                children: Vec::new(),
+            })
+        })
+        .collect();
+
+    let mut roots = Vec::new();
+    for index in (0..nodes.len()).rev() {
+        let mut node = nodes[index]
+            .take()
+            .ok_or_else(|| BundlerError::Internal("Instance laid out twice".to_string()))?;
+        node.children.reverse();
+        match layout.tree.nodes[index].parent.and_then(|parent| nodes.get_mut(parent)) {
+            Some(Some(parent)) => parent.children.push(node),
+            _ => roots.push(node),
+        }
+    }
+    roots.reverse();
+
+    let header = TreemapNode::part("Header", layout.header_bytes);
+    let runtime = TreemapNode::part("Runtime", layout.runtime_bytes);
+    let accounted = header.size + runtime.size + roots.iter().map(TreemapNode::total).sum::<usize>();
+    let references = TreemapNode::part("References & start", source.len().saturating_sub(accounted));
+
+    let mut children = vec![header, runtime];
+    children.extend(roots);
+    children.push(references);
+
+    let data = AnalyzerData {
+        file,
+        target,
+        total_bytes: source.len(),
+        tree: TreemapNode {
+            name: file.to_string(),
+            class: "Bundle".to_string(),
+            path: file.to_string(),
+            size: 0,
+            source: None,
+            children,
+        },
+    };
+
+    // `<` is escaped so no source can close the script element holding the data
+    let json = serde_json::to_string(&data)
+        .map_err(|e| BundlerError::Internal(format!("Failed to serialize analyzer data: {e}")))?
+        .replace('<', "\\u003c");
+
+    Ok(assets::ANALYZER_PAGE
+        .replace("{{TITLE}}", &escape_html(file))
+        .replace("{{DATA}}", &json))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
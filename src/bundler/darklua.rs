@@ -0,0 +1,233 @@
+//! Darklua configuration for each target, built-in or user-supplied.
+//!
+//! Every target starts from one of the embedded `assets::DARKLUA_*` configs.
+//! Users can replace it entirely or extend it with extra rules, and the result
+//! is validated before any target is built.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use darklua_core::Configuration;
+use serde_json::Value;
+
+use super::types::{Mode, Target};
+use crate::assets;
+
+/// A user-supplied darklua configuration (JSON) applied to a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DarkluaOverride {
+    /// Replaces the built-in configuration of the target.
+    Replace(String),
+    /// Extends the built-in configuration of the target.
+    ///
+    /// Rules are appended, except that a rule sharing its name with an existing
+    /// one replaces it in place; `globals` lists are merged rather than
+    /// replaced, so the runtime's functions stay protected from renaming.
+    /// Other top-level fields, such as `generator`, override the existing ones.
+    Extend(String),
+}
+
+/// A global value injected into scripts at build time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefineValue {
+    /// A `true` or `false` literal.
+    Bool(bool),
+    /// A number literal, always finite since Luau has no literal for the others.
+    Number(f64),
+    /// A string literal.
+    String(String),
+}
+
+/// Largest integer a Luau number, an `f64`, holds exactly.
+pub(crate) const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+impl FromStr for DefineValue {
+    type Err = std::convert::Infallible;
+
+    /// Parses `true`/`false` as booleans, finite numbers as numbers, and
+    /// anything else as a string, so `nan`, `inf` or integers too large to
+    /// keep exactly stay strings.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "true" => DefineValue::Bool(true),
+            "false" => DefineValue::Bool(false),
+            _ => match s.parse::<f64>() {
+                Ok(value) if value.is_finite() && !is_inexact_integer(s) => DefineValue::Number(value),
+                _ => DefineValue::String(s.to_string()),
+            },
+        })
+    }
+}
+
+/// Returns whether a string is an integer literal, with an optional sign,
+/// that an `f64` would round.
+fn is_inexact_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty()
+        && digits.bytes().all(|byte| byte.is_ascii_digit())
+        && digits.parse::<u64>().map_or(true, |value| value > MAX_EXACT_INTEGER)
+}
+
+impl DefineValue {
+    /// Returns the `inject_global_value` rule defining a global with this value.
+    fn to_rule(&self, name: &str) -> Value {
+        let value = match self {
+            DefineValue::Bool(value) => Value::from(*value),
+            DefineValue::Number(value) => Value::from(*value),
+            DefineValue::String(value) => Value::from(value.as_str()),
+        };
+        serde_json::json!({ "rule": "inject_global_value", "identifier": name, "value": value })
+    }
+}
+
+/// Returns the embedded darklua configuration of a target.
+///
+/// Development targets apply it to each script, release targets to the whole bundle.
+#[must_use]
+pub fn builtin_config(target: &Target) -> &'static str {
+    match (target.mode(), target.compat()) {
+        (Mode::Development, false) => assets::DARKLUA_DEV,
+        (Mode::Development, true) => assets::DARKLUA_DEV_COMPAT,
+        (Mode::Production, false) => assets::DARKLUA_REL,
+        (Mode::Production, true) => assets::DARKLUA_REL_COMPAT,
+    }
+}
+
+/// Resolves the darklua configuration of a target by applying every override
+/// for it, in order, on top of the built-in configuration. Custom targets
+/// apply their own overrides first.
+///
+/// Defines become `inject_global_value` rules placed before every other rule,
+/// so later rules can simplify the code that depends on them. A custom
+/// target's own defines take precedence over the shared ones.
+///
+/// # Errors
+///
+/// Returns an error if an override is not valid JSON, or if the resulting
+/// configuration is not a valid darklua configuration.
+pub fn resolve_config(
+    target: &Target,
+    overrides: &[(Target, DarkluaOverride)],
+    defines: &BTreeMap<String, DefineValue>,
+) -> Result<String> {
+    let mut applicable: Vec<&DarkluaOverride> = Vec::new();
+    let mut defines = defines.clone();
+    if let Target::Custom(custom) = target {
+        applicable.extend(&custom.darklua_overrides);
+        defines.extend(custom.defines.clone());
+    }
+    applicable.extend(overrides.iter().filter(|(t, _)| t == target).map(|(_, o)| o));
+
+    if applicable.is_empty() && defines.is_empty() {
+        return Ok(builtin_config(target).to_string());
+    }
+
+    let mut config = parse(builtin_config(target))?;
+    for entry in applicable {
+        match entry {
+            DarkluaOverride::Replace(json) => config = parse(json)?,
+            DarkluaOverride::Extend(json) => extend(&mut config, parse(json)?)?,
+        }
+    }
+
+    if !defines.is_empty() {
+        let fields = config.as_object_mut().context("Darklua configuration must be a JSON object")?;
+        let rules = fields
+            .entry("rules")
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+            .context("Darklua configuration field 'rules' must be an array")?;
+        rules.splice(0..0, defines.iter().map(|(name, value)| value.to_rule(name)));
+    }
+
+    let resolved = serde_json::to_string(&config)?;
+    serde_json::from_str::<Configuration>(&resolved)
+        .with_context(|| format!("Invalid darklua configuration for target '{target}'"))?;
+    Ok(resolved)
+}
+
+/// Parses a configuration as a JSON object.
+fn parse(json: &str) -> Result<Value> {
+    let value: Value = serde_json::from_str(json).context("Failed to parse darklua configuration")?;
+    if !value.is_object() {
+        bail!("Darklua configuration must be a JSON object");
+    }
+    Ok(value)
+}
+
+/// Merges an extension into a configuration.
+fn extend(config: &mut Value, extension: Value) -> Result<()> {
+    let (Some(config), Value::Object(extension)) = (config.as_object_mut(), extension) else {
+        bail!("Darklua configuration must be a JSON object");
+    };
+
+    for (key, value) in extension {
+        if key != "rules" {
+            config.insert(key, value);
+            continue;
+        }
+
+        let Value::Array(rules) = value else {
+            bail!("Darklua configuration field 'rules' must be an array");
+        };
+        let existing = config
+            .entry("rules")
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+            .context("Darklua configuration field 'rules' must be an array")?;
+        merge_rules(existing, rules);
+    }
+
+    Ok(())
+}
+
+/// Appends rules, replacing existing rules of the same name in place.
+fn merge_rules(existing: &mut Vec<Value>, rules: Vec<Value>) {
+    for mut rule in rules {
+        let position = rule_name(&rule)
+            .and_then(|name| existing.iter().position(|r| rule_name(r) == Some(name)));
+
+        match position {
+            Some(index) => {
+                merge_globals(&existing[index], &mut rule);
+                existing[index] = rule;
+            }
+            None => existing.push(rule),
+        }
+    }
+}
+
+/// Returns the name of a rule written either as a string or as an object.
+fn rule_name(rule: &Value) -> Option<&str> {
+    match rule {
+        Value::String(name) => Some(name),
+        Value::Object(fields) => fields.get("rule")?.as_str(),
+        _ => None,
+    }
+}
+
+/// Keeps the `globals` of a replaced rule in its replacement.
+fn merge_globals(previous: &Value, rule: &mut Value) {
+    let Some(previous) = previous.get("globals").and_then(Value::as_array) else {
+        return;
+    };
+
+    if let Value::String(name) = rule {
+        *rule = serde_json::json!({ "rule": name.clone() });
+    }
+    let Some(fields) = rule.as_object_mut() else {
+        return;
+    };
+
+    let globals = fields
+        .entry("globals")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(globals) = globals.as_array_mut() {
+        for global in previous.iter().rev() {
+            if !globals.contains(global) {
+                globals.insert(0, global.clone());
+            }
+        }
+    }
+}
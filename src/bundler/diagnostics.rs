@@ -0,0 +1,227 @@
+//! Diagnostics about suspicious input that does not fail a build.
+//!
+//! The input is checked once it is loaded, and every target reports what it
+//! had to leave out while being generated. Diagnostics are warnings unless
+//! [`BuildConfig::with_deny_warnings`](super::BuildConfig::with_deny_warnings)
+//! promotes them to errors, which fail the affected targets.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::WeakDom;
+use serde::Serialize;
+
+use super::properties::database;
+
+/// How serious a diagnostic is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Reported, but the bundle is still written.
+    Warning,
+    /// A warning promoted by `--deny-warnings`; the bundle is not written.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What a diagnostic is about.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    /// The class is not in the reflection database, so its properties are
+    /// dropped and `Instance.new` fails at runtime.
+    UnknownClass,
+    /// Siblings share a name with a script, so indexing their parent by name
+    /// only finds one of them.
+    DuplicateName,
+    /// A script's source is kept as bytes that are not valid UTF-8, and is
+    /// decoded lossily.
+    LossySource,
+    /// A segment of the root path matched several siblings; only the first
+    /// was bundled.
+    AmbiguousRoot,
+    /// A reference property points outside the bundle and was left unset.
+    UnresolvedReference,
+    /// A required path leads to no instance.
+    UnresolvedRequire,
+    /// A required instance is not a `ModuleScript`.
+    NotAModule,
+    /// Modules require each other while loading, which always fails at
+    /// runtime; reported as an error.
+    RequireCycle,
+    /// A script reachable from a launched script has a require tree shaking
+    /// cannot follow, so no module was dropped.
+    DynamicRequire,
+    /// Darklua's output for a release target no longer ends with the calls
+    /// of the instance tree, so inlined scripts cannot be located in it.
+    UnmappedOutput,
+}
+
+impl DiagnosticCode {
+    /// Returns the code as shown in diagnostics and JSON output.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::UnknownClass => "unknown-class",
+            DiagnosticCode::DuplicateName => "duplicate-name",
+            DiagnosticCode::LossySource => "lossy-source",
+            DiagnosticCode::AmbiguousRoot => "ambiguous-root",
+            DiagnosticCode::UnresolvedReference => "unresolved-reference",
+            DiagnosticCode::UnresolvedRequire => "unresolved-require",
+            DiagnosticCode::NotAModule => "not-a-module",
+            DiagnosticCode::RequireCycle => "require-cycle",
+            DiagnosticCode::DynamicRequire => "dynamic-require",
+            DiagnosticCode::UnmappedOutput => "unmapped-output",
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found in the input or while generating a target.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// Dotted path of the instance concerned, as in source maps.
+    pub path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a warning about the instance at `path`.
+    pub fn warning(code: DiagnosticCode, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Returns whether the diagnostic fails its target.
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}] {}: {}", self.severity, self.code, self.path, self.message)
+    }
+}
+
+/// Promotes every warning to an error.
+pub(crate) fn deny_warnings(diagnostics: &mut [Diagnostic]) {
+    for diagnostic in diagnostics {
+        diagnostic.severity = Severity::Error;
+    }
+}
+
+/// Returns the error of a target whose diagnostics include errors.
+pub(crate) fn denied(diagnostics: &[Diagnostic]) -> Option<String> {
+    let count = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    (count > 0).then(|| format!("Denied {count} {}", if count == 1 { "warning" } else { "warnings" }))
+}
+
+/// Checks every instance that will be bundled, in bundle order.
+pub(crate) fn check_input(dom: &WeakDom) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let root = dom.root();
+    check_siblings(dom, root.children(), None, &mut diagnostics);
+
+    let mut stack: Vec<(Ref, String)> = root
+        .children()
+        .iter()
+        .rev()
+        .filter_map(|&referent| Some((referent, dom.get_by_ref(referent)?.name.clone())))
+        .collect();
+
+    while let Some((referent, path)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(referent) else {
+            continue;
+        };
+
+        if !database().classes.contains_key(instance.class.as_str()) {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::UnknownClass,
+                &path,
+                format!("Class '{}' is unknown; its properties are dropped and it cannot be created", instance.class),
+            ));
+        }
+        if is_lossy(instance.properties.get(&"Source".into())) {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::LossySource,
+                &path,
+                "Source is not valid UTF-8; invalid bytes were replaced with U+FFFD",
+            ));
+        }
+
+        check_siblings(dom, instance.children(), Some(&path), &mut diagnostics);
+        for &child in instance.children().iter().rev() {
+            if let Some(child_instance) = dom.get_by_ref(child) {
+                stack.push((child, format!("{path}.{}", child_instance.name)));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Returns whether a source holds invalid UTF-8, which is only possible for
+/// sources kept as bytes; a replacement character in a `String` source may
+/// well be intended.
+fn is_lossy(source: Option<&Variant>) -> bool {
+    match source {
+        Some(Variant::BinaryString(source)) => std::str::from_utf8(source.as_ref()).is_err(),
+        _ => false,
+    }
+}
+
+/// Reports names shared by several siblings, one of them a script.
+fn check_siblings(dom: &WeakDom, children: &[Ref], parent_path: Option<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names: Vec<(&str, usize, bool)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for instance in children.iter().filter_map(|&child| dom.get_by_ref(child)) {
+        let is_script = matches!(instance.class.as_str(), "Script" | "LocalScript" | "ModuleScript");
+        match index.get(instance.name.as_str()) {
+            Some(&i) => {
+                names[i].1 += 1;
+                names[i].2 |= is_script;
+            }
+            None => {
+                index.insert(&instance.name, names.len());
+                names.push((&instance.name, 1, is_script));
+            }
+        }
+    }
+
+    for (name, count, has_script) in names {
+        if count > 1 && has_script {
+            let path = match parent_path {
+                Some(parent) => format!("{parent}.{name}"),
+                None => name.to_string(),
+            };
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::DuplicateName,
+                path,
+                format!("{count} siblings are named '{name}', so indexing by name only finds the first"),
+            ));
+        }
+    }
+}
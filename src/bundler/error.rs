@@ -0,0 +1,89 @@
+//! Errors returned by the bundler library.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a build, or one of its targets, failed.
+///
+/// Messages are complete on their own, so the error converts into
+/// [`anyhow::Error`] with `?` without losing anything.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BundlerError {
+    /// The input path does not exist.
+    InputMissing { path: PathBuf },
+    /// The input could not be decoded into instances: an unrecognized or
+    /// corrupt model, or an invalid Rojo or roblox-ts project. Also returned
+    /// when a source map or size report read back from disk is invalid.
+    Decode { path: PathBuf, message: String },
+    /// The input decoded to no instances.
+    EmptyModel { path: PathBuf },
+    /// Darklua failed to process the bundle, or one of its scripts, given by
+    /// its dotted path when known.
+    Minify { module: Option<String>, message: String },
+    /// A file or directory could not be read or written; the cause is the
+    /// error's [`source`](std::error::Error::source).
+    Io { path: PathBuf, source: io::Error },
+    /// The build configuration is invalid or does not match the input, such
+    /// as an unknown entry point or root path.
+    InvalidConfig(String),
+    /// Diagnostics about the input are errors, warnings promoted by
+    /// [`BuildConfig::with_deny_warnings`](super::BuildConfig::with_deny_warnings).
+    InputErrors { count: usize },
+    /// A bug in the bundler.
+    Internal(String),
+}
+
+impl BundlerError {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), source }
+    }
+
+    /// Returns the message followed by its causes, for errors reported as
+    /// plain strings such as [`TargetResult::error_message`](super::TargetResult::error_message).
+    pub(crate) fn full_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        message
+    }
+
+    /// Attributes a darklua failure to a script, unless it already is.
+    pub(crate) fn in_module(self, module: &str) -> Self {
+        match self {
+            Self::Minify { module: None, message } => Self::Minify { module: Some(module.to_string()), message },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for BundlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InputMissing { path } => write!(f, "Input file does not exist: {}", path.display()),
+            Self::Decode { message, .. } => f.write_str(message),
+            Self::EmptyModel { .. } => write!(f, "Model file contains no instances"),
+            Self::Minify { module: Some(module), message } => write!(f, "Darklua failed on {module}: {message}"),
+            Self::Minify { module: None, message } => write!(f, "Darklua minification failed: {message}"),
+            Self::Io { path, .. } => write!(f, "Failed to access {}", path.display()),
+            Self::InvalidConfig(message) | Self::Internal(message) => f.write_str(message),
+            Self::InputErrors { count } => {
+                write!(f, "Found {count} {} in the input", if *count == 1 { "error" } else { "errors" })
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundlerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Input model loading, format detection and subtree selection.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+use super::diagnostics::{Diagnostic, DiagnosticCode};
+use super::error::BundlerError;
+use super::rbxts::{load_rbxts_project, rbxts_sources};
+use super::rojo::{load_project, project_file, project_sources};
+
+/// Magic bytes at the start of every binary model file.
+const BINARY_MAGIC: &[u8] = b"<roblox!";
+
+/// UTF-8 byte order mark, occasionally written before XML files.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Serialization format of an input model or place file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ModelFormat {
+    /// Binary model or place (`.rbxm`, `.rbxl`).
+    Binary,
+    /// XML model or place (`.rbxmx`, `.rbxlx`).
+    Xml,
+}
+
+impl fmt::Display for ModelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelFormat::Binary => write!(f, "binary"),
+            ModelFormat::Xml => write!(f, "XML"),
+        }
+    }
+}
+
+impl ModelFormat {
+    /// Detects the format from the file contents, falling back to the extension.
+    #[must_use]
+    pub fn detect(path: &Path, contents: &[u8]) -> Option<Self> {
+        Self::sniff(contents).or_else(|| Self::from_extension(path))
+    }
+
+    /// Detects the format from the leading bytes of a model file.
+    #[must_use]
+    pub fn sniff(contents: &[u8]) -> Option<Self> {
+        if contents.starts_with(BINARY_MAGIC) {
+            return Some(Self::Binary);
+        }
+
+        let text = contents.strip_prefix(UTF8_BOM).unwrap_or(contents);
+        let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+        let text = &text[start..];
+
+        if text.starts_with(b"<roblox") || text.starts_with(b"<?xml") {
+            Some(Self::Xml)
+        } else {
+            None
+        }
+    }
+
+    /// Detects the format from the file extension.
+    #[must_use]
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "rbxm" | "rbxl" => Some(Self::Binary),
+            "rbxmx" | "rbxlx" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+}
+
+/// How an input path is interpreted.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum InputKind {
+    /// A model or place file, or a Rojo project, detected from the path.
+    #[default]
+    Auto,
+    /// A roblox-ts project directory, read from its `out/`, `include/` and
+    /// `node_modules/@rbxts` directories.
+    RobloxTs,
+}
+
+/// Loads an input into a DOM.
+///
+/// With [`InputKind::Auto`], accepts binary or XML model and place files, as
+/// well as Rojo projects (a `*.project.json` file or a directory containing
+/// `default.project.json`).
+pub(crate) fn load_model(input: &Path, kind: InputKind) -> Result<WeakDom, BundlerError> {
+    let project = |e: anyhow::Error| BundlerError::Decode { path: input.to_path_buf(), message: format!("{e:#}") };
+    match kind {
+        InputKind::RobloxTs => load_rbxts_project(input).map_err(project),
+        InputKind::Auto => match project_file(input) {
+            Some(project_path) => load_project(&project_path).map_err(project),
+            None => read_model_file(input),
+        },
+    }
+}
+
+/// Returns the files and directories an input is loaded from.
+///
+/// # Errors
+///
+/// Returns an error if the input is a Rojo project that cannot be read.
+pub(crate) fn input_sources(input: &Path, kind: InputKind) -> Result<Vec<PathBuf>> {
+    Ok(match kind {
+        InputKind::RobloxTs => rbxts_sources(input),
+        InputKind::Auto => match project_file(input) {
+            Some(project) => project_sources(&project)?,
+            None => vec![input.to_path_buf()],
+        },
+    })
+}
+
+/// Reads and decodes a model or place file in either binary or XML format.
+pub(crate) fn read_model_file(input: &Path) -> Result<WeakDom, BundlerError> {
+    let contents = fs::read(input).map_err(|e| BundlerError::io(input, e))?;
+    decode_model_file(input, &contents)
+}
+
+/// Decodes the contents of a model or place file, detecting its format from
+/// the contents or the extension of `input`.
+pub(crate) fn decode_model_file(input: &Path, contents: &[u8]) -> Result<WeakDom, BundlerError> {
+    let Some(format) = ModelFormat::detect(input, contents) else {
+        return Err(BundlerError::Decode {
+            path: input.to_path_buf(),
+            message: format!("Unrecognized model format: {}", input.display()),
+        });
+    };
+
+    decode_model(contents, format).map_err(|e| BundlerError::Decode {
+        path: input.to_path_buf(),
+        message: format!("Failed to decode {format} model: {e:#}"),
+    })
+}
+
+/// Decodes model contents using the given format.
+fn decode_model(contents: &[u8], format: ModelFormat) -> Result<WeakDom> {
+    let dom = match format {
+        ModelFormat::Binary => rbx_binary::from_reader(contents)?,
+        ModelFormat::Xml => rbx_xml::from_reader_default(contents)?,
+    };
+    Ok(dom)
+}
+
+/// Moves the instance at a dotted path (e.g. `ReplicatedStorage.Client`) into
+/// its own DOM, making it the only top-level instance to bundle.
+///
+/// Top-level segments also match by class name so services can be found even
+/// when renamed, and a leading `game` segment is ignored. When a segment
+/// matches several siblings, the first is selected and a diagnostic returned.
+pub(crate) fn select_root(
+    mut dom: WeakDom,
+    root_path: &str,
+) -> Result<(WeakDom, Vec<Diagnostic>), BundlerError> {
+    let (referent, diagnostics) = resolve_path(&dom, root_path)?;
+
+    let mut subtree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let subtree_root = subtree.root_ref();
+    dom.transfer(referent, &mut subtree, subtree_root);
+
+    Ok((subtree, diagnostics))
+}
+
+/// Resolves a dotted instance path against the DOM root.
+fn resolve_path(dom: &WeakDom, root_path: &str) -> Result<(Ref, Vec<Diagnostic>), BundlerError> {
+    let mut segments = root_path.split('.').peekable();
+    if segments.peek() == Some(&"game") {
+        segments.next();
+    }
+
+    let mut current = dom.root_ref();
+    let mut resolved = String::new();
+    let mut diagnostics = Vec::new();
+
+    for segment in segments {
+        let parent = dom
+            .get_by_ref(current)
+            .ok_or_else(|| BundlerError::Internal("Referent missing from DOM tree".to_string()))?;
+        let is_top_level = current == dom.root_ref();
+
+        let matches: Vec<Ref> = parent
+            .children()
+            .iter()
+            .copied()
+            .filter(|&child| {
+                dom.get_by_ref(child).is_some_and(|inst| {
+                    inst.name == segment || (is_top_level && inst.class == segment)
+                })
+            })
+            .collect();
+
+        let Some(&child) = matches.first() else {
+            let available: Vec<&str> = parent
+                .children()
+                .iter()
+                .filter_map(|&child| dom.get_by_ref(child))
+                .map(|inst| inst.name.as_str())
+                .collect();
+            let location = if resolved.is_empty() { "the root" } else { resolved.as_str() };
+            return Err(BundlerError::InvalidConfig(format!(
+                "No instance named '{}' under {} while resolving root path '{}' (available: {})",
+                segment,
+                location,
+                root_path,
+                available.join(", ")
+            )));
+        };
+
+        if !resolved.is_empty() {
+            resolved.push('.');
+        }
+        resolved.push_str(segment);
+        current = child;
+
+        if matches.len() > 1 {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::AmbiguousRoot,
+                resolved.clone(),
+                format!("{} instances match root path segment '{segment}'; only the first is bundled", matches.len()),
+            ));
+        }
+    }
+
+    if current == dom.root_ref() {
+        return Err(BundlerError::InvalidConfig(format!(
+            "Root path '{root_path}' does not name an instance"
+        )));
+    }
+
+    Ok((current, diagnostics))
+}
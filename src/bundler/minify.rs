@@ -1,33 +1,192 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use anyhow::{anyhow, bail, Context, Result};
-use darklua_core::{Configuration, Options, Resources};
+use std::str::FromStr;
+
+use darklua_core::{Configuration, GeneratorParameters, Options, Resources};
+
+use super::content_hash;
+use super::error::BundlerError;
 
 /// Minifies Luau source code using darklua with the provided configuration.
-pub fn minify(text: &str, config_content: &str) -> Result<String> {
-    let config: Configuration =
-        serde_json::from_str(config_content).context("Failed to parse darklua configuration")?;
+///
+/// # Errors
+///
+/// Returns [`BundlerError::Minify`] if the configuration is invalid or the
+/// source cannot be parsed; the error names no module.
+pub fn minify(text: &str, config_content: &str) -> Result<String, BundlerError> {
+    let mut outputs = process(&[text], config_content)?;
+    Ok(outputs.remove(0))
+}
+
+/// Runs darklua over several sources at once, so the configuration is only
+/// parsed once.
+fn process(texts: &[&str], config_content: &str) -> Result<Vec<String>, BundlerError> {
+    let config: Configuration = serde_json::from_str(config_content)
+        .map_err(|e| minify_error(format!("invalid darklua configuration: {e}")))?;
 
     let resources = Resources::from_memory();
-    let temp_file = "temp.lua";
-
-    resources
-        .write(temp_file, text)
-        .map_err(|e| anyhow!("Failed to write to darklua resources: {:?}", e))?;
-
-    let options = Options::new(Path::new(temp_file)).with_configuration(config);
-
-    match darklua_core::process(&resources, options) {
-        Ok(_) => {
-            let result = resources
-                .get(temp_file)
-                .map_err(|e| anyhow!("Failed to retrieve minified content: {:?}", e));
-            
-            // Clean up temp file to prevent memory accumulation
-            let _ = resources.remove(temp_file);
-            
-            result
+    let root = Path::new("src");
+    let files: Vec<PathBuf> = (0..texts.len()).map(|i| root.join(format!("{i}.lua"))).collect();
+
+    for (file, text) in files.iter().zip(texts) {
+        resources
+            .write(file, text)
+            .map_err(|e| minify_error(format!("failed to write to darklua resources: {e:?}")))?;
+    }
+
+    // A single source is processed as a file, more as the directory holding them
+    let input = if files.len() == 1 { files[0].as_path() } else { root };
+    let options = Options::new(input).with_configuration(config);
+
+    // Files that fail to parse are reported by the worker tree, not by `process`
+    darklua_core::process(&resources, options)
+        .map_err(|e| minify_error(e.to_string()))?
+        .result()
+        .map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            minify_error(messages.join("; "))
+        })?;
+
+    files
+        .iter()
+        .map(|file| {
+            resources
+                .get(file)
+                .map_err(|e| minify_error(format!("failed to retrieve minified content: {e:?}")))
+        })
+        .collect()
+}
+
+/// Returns whether a darklua configuration keeps every token on its line,
+/// which darklua does unless another generator is configured.
+pub(crate) fn retains_lines(config_content: &str) -> bool {
+    let generator = serde_json::from_str::<serde_json::Value>(config_content)
+        .ok()
+        .and_then(|mut config| config.get_mut("generator").map(serde_json::Value::take));
+    let generator = match generator {
+        None => Some(GeneratorParameters::default()),
+        Some(serde_json::Value::String(name)) => GeneratorParameters::from_str(&name).ok(),
+        Some(value) => serde_json::from_value(value).ok(),
+    };
+    generator == Some(GeneratorParameters::RetainLines)
+}
+
+fn minify_error(message: String) -> BundlerError {
+    BundlerError::Minify { module: None, message }
+}
+
+/// Number of builds a darklua output is kept for without being used.
+const MAX_UNUSED_BUILDS: u64 = 8;
+
+/// A darklua output and the last build that used it.
+struct CachedOutput {
+    text: String,
+    generation: u64,
+}
+
+/// Identifies a source by its [`content_hash`] and size, so the cache does
+/// not keep a copy of every source next to its output.
+type SourceKey = (String, usize);
+
+fn source_key(text: &str) -> SourceKey {
+    (content_hash(text), text.len())
+}
+
+/// Darklua output of every source processed by a session, by configuration
+/// and source.
+///
+/// Outputs unused for [`MAX_UNUSED_BUILDS`] builds are dropped, so editing
+/// scripts does not grow the cache forever.
+#[derive(Default)]
+pub(crate) struct MinifyCache {
+    outputs: Mutex<HashMap<String, HashMap<SourceKey, CachedOutput>>>,
+    generation: u64,
+}
+
+impl MinifyCache {
+    /// Starts a build, dropping the outputs no recent build used.
+    pub(crate) fn next_build(&mut self) {
+        self.generation += 1;
+        let generation = self.generation;
+        if let Ok(configs) = self.outputs.get_mut() {
+            for outputs in configs.values_mut() {
+                outputs.retain(|_, output| generation - output.generation <= MAX_UNUSED_BUILDS);
+            }
+            configs.retain(|_, outputs| !outputs.is_empty());
+        }
+    }
+
+    /// Returns a minifier applying a darklua configuration through the cache.
+    pub(crate) fn minifier<'a>(&'a self, config: &'a str) -> Minifier<'a> {
+        Minifier { cache: self, config, retains_lines: retains_lines(config) }
+    }
+
+    fn get(&self, config: &str, text: &str) -> Option<String> {
+        let mut configs = self.outputs.lock().ok()?;
+        let output = configs.get_mut(config)?.get_mut(&source_key(text))?;
+        output.generation = self.generation;
+        Some(output.text.clone())
+    }
+
+    fn insert(&self, config: &str, text: &str, output: String) {
+        if let Ok(mut configs) = self.outputs.lock() {
+            let output = CachedOutput { text: output, generation: self.generation };
+            configs.entry(config.to_string()).or_default().insert(source_key(text), output);
+        }
+    }
+}
+
+/// Applies a darklua configuration, reusing the output of sources it has
+/// already processed.
+///
+/// Darklua consumes the `Configuration` it runs with, and it is not `Clone`,
+/// so the configuration is parsed again for every run; cache hits do not run
+/// darklua at all, and [`Minifier::prefetch`] shares one run between every
+/// missing source.
+#[derive(Clone)]
+pub(crate) struct Minifier<'a> {
+    cache: &'a MinifyCache,
+    config: &'a str,
+    retains_lines: bool,
+}
+
+impl Minifier<'_> {
+    /// Returns whether the configuration keeps every token on its line.
+    pub(crate) fn retains_lines(&self) -> bool {
+        self.retains_lines
+    }
+
+    /// Minifies a source, or returns its cached output.
+    pub(crate) fn minify(&self, text: &str) -> Result<String, BundlerError> {
+        if let Some(output) = self.cache.get(self.config, text) {
+            return Ok(output);
+        }
+        let output = minify(text, self.config)?;
+        self.cache.insert(self.config, text, output.clone());
+        Ok(output)
+    }
+
+    /// Processes every source missing from the cache in a single darklua run.
+    ///
+    /// Nothing is cached if any source fails, leaving [`Minifier::minify`] to
+    /// report the error of the source at fault.
+    pub(crate) fn prefetch(&self, texts: &[String]) {
+        let mut seen = HashSet::new();
+        let missing: Vec<&str> = texts
+            .iter()
+            .map(String::as_str)
+            .filter(|text| seen.insert(*text) && self.cache.get(self.config, text).is_none())
+            .collect();
+        if missing.len() < 2 {
+            return;
+        }
+
+        if let Ok(outputs) = process(&missing, self.config) {
+            for (text, output) in missing.into_iter().zip(outputs) {
+                self.cache.insert(self.config, text, output);
+            }
         }
-        Err(e) => bail!("Darklua minification failed: {}", e),
     }
 }
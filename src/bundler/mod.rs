@@ -1,29 +1,67 @@
-//! Core bundler functionality for compiling `.rbxm` models into Luau scripts.
+//! Core bundler functionality for compiling Roblox models and places into Luau scripts.
 //!
 //! This module provides the main [`build`] function and re-exports commonly used types.
 
+pub mod analyzer;
+pub mod darklua;
+pub mod diagnostics;
+pub mod error;
 pub mod escape;
+pub mod input;
 pub mod minify;
+pub mod project;
+pub mod properties;
+pub mod rbxts;
+pub mod relocate;
+pub mod report;
+pub mod requires;
+pub mod rojo;
+pub mod session;
+pub mod shake;
+pub mod sourcemap;
 pub mod traverse;
 pub mod types;
+pub mod watch;
 pub mod writer;
 
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::fs;
-use std::io::BufReader;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Context, Result};
 use rayon::prelude::*;
+use rbx_dom_weak::types::Ref;
 use rbx_dom_weak::WeakDom;
 
 use crate::assets;
-use minify::minify;
-use traverse::process_instance;
+use analyzer::render_analyzer;
+use diagnostics::{check_input, denied, deny_warnings};
+use input::{decode_model_file, load_model, select_root};
+use minify::{MinifyCache, Minifier};
+use relocate::relocate;
+use report::BundleLayout;
+use session::BuildCaches;
+use shake::tree_shake;
+use traverse::TreeWriter;
+use writer::ScriptEmbedding;
 
 // Re-export public types for library consumers
-pub use types::{BuildConfig, BuildResult, Mode, Target, TargetResult, PKG_NAME, PKG_VERSION};
+pub use darklua::{DarkluaOverride, DefineValue};
+pub use diagnostics::{Diagnostic, DiagnosticCode, Severity};
+pub use error::BundlerError;
+pub use input::{InputKind, ModelFormat};
+pub use project::{CustomTargetConfig, ProjectConfig, PROJECT_FILE_NAME};
+pub use report::{ModuleSize, SizeReport};
+pub use requires::{RequireGraph, RequireIssue};
+pub use session::Bundler;
+pub use shake::{ShakenModule, TreeShakeReport};
+pub use sourcemap::{ModuleMapping, SourceMap};
+pub use watch::{WatchedPath, Watcher};
+pub use types::{
+    BuildConfig, BuildResult, CustomTarget, MemoryBuildResult, Mode, RunContext, StartPolicy, Target,
+    TargetBundle, TargetResult, TreeShaking, PKG_NAME, PKG_VERSION,
+};
 
 // Internal re-exports for submodules
 pub(crate) use types::BundlerContext;
@@ -39,11 +77,20 @@ pub(crate) use types::BundlerContext;
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - No targets are specified
-/// - The input file does not exist
-/// - The model file cannot be parsed
-/// - The output directory cannot be created
+/// Returns a [`BundlerError`] if:
+/// - No targets are specified, or two targets share an output file name
+///   ([`BundlerError::InvalidConfig`])
+/// - The input file does not exist ([`BundlerError::InputMissing`])
+/// - A user-supplied darklua configuration is invalid ([`BundlerError::InvalidConfig`])
+/// - The model file cannot be parsed ([`BundlerError::Decode`]) or is empty
+///   ([`BundlerError::EmptyModel`])
+/// - The root path does not resolve to an instance, or an entry point does
+///   not exist ([`BundlerError::InvalidConfig`])
+/// - Tree shaking is enabled but no script is launched ([`BundlerError::InvalidConfig`])
+/// - The output directory cannot be created ([`BundlerError::Io`])
+///
+/// Targets that fail on their own, for instance when darklua cannot parse a
+/// script, are reported in their [`TargetResult`] instead.
 ///
 /// # Example
 ///
@@ -59,87 +106,336 @@ pub(crate) use types::BundlerContext;
 /// let result = build(&config).expect("Build failed");
 /// assert!(result.is_success());
 /// ```
-pub fn build(config: &BuildConfig) -> Result<BuildResult> {
+pub fn build(config: &BuildConfig) -> Result<BuildResult, BundlerError> {
     let start_time = Instant::now();
+    let dom = load_input(config)?;
+    let rendered = render_dom(dom, config, &mut BuildCaches::default())?;
+    write_build(config, rendered, start_time)
+}
 
-    validate_config(config)?;
+/// Build one or more targets without writing anything to disk.
+///
+/// Each bundle is generated exactly as [`build`] would write it, along with
+/// its source map. The output directory is not used.
+///
+/// # Errors
+///
+/// Returns an error in the same cases as [`build`], except that no output
+/// directory is created.
+pub fn build_in_memory(config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+    let dom = load_input(config)?;
+    build_from_dom(dom, config)
+}
 
-    let dom = load_model(&config.input)?;
-    let stem = extract_stem(&config.input);
-    let targets = prepare_targets(config, &stem);
+/// Build one or more targets from a DOM that is already loaded, without
+/// touching the filesystem.
+///
+/// The DOM's top-level instances are bundled as if they had been read from
+/// `config.input`, which is not read: its file stem still names the bundles
+/// and it fills the `{{INPUT}}` placeholder of headers. The input kind and
+/// output directory are not used.
+///
+/// # Errors
+///
+/// Returns an error in the same cases as [`build_in_memory`], except those
+/// about reading the input, and [`BundlerError::EmptyModel`] if the DOM has
+/// no top-level instance.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+/// use rbxts_bundler::bundler::{build_from_dom, BuildConfig};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+/// dom.insert(dom.root_ref(), InstanceBuilder::new("Folder").with_name("Client"));
+///
+/// let config = BuildConfig::new(PathBuf::from("client.rbxm"), PathBuf::new());
+/// let result = build_from_dom(dom, &config).expect("Build failed");
+/// println!("{}", result.bundles[0].source);
+/// ```
+pub fn build_from_dom(dom: WeakDom, config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+    let start_time = Instant::now();
+    let rendered = render_dom(dom, config, &mut BuildCaches::default())?;
+    Ok(memory_result(config, rendered, start_time))
+}
 
-    fs::create_dir_all(&config.out_dir).context("Failed to create output directory")?;
+/// Build one or more targets from the contents of a binary or XML model or
+/// place file, without touching the filesystem.
+///
+/// The format is detected from the contents, falling back to the extension
+/// of `config.input`, which is otherwise used as in [`build_from_dom`].
+///
+/// # Errors
+///
+/// Returns [`BundlerError::Decode`] if the contents are not a model, and
+/// otherwise errors in the same cases as [`build_from_dom`].
+pub fn build_from_bytes(contents: &[u8], config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+    let dom = decode_model_file(&config.input, contents)?;
+    build_from_dom(dom, config)
+}
 
-    let outcomes = build_targets_parallel(&targets, &dom, &config.input, config.header_content.as_ref())?;
+// ─────────────────────────────────────────────────────────────────────────────
+// Build Pipeline
+// ─────────────────────────────────────────────────────────────────────────────
 
-    let target_results = outcomes
-        .into_iter()
-        .map(|(idx, res)| {
-            let spec = &targets[idx];
-            TargetResult {
-                target: spec.target,
-                output_file: spec.output.clone(),
-                success: res.is_ok(),
-                error_message: res.err().map(|e| format!("{e:#}")),
-            }
-        })
+/// A prepared build and the bundle of each of its targets.
+struct RenderedBuild {
+    prepared: PreparedBuild,
+    bundles: Vec<TargetBundle>,
+}
+
+/// Prepares a loaded model and renders every target in memory.
+fn render_dom(dom: WeakDom, config: &BuildConfig, caches: &mut BuildCaches) -> Result<RenderedBuild, BundlerError> {
+    let (pool, minify_cache) = caches.start(&dom)?;
+    let prepared = prepare_build(dom, config, pool)?;
+    let bundles = render_bundles(&prepared, config, pool, minify_cache)?;
+    Ok(RenderedBuild { prepared, bundles })
+}
+
+/// Writes every rendered target into the output directory.
+fn write_build(config: &BuildConfig, rendered: RenderedBuild, start_time: Instant) -> Result<BuildResult, BundlerError> {
+    let RenderedBuild { prepared, bundles } = rendered;
+    fs::create_dir_all(&config.out_dir).map_err(|e| BundlerError::io(&config.out_dir, e))?;
+
+    let target_results = prepared
+        .targets
+        .iter()
+        .zip(bundles)
+        .map(|(spec, bundle)| write_bundle(spec, bundle))
         .collect();
 
     Ok(BuildResult {
         input_path: config.input.clone(),
         target_results,
+        diagnostics: prepared.diagnostics,
+        tree_shaking: prepared.tree_shaking,
         duration: start_time.elapsed(),
     })
 }
 
+/// Returns the rendered targets as the result of an in-memory build.
+fn memory_result(config: &BuildConfig, rendered: RenderedBuild, start_time: Instant) -> MemoryBuildResult {
+    MemoryBuildResult {
+        input_path: config.input.clone(),
+        bundles: rendered.bundles,
+        diagnostics: rendered.prepared.diagnostics,
+        tree_shaking: rendered.prepared.tree_shaking,
+        duration: start_time.elapsed(),
+    }
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Validation & Setup
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// A loaded model, ready to be bundled into every target.
+struct PreparedBuild {
+    dom: WeakDom,
+    targets: Vec<TargetSpec>,
+    /// Modules bundled without their source by tree shaking.
+    stripped: HashSet<Ref>,
+    tree_shaking: Option<TreeShakeReport>,
+    /// Diagnostics about the input, shared by every target.
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Reads the configured input into a DOM.
+fn load_input(config: &BuildConfig) -> Result<WeakDom, BundlerError> {
+    if !config.input.exists() {
+        return Err(BundlerError::InputMissing { path: config.input.clone() });
+    }
+    load_model(&config.input, config.input_kind)
+}
+
+/// Validates the configuration against a loaded model and prepares every target.
+fn prepare_build(
+    mut dom: WeakDom,
+    config: &BuildConfig,
+    pool: &rayon::ThreadPool,
+) -> Result<PreparedBuild, BundlerError> {
+    validate_config(config)?;
+    if dom.root().children().is_empty() {
+        return Err(BundlerError::EmptyModel { path: config.input.clone() });
+    }
+
+    let darklua_configs = config
+        .targets
+        .iter()
+        .map(|target| {
+            darklua::resolve_config(target, &config.darklua_overrides, &config.defines)
+                .map_err(|e| BundlerError::InvalidConfig(format!("{e:#}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut diagnostics = Vec::new();
+    if let Some(root_path) = &config.root_path {
+        let (subtree, root_diagnostics) = select_root(dom, root_path)?;
+        dom = subtree;
+        diagnostics = root_diagnostics;
+    }
+    validate_roots(&dom, &config.entry_points)?;
+
+    let mut stripped = HashSet::new();
+    let mut tree_shaking = None;
+    let shake = config.tree_shaking != TreeShaking::Off;
+    if config.check_requires || shake {
+        let graph = RequireGraph::analyze_in(&dom, pool);
+        if config.check_requires {
+            diagnostics.extend(require_diagnostics(&graph));
+        }
+        if shake {
+            let (shaken, report) = tree_shake(&mut dom, &graph, config, &mut diagnostics)?;
+            stripped = shaken;
+            tree_shaking = Some(report);
+        }
+    }
+
+    diagnostics.extend(check_input(&dom));
+    if config.deny_warnings {
+        deny_warnings(&mut diagnostics);
+    }
+
+    let stem = extract_stem(&config.input);
+    let targets = prepare_targets(config, &stem, darklua_configs);
+
+    Ok(PreparedBuild { dom, targets, stripped, tree_shaking, diagnostics })
+}
+
 /// Validates the build configuration before processing.
-fn validate_config(config: &BuildConfig) -> Result<()> {
+fn validate_config(config: &BuildConfig) -> Result<(), BundlerError> {
     if config.targets.is_empty() {
-        bail!("At least one target must be specified");
+        return Err(BundlerError::InvalidConfig("At least one target must be specified".to_string()));
     }
-    if !config.input.exists() {
-        bail!("Input file does not exist: {}", config.input.display());
+    for target in &config.targets {
+        let Target::Custom(custom) = target else {
+            continue;
+        };
+        if Target::ALL.iter().any(|builtin| builtin.to_string() == custom.name) {
+            return Err(BundlerError::InvalidConfig(format!(
+                "Custom target '{}' has the name of a built-in target",
+                custom.name
+            )));
+        }
+        if config.targets.iter().any(|other| {
+            matches!(other, Target::Custom(other) if other.name == custom.name && other != custom)
+        }) {
+            return Err(BundlerError::InvalidConfig(format!(
+                "Several custom targets are named '{}'",
+                custom.name
+            )));
+        }
+    }
+
+    let stem = extract_stem(&config.input);
+    let mut file_names: Vec<String> = Vec::with_capacity(config.targets.len());
+    for target in &config.targets {
+        let file_name = output_file_name(config, &stem, target);
+        if file_names.contains(&file_name) {
+            return Err(BundlerError::InvalidConfig(format!(
+                "Several targets would be written to '{file_name}'; include {{target}} or {{suffix}} in the output name"
+            )));
+        }
+        file_names.push(file_name);
     }
     Ok(())
 }
 
-/// Loads and parses the `.rbxm` model file.
-fn load_model(input: &Path) -> Result<WeakDom> {
-    let file = fs::File::open(input).context("Failed to open input file")?;
-    let dom = rbx_binary::from_reader(BufReader::new(file)).context("Failed to decode model")?;
+/// Validates the configured entry points against the top-level instances.
+fn validate_roots(dom: &WeakDom, entry_points: &[String]) -> Result<(), BundlerError> {
+    let mut names: Vec<&str> = Vec::with_capacity(dom.root().children().len());
 
-    if dom.root().children().is_empty() {
-        bail!("Model file contains no instances");
+    for &referent in dom.root().children() {
+        let instance = dom
+            .get_by_ref(referent)
+            .ok_or_else(|| BundlerError::Internal("Root child missing from DOM tree".to_string()))?;
+        if !names.contains(&instance.name.as_str()) {
+            names.push(&instance.name);
+        }
     }
 
-    Ok(dom)
+    for entry in entry_points {
+        if !names.contains(&entry.as_str()) {
+            return Err(BundlerError::InvalidConfig(format!(
+                "Entry point '{}' is not a top-level instance (available: {})",
+                entry,
+                names.join(", ")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reports the issues of the static require graph of the bundled scripts.
+///
+/// Every issue is a warning: a cycle only fails if the bundle actually
+/// loads its modules in that order, which the static graph cannot tell.
+fn require_diagnostics(graph: &RequireGraph) -> impl Iterator<Item = Diagnostic> + '_ {
+    graph.issues().iter().map(|issue| match issue {
+        RequireIssue::Cycle { chain } => Diagnostic::warning(
+            DiagnosticCode::RequireCycle,
+            chain.first().map_or("", String::as_str),
+            format!("Circular require when loading: {}", chain.join(" → ")),
+        ),
+        RequireIssue::Unresolved { script, line, path, reason } => Diagnostic::warning(
+            DiagnosticCode::UnresolvedRequire,
+            script,
+            format!("The require of {path} on line {line} cannot be resolved: {reason}"),
+        ),
+        RequireIssue::NotAModule { script, line, target, class } => Diagnostic::warning(
+            DiagnosticCode::NotAModule,
+            script,
+            format!("The require on line {line} targets {target}, which is a {class}, not a ModuleScript"),
+        ),
+    })
 }
 
 /// Extracts the file stem from the input path for naming output files.
+///
+/// Rojo projects drop their `.project` suffix, so `default.project.json`
+/// produces `default.debug.lua`.
 fn extract_stem(input: &Path) -> String {
-    input
+    let stem = input
         .file_stem()
         .and_then(|s| s.to_str())
-        .unwrap_or("bundle")
-        .to_string()
+        .unwrap_or("bundle");
+    stem.strip_suffix(".project").unwrap_or(stem).to_string()
 }
 
-/// Prepares target specifications from the build configuration.
-fn prepare_targets(config: &BuildConfig, stem: &str) -> Vec<TargetSpec> {
+/// Default output file name template, see [`BuildConfig::with_output_name`].
+const DEFAULT_OUTPUT_NAME: &str = "{name}.{suffix}.lua";
+
+/// Expands the output file name template for a target.
+fn output_file_name(config: &BuildConfig, stem: &str, target: &Target) -> String {
+    config
+        .output_name
+        .as_deref()
+        .unwrap_or(DEFAULT_OUTPUT_NAME)
+        .replace("{name}", stem)
+        .replace("{target}", &target.to_string())
+        .replace("{suffix}", target.file_suffix())
+}
+
+/// Prepares target specifications from the build configuration and the
+/// resolved darklua configuration of each target.
+fn prepare_targets(config: &BuildConfig, stem: &str, darklua_configs: Vec<String>) -> Vec<TargetSpec> {
     config
         .targets
         .iter()
-        .map(|target| {
-            let filename = format!("{}.{}.lua", stem, target.file_suffix());
+        .zip(darklua_configs)
+        .map(|(target, darklua_config)| {
+            let filename = output_file_name(config, stem, target);
             TargetSpec {
-                target: *target,
+                target: target.clone(),
                 mode: target.mode(),
-                compat: target.compat(),
-                output: config.out_dir.join(filename),
+                loadstring: target.uses_loadstring(),
+                darklua_config,
+                output: config.out_dir.join(&filename),
+                source_map: config.out_dir.join(format!("{filename}.map")),
+                size_report: config.out_dir.join(format!("{filename}.size.json")),
+                analyzer: config.out_dir.join(format!("{filename}.analyzer.html")),
             }
         })
         .collect()
@@ -154,127 +450,351 @@ fn prepare_targets(config: &BuildConfig, stem: &str) -> Vec<TargetSpec> {
 struct TargetSpec {
     target: Target,
     mode: Mode,
-    compat: bool,
+    loadstring: bool,
+    darklua_config: String,
     output: PathBuf,
+    source_map: PathBuf,
+    size_report: PathBuf,
+    analyzer: PathBuf,
 }
 
-/// Builds all targets in parallel using a custom thread pool.
-fn build_targets_parallel(
-    targets: &[TargetSpec],
-    dom: &WeakDom,
-    input_path: &Path,
-    header: Option<&String>,
-) -> Result<Vec<(usize, Result<()>)>> {
-    let stack_size = estimate_thread_stack_size(dom);
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(targets.len().max(1))
-        .stack_size(stack_size)
-        .build()
-        .context("Failed to build thread pool")?;
+impl TargetSpec {
+    /// Returns the file name of the output.
+    fn file_name(&self) -> String {
+        self.output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
 
-    let results = pool.install(|| {
-        targets
+/// Index of a target, the outcome of building it, and how long that took.
+type TargetOutcome<T> = (usize, Result<T, BundlerError>, Duration);
+
+/// Builds all targets in parallel on the build's thread pool, timing each.
+fn build_targets_parallel<T: Send>(
+    prepared: &PreparedBuild,
+    pool: &rayon::ThreadPool,
+    build_target: impl Fn(&TargetSpec) -> Result<T, BundlerError> + Sync,
+) -> Vec<TargetOutcome<T>> {
+    pool.install(|| {
+        prepared
+            .targets
             .par_iter()
             .enumerate()
-            .map(|(idx, spec)| (idx, build_single_target(dom, input_path, header, spec)))
+            .map(|(idx, spec)| {
+                let start_time = Instant::now();
+                let result = build_target(spec);
+                (idx, result, start_time.elapsed())
+            })
             .collect()
+    })
+}
+
+/// Builds a thread pool whose stacks are large enough to process the DOM's
+/// scripts; `num_threads` of 0 uses rayon's default.
+pub(crate) fn thread_pool(dom: &WeakDom, num_threads: usize) -> Result<rayon::ThreadPool, BundlerError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .stack_size(estimate_thread_stack_size(dom))
+        .build()
+        .map_err(|e| BundlerError::Internal(format!("Failed to build thread pool: {e}")))
+}
+
+/// Returns a hash of a bundle, stable across runs and Rust versions.
+///
+/// FNV-1a is enough to tell builds apart, so servers can use it as an `ETag`
+/// and CI can compare builds without reading them.
+pub(crate) fn content_hash(content: impl AsRef<[u8]>) -> String {
+    let hash = content.as_ref().iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
     });
+    format!("{hash:016x}")
+}
+
+/// Renders every target in parallel, keeping failed targets with their error.
+fn render_bundles(
+    prepared: &PreparedBuild,
+    config: &BuildConfig,
+    pool: &rayon::ThreadPool,
+    minify_cache: &MinifyCache,
+) -> Result<Vec<TargetBundle>, BundlerError> {
+    let outcomes = build_targets_parallel(prepared, pool, |spec| render_target(prepared, config, spec, minify_cache));
+
+    let bundles = outcomes
+        .into_iter()
+        .map(|(idx, res, duration)| {
+            let spec = &prepared.targets[idx];
+            let file_name = spec.file_name();
+            let error_message = match &res {
+                Ok(rendered) => denied(&rendered.diagnostics),
+                Err(e) => Some(e.full_message()),
+            };
+            match res {
+                Ok(rendered) if error_message.is_none() => TargetBundle {
+                    target: spec.target.clone(),
+                    file_name,
+                    source: rendered.source,
+                    source_map: rendered.source_map,
+                    size_report: rendered.size_report,
+                    analyzer: rendered.analyzer,
+                    diagnostics: rendered.diagnostics,
+                    success: true,
+                    error_message: None,
+                    duration,
+                },
+                res => TargetBundle {
+                    target: spec.target.clone(),
+                    source_map: SourceMap::new(file_name.clone(), Vec::new()),
+                    file_name,
+                    source: String::new(),
+                    size_report: None,
+                    analyzer: None,
+                    diagnostics: res.map(|rendered| rendered.diagnostics).unwrap_or_default(),
+                    success: false,
+                    error_message,
+                    duration,
+                },
+            }
+        })
+        .collect();
 
-    Ok(results)
+    Ok(bundles)
 }
 
-/// Builds a single target and writes the output file.
-fn build_single_target(
-    dom: &WeakDom,
-    input_path: &Path,
-    header_content: Option<&String>,
+/// Writes a rendered target's output file, its source map, and its size
+/// report and analyzer page if enabled.
+///
+/// Nothing is written for a failed target, and a failed write fails the target.
+fn write_bundle(spec: &TargetSpec, bundle: TargetBundle) -> TargetResult {
+    let start_time = Instant::now();
+    let written = if bundle.success { write_files(spec, &bundle).map(Some) } else { Ok(None) };
+    let (analyzer_file, error_message) = match written {
+        Ok(Some(analyzer_file)) => (analyzer_file, None),
+        Ok(None) => (None, bundle.error_message),
+        Err(e) => (None, Some(e.full_message())),
+    };
+    let success = error_message.is_none();
+
+    TargetResult {
+        target: bundle.target,
+        output_file: spec.output.clone(),
+        source_map_file: spec.source_map.clone(),
+        success,
+        error_message,
+        output_bytes: if success { bundle.source.len() } else { 0 },
+        hash: success.then(|| content_hash(&bundle.source)),
+        size_report: bundle.size_report.filter(|_| success),
+        analyzer_file,
+        diagnostics: bundle.diagnostics,
+        duration: bundle.duration + start_time.elapsed(),
+    }
+}
+
+/// Writes the files of a successful target, returning the analyzer page's path.
+fn write_files(spec: &TargetSpec, bundle: &TargetBundle) -> Result<Option<PathBuf>, BundlerError> {
+    if let Some(parent) = spec.output.parent() {
+        fs::create_dir_all(parent).map_err(|e| BundlerError::io(parent, e))?;
+    }
+
+    fs::write(&spec.output, &bundle.source).map_err(|e| BundlerError::io(&spec.output, e))?;
+    bundle.source_map.save(&spec.source_map)?;
+    if let Some(size_report) = &bundle.size_report {
+        size_report.save(&spec.size_report)?;
+    }
+    match &bundle.analyzer {
+        Some(page) => {
+            fs::write(&spec.analyzer, page).map_err(|e| BundlerError::io(&spec.analyzer, e))?;
+            Ok(Some(spec.analyzer.clone()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// A target's final source, with its source map, size report, analyzer page
+/// and the diagnostics raised while generating it.
+struct RenderedTarget {
+    source: String,
+    source_map: SourceMap,
+    size_report: Option<SizeReport>,
+    analyzer: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Builds a single target, returning the final source and what describes it.
+///
+/// Fails if the input has denied warnings.
+fn render_target(
+    prepared: &PreparedBuild,
+    config: &BuildConfig,
     target: &TargetSpec,
-) -> Result<()> {
-    let ctx = BundlerContext::new(target.mode, input_path);
-    let source = generate_bundle(dom, &ctx, header_content, target)?;
+    minify_cache: &MinifyCache,
+) -> Result<RenderedTarget, BundlerError> {
+    let denied_count = prepared.diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    if denied_count > 0 {
+        return Err(BundlerError::InputErrors { count: denied_count });
+    }
+
+    let ctx = BundlerContext::new(target.mode, &config.input);
+    let header_content = config.header_content.as_ref();
+    let darklua = minify_cache.minifier(&target.darklua_config);
+
+    // Dev builds apply their darklua config to each script, all in one run
+    let script_darklua = (target.mode == Mode::Development).then(|| {
+        darklua.prefetch(&bundled_sources(prepared));
+        darklua.clone()
+    });
+    let GeneratedBundle { source, mut modules, mut layout, mut diagnostics } =
+        generate_bundle(prepared, &ctx, config, target, script_darklua)?;
+    if config.deny_warnings {
+        deny_warnings(&mut diagnostics);
+    }
 
+    let mut mapped = true;
     let final_source = if target.mode == Mode::Production {
-        let config = if target.compat {
-            assets::DARKLUA_REL_COMPAT
-        } else {
-            assets::DARKLUA_REL
-        };
-        let minified = minify(&source, config)?;
+        let minified = darklua.minify(&source).map_err(|e| attribute_to_module(e, &modules))?;
 
         // Prepend header after minification to preserve it
         let header_raw = header_content.map_or(assets::FILE_HEADER, String::as_str);
         let header = ctx.apply_templates(header_raw);
-        format!("{header}\n{minified}")
+
+        let final_source = format!("{header}\n{minified}");
+        let relocated = relocate(&mut layout, &mut modules, &final_source, header.len() + 1, darklua.retains_lines());
+        if let Err(reason) = relocated {
+            // Without the tree's locations, nothing can be attributed to a script
+            modules.retain(|module| module.chunk.is_some());
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::UnmappedOutput,
+                target.target.to_string(),
+                format!("{reason}, so inlined scripts are missing from the source map, size report and analyzer"),
+            ));
+            mapped = false;
+        }
+        final_source
     } else {
         source
     };
 
-    if let Some(parent) = target.output.parent() {
-        fs::create_dir_all(parent).context("Failed to create output directory")?;
-    }
+    let file_name = target.file_name();
+    let size_report = (config.size_report && mapped).then(|| layout.measure(file_name.clone(), &final_source));
+    let analyzer = if config.analyzer && mapped {
+        let target_name = target.target.to_string();
+        Some(render_analyzer(&layout, &final_source, &prepared.dom, &target_name, &file_name)?)
+    } else {
+        None
+    };
+    Ok(RenderedTarget {
+        source: final_source,
+        source_map: SourceMap::new(file_name, modules),
+        size_report,
+        analyzer,
+        diagnostics,
+    })
+}
 
-    fs::write(&target.output, final_source).context("Failed to write output file")?;
-    Ok(())
+/// Attributes a darklua failure on a whole bundle to the inlined script
+/// holding the first line it reports.
+fn attribute_to_module(error: BundlerError, modules: &[ModuleMapping]) -> BundlerError {
+    let BundlerError::Minify { module: None, message } = &error else {
+        return error;
+    };
+    let line = message.split("line ").skip(1).find_map(|rest| {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        rest[..digits].parse::<usize>().ok()
+    });
+    let module = line.and_then(|line| {
+        modules
+            .iter()
+            .filter(|module| module.chunk.is_none())
+            .find(|module| module.contains(line, None))
+    });
+    match module {
+        Some(module) => error.in_module(&module.path),
+        None => error,
+    }
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Bundle Generation
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// Returns the source of every script bundled with its source.
+fn bundled_sources(prepared: &PreparedBuild) -> Vec<String> {
+    prepared
+        .dom
+        .descendants()
+        .filter(|instance| {
+            matches!(instance.class.as_str(), "Script" | "LocalScript" | "ModuleScript")
+                && !prepared.stripped.contains(&instance.referent())
+        })
+        .map(writer::script_source)
+        .collect()
+}
+
+/// A bundle before minification.
+struct GeneratedBundle {
+    source: String,
+    /// Location of every script in the bundle.
+    modules: Vec<ModuleMapping>,
+    /// Bytes each part of the bundle spans.
+    layout: BundleLayout,
+    diagnostics: Vec<Diagnostic>,
+}
+
 /// Generates the bundle content for a single target.
 fn generate_bundle(
-    dom: &WeakDom,
+    prepared: &PreparedBuild,
     ctx: &BundlerContext<'_>,
-    header_content: Option<&String>,
+    config: &BuildConfig,
     target: &TargetSpec,
-) -> Result<String> {
+    darklua: Option<Minifier<'_>>,
+) -> Result<GeneratedBundle, BundlerError> {
+    let dom = &prepared.dom;
+    let header_content = config.header_content.as_ref();
     let mut output = String::with_capacity(64 * 1024);
 
     // For production builds, skip header (added after minification)
     if target.mode != Mode::Production {
         let header_raw = header_content.map_or(assets::FILE_HEADER, String::as_str);
         let header = ctx.apply_templates(header_raw);
-        writeln!(output, "{header}\n")?;
+        let _ = writeln!(output, "{header}\n");
     }
+    let header_bytes = output.len();
 
     // Write runtime shim
     let runtime_raw = format!("{}\n{}", assets::RUNTIME_HEADER, assets::RUNTIME_BODY);
     let runtime = ctx.apply_templates(&runtime_raw);
-    writeln!(output, "{runtime}\n")?;
+    let _ = writeln!(output, "{runtime}\n");
 
     // Write tree header
     let tree_header = ctx.apply_templates(assets::TREE_HEADER);
-    writeln!(output, "{tree_header}")?;
-
-    // Get darklua config for dev builds
-    let darklua_config = if target.mode == Mode::Development {
-        Some(if target.compat {
-            assets::DARKLUA_DEV_COMPAT
-        } else {
-            assets::DARKLUA_DEV
-        })
-    } else {
-        None
+    let _ = writeln!(output, "{tree_header}");
+    let runtime_bytes = output.len() - header_bytes;
+
+    let embedding = match (target.mode, target.loadstring) {
+        (Mode::Production, _) => ScriptEmbedding::Minified,
+        (Mode::Development, true) => ScriptEmbedding::Loadstring,
+        (Mode::Development, false) => ScriptEmbedding::Inline,
     };
 
-    // Process the instance tree
-    let root_children = dom.root().children();
-    let main_ref = root_children[0];
-    let main_instance = dom.get_by_ref(main_ref).expect("Root child must exist");
-
-    process_instance(
-        dom,
-        &mut output,
-        ctx.mode,
-        main_ref,
-        &main_instance.name,
-        "nil",
-        darklua_config,
-    )?;
-
-    writeln!(output, "__start()")?;
-
-    Ok(output)
+    // Process every top-level instance under its own root path
+    let mut tree = TreeWriter::new(dom, embedding, darklua, config.start_policy, &prepared.stripped);
+    for &root_ref in dom.root().children() {
+        let root = dom
+            .get_by_ref(root_ref)
+            .ok_or_else(|| BundlerError::Internal("Root child missing from DOM tree".to_string()))?;
+
+        let is_entry = config.entry_points.is_empty() || config.entry_points.contains(&root.name);
+        tree.process_root(&mut output, root_ref, &root.name, is_entry)?;
+    }
+    let (modules, tree, diagnostics) = tree.finish(&mut output);
+
+    Ok(GeneratedBundle {
+        source: output,
+        modules,
+        layout: BundleLayout { header_bytes, runtime_bytes, tree },
+        diagnostics,
+    })
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
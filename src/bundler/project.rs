@@ -0,0 +1,451 @@
+//! Project configuration files (`rbxts-bundler.toml`).
+//!
+//! A project file declares the build options that would otherwise be repeated
+//! on every invocation. Relative paths in it are resolved against the directory
+//! containing the file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use super::darklua::{DarkluaOverride, DefineValue, MAX_EXACT_INTEGER};
+use super::input::InputKind;
+use super::types::{BuildConfig, CustomTarget, StartPolicy, Target, TreeShaking};
+use super::watch::WatchedPath;
+
+/// File name of the project configuration, looked up in the working directory.
+pub const PROJECT_FILE_NAME: &str = "rbxts-bundler.toml";
+
+/// Key of the `[darklua.*]` table that applies to every target.
+const ALL_TARGETS: &str = "all";
+
+/// Darklua configuration files for one target, or for all of them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DarkluaFiles {
+    /// Configuration replacing the built-in one.
+    pub config: Option<PathBuf>,
+    /// Configuration extending the built-in one.
+    pub extend: Option<PathBuf>,
+}
+
+/// A `[custom-targets.*]` table, defining a target derived from a built-in one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CustomTargetConfig {
+    /// Built-in target providing the defaults, by name (e.g. `rel-compat`).
+    pub base: String,
+    /// Build mode, `development` or `production`.
+    pub mode: Option<String>,
+    /// Whether compatibility shims are used.
+    pub compat: Option<bool>,
+    /// Whether development builds compile scripts with `loadstring`.
+    pub loadstring: Option<bool>,
+    /// File suffix used for `{suffix}` in output names (defaults to the target name).
+    pub suffix: Option<String>,
+    /// Darklua configuration files of this target.
+    #[serde(default)]
+    pub darklua: DarkluaFiles,
+    /// Global values injected into every script of this target.
+    #[serde(default)]
+    pub defines: BTreeMap<String, toml::Value>,
+}
+
+/// Contents of a `rbxts-bundler.toml` file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectConfig {
+    /// Input model, place file, Rojo project or roblox-ts project directory.
+    pub input: Option<PathBuf>,
+    /// Treat the input as a roblox-ts project directory.
+    #[serde(default)]
+    pub rbxts: bool,
+    /// Output directory for generated bundles.
+    pub out_dir: Option<PathBuf>,
+    /// Output file name template, see [`BuildConfig::with_output_name`].
+    pub output_name: Option<String>,
+    /// Targets to build, by name (e.g. `rel-compat`).
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Custom header file.
+    pub header: Option<PathBuf>,
+    /// Dotted instance path of the subtree to bundle.
+    pub root: Option<String>,
+    /// Top-level instances whose scripts are launched on start.
+    #[serde(default)]
+    pub entry: Vec<String>,
+    /// Which scripts are launched on start, by name (e.g. `server`).
+    pub start: Option<String>,
+    /// Darklua configuration files, keyed by target name or `all`.
+    #[serde(default)]
+    pub darklua: BTreeMap<String, DarkluaFiles>,
+    /// Global values injected into every script.
+    #[serde(default)]
+    pub defines: BTreeMap<String, toml::Value>,
+    /// User-defined targets, keyed by name.
+    #[serde(default)]
+    pub custom_targets: BTreeMap<String, CustomTargetConfig>,
+    /// Whether require issues are reported (default: true).
+    pub check_requires: Option<bool>,
+    /// How unreachable modules are dropped, by name (e.g. `remove`).
+    pub tree_shake: Option<String>,
+    /// Whether a size report is written next to every output (default: false).
+    pub report: Option<bool>,
+    /// Whether an HTML analyzer page is written next to every output (default: false).
+    pub analyze: Option<bool>,
+    /// Whether warnings fail their targets (default: false).
+    pub deny_warnings: Option<bool>,
+    /// Contents of the darklua files, read once by [`ProjectConfig::load`] so
+    /// every target built from this project sees the same files.
+    #[serde(skip)]
+    darklua_contents: BTreeMap<PathBuf, String>,
+}
+
+impl ProjectConfig {
+    /// Returns the project file in a directory, if there is one.
+    #[must_use]
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        let path = dir.join(PROJECT_FILE_NAME);
+        path.is_file().then_some(path)
+    }
+
+    /// Reads a project file, resolving its relative paths against its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read project file {}", path.display()))?;
+        let mut project: Self = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse project file {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        resolve_relative(base, &mut project.input);
+        resolve_relative(base, &mut project.out_dir);
+        resolve_relative(base, &mut project.header);
+        for files in project.darklua.values_mut() {
+            resolve_relative(base, &mut files.config);
+            resolve_relative(base, &mut files.extend);
+        }
+        for custom in project.custom_targets.values_mut() {
+            resolve_relative(base, &mut custom.darklua.config);
+            resolve_relative(base, &mut custom.darklua.extend);
+        }
+
+        let paths: Vec<PathBuf> = project
+            .darklua
+            .values()
+            .chain(project.custom_targets.values().map(|custom| &custom.darklua))
+            .flat_map(DarkluaFiles::paths)
+            .collect();
+        for path in paths {
+            let content = read_darklua(&path)?;
+            project.darklua_contents.insert(path, content);
+        }
+
+        Ok(project)
+    }
+
+    /// Parses the configured targets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a target name is unknown.
+    pub fn targets(&self) -> Result<Vec<Target>> {
+        self.targets
+            .iter()
+            .map(|name| self.target(name).map_err(|e| anyhow!("Invalid `targets` entry: {e:#}")))
+            .collect()
+    }
+
+    /// Looks up a target by name, among the custom targets first and then the built-in ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no target has this name, or if the custom target is invalid.
+    pub fn target(&self, name: &str) -> Result<Target> {
+        if let Some(custom) = self.custom_targets.get(name) {
+            return custom_target(name, custom, &self.darklua_contents)
+                .with_context(|| format!("Invalid `[custom-targets.{name}]` table"));
+        }
+
+        name.parse().map_err(|_: String| {
+            let mut names: Vec<String> = Target::ALL.iter().map(ToString::to_string).collect();
+            names.extend(self.custom_targets.keys().cloned());
+            anyhow!("unknown target '{name}' (expected one of: {})", names.join(", "))
+        })
+    }
+
+    /// Parses the configured start policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policy name is unknown.
+    pub fn start_policy(&self) -> Result<Option<StartPolicy>> {
+        self.start
+            .as_deref()
+            .map(|name| name.parse().map_err(|e: String| anyhow!("Invalid `start`: {e}")))
+            .transpose()
+    }
+
+    /// Parses the configured tree shaking mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mode name is unknown.
+    pub fn tree_shaking(&self) -> Result<Option<TreeShaking>> {
+        self.tree_shake
+            .as_deref()
+            .map(|name| name.parse().map_err(|e: String| anyhow!("Invalid `tree-shake`: {e}")))
+            .transpose()
+    }
+
+    /// Reads the configured header file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header file cannot be read.
+    pub fn header_content(&self) -> Result<Option<String>> {
+        self.header
+            .as_ref()
+            .map(|path| fs::read_to_string(path).context("Failed to read header file"))
+            .transpose()
+    }
+
+    /// Reads the darklua configurations that apply to the given targets.
+    ///
+    /// Configurations for all targets come first, and replacements come
+    /// before extensions, so target-specific extensions are applied last.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a key is not a target name, or a file cannot be read.
+    pub fn darklua_overrides(&self, targets: &[Target]) -> Result<Vec<(Target, DarkluaOverride)>> {
+        let mut scoped: Vec<(Option<Target>, &DarkluaFiles)> = Vec::with_capacity(self.darklua.len());
+        for (key, files) in &self.darklua {
+            if key == ALL_TARGETS {
+                scoped.insert(0, (None, files));
+            } else {
+                let target = self
+                    .target(key)
+                    .with_context(|| format!("Invalid `[darklua.{key}]` table"))?;
+                scoped.push((Some(target), files));
+            }
+        }
+
+        let mut overrides = Vec::new();
+        for (scope, files) in scoped {
+            let applies_to: Vec<&Target> = match &scope {
+                Some(target) => targets.iter().filter(|&t| t == target).collect(),
+                None => targets.iter().collect(),
+            };
+
+            for darklua in files.read(&self.darklua_contents)? {
+                for &target in &applies_to {
+                    overrides.push((target.clone(), darklua.clone()));
+                }
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    /// Returns the files referenced by the project file, for watch mode.
+    ///
+    /// Darklua configurations of a single target are scoped to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a `[darklua.*]` key is not a target name.
+    pub fn watched_paths(&self) -> Result<Vec<WatchedPath>> {
+        let mut paths: Vec<WatchedPath> = self.header.iter().cloned().map(WatchedPath::all).collect();
+
+        for (key, files) in &self.darklua {
+            let target = if key == ALL_TARGETS {
+                None
+            } else {
+                Some(self.target(key).with_context(|| format!("Invalid `[darklua.{key}]` table"))?)
+            };
+            paths.extend(files.paths().map(|path| WatchedPath { path, target: target.clone() }));
+        }
+
+        for (name, custom) in &self.custom_targets {
+            let target = self.target(name)?;
+            paths.extend(custom.darklua.paths().map(|path| WatchedPath {
+                path,
+                target: Some(target.clone()),
+            }));
+        }
+
+        Ok(paths)
+    }
+
+    /// Converts the configured defines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a define is not a boolean, number or string.
+    pub fn defines(&self) -> Result<BTreeMap<String, DefineValue>> {
+        convert_defines(&self.defines)
+    }
+
+    /// Converts the project file into a build configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `input` or `out-dir` is missing, or if any other
+    /// setting is invalid.
+    pub fn to_build_config(&self) -> Result<BuildConfig> {
+        let input = self
+            .input
+            .clone()
+            .with_context(|| format!("`input` is not set in {PROJECT_FILE_NAME}"))?;
+        let out_dir = self
+            .out_dir
+            .clone()
+            .with_context(|| format!("`out-dir` is not set in {PROJECT_FILE_NAME}"))?;
+
+        let mut config = BuildConfig::new(input, out_dir).with_entry_points(self.entry.clone());
+
+        let targets = self.targets()?;
+        if !targets.is_empty() {
+            config = config.with_targets(targets);
+        }
+        if self.rbxts {
+            config = config.with_input_kind(InputKind::RobloxTs);
+        }
+        if let Some(start_policy) = self.start_policy()? {
+            config = config.with_start_policy(start_policy);
+        }
+        if let Some(header) = self.header_content()? {
+            config = config.with_header(header);
+        }
+        if let Some(root) = &self.root {
+            config = config.with_root_path(root.clone());
+        }
+        if let Some(output_name) = &self.output_name {
+            config = config.with_output_name(output_name.clone());
+        }
+        if let Some(check_requires) = self.check_requires {
+            config = config.with_check_requires(check_requires);
+        }
+        if let Some(tree_shaking) = self.tree_shaking()? {
+            config = config.with_tree_shaking(tree_shaking);
+        }
+        if let Some(report) = self.report {
+            config = config.with_size_report(report);
+        }
+        if let Some(analyze) = self.analyze {
+            config = config.with_analyzer(analyze);
+        }
+        if let Some(deny_warnings) = self.deny_warnings {
+            config = config.with_deny_warnings(deny_warnings);
+        }
+
+        for (target, darklua) in self.darklua_overrides(&config.targets)? {
+            config = config.with_darklua_override(target, darklua);
+        }
+        for (name, value) in self.defines()? {
+            config = config.with_define(name, value);
+        }
+
+        Ok(config)
+    }
+}
+
+impl DarkluaFiles {
+    /// Returns the configured file paths.
+    fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.config.iter().chain(&self.extend).cloned()
+    }
+
+    /// Reads the configuration files, the replacement before the extension,
+    /// taking the contents loaded with the project when there are some.
+    fn read(&self, loaded: &BTreeMap<PathBuf, String>) -> Result<Vec<DarkluaOverride>> {
+        let mut overrides = Vec::new();
+        for (path, extend) in [(&self.config, false), (&self.extend, true)] {
+            let Some(path) = path else {
+                continue;
+            };
+            let content = match loaded.get(path) {
+                Some(content) => content.clone(),
+                None => read_darklua(path)?,
+            };
+
+            overrides.push(if extend {
+                DarkluaOverride::Extend(content)
+            } else {
+                DarkluaOverride::Replace(content)
+            });
+        }
+        Ok(overrides)
+    }
+}
+
+/// Reads a darklua configuration file.
+fn read_darklua(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read darklua config {}", path.display()))
+}
+
+/// Builds a custom target from its `[custom-targets.*]` table.
+fn custom_target(name: &str, config: &CustomTargetConfig, loaded: &BTreeMap<PathBuf, String>) -> Result<Target> {
+    let base: Target = config
+        .base
+        .parse()
+        .map_err(|e: String| anyhow!("Invalid `base`: {e}"))?;
+    let mut custom = CustomTarget::new(name.to_string(), &base);
+
+    if let Some(mode) = &config.mode {
+        custom = custom.with_mode(mode.parse().map_err(|e: String| anyhow!("Invalid `mode`: {e}"))?);
+    }
+    if let Some(compat) = config.compat {
+        custom = custom.with_compat(compat);
+    }
+    if let Some(loadstring) = config.loadstring {
+        custom = custom.with_loadstring(loadstring);
+    }
+    if let Some(suffix) = &config.suffix {
+        custom = custom.with_file_suffix(suffix.clone());
+    }
+    for darklua in config.darklua.read(loaded)? {
+        custom = custom.with_darklua_override(darklua);
+    }
+    for (name, value) in convert_defines(&config.defines)? {
+        custom = custom.with_define(name, value);
+    }
+
+    Ok(Target::custom(custom))
+}
+
+/// Converts TOML define values, which must be booleans, finite numbers or
+/// strings; integers must also fit a Luau number exactly.
+fn convert_defines(defines: &BTreeMap<String, toml::Value>) -> Result<BTreeMap<String, DefineValue>> {
+    defines
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                toml::Value::Boolean(value) => DefineValue::Bool(*value),
+                toml::Value::Integer(value) if value.unsigned_abs() <= MAX_EXACT_INTEGER => {
+                    DefineValue::Number(*value as f64)
+                }
+                toml::Value::Integer(_) => bail!("Define '{name}' is too large for a Luau number"),
+                toml::Value::Float(value) if value.is_finite() => DefineValue::Number(*value),
+                toml::Value::Float(_) => bail!("Define '{name}' must be a finite number"),
+                toml::Value::String(value) => DefineValue::String(value.clone()),
+                _ => bail!("Define '{name}' must be a boolean, number or string"),
+            };
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+/// Resolves an optional path against the project file's directory.
+fn resolve_relative(base: &Path, path: &mut Option<PathBuf>) {
+    if let Some(path) = path {
+        *path = base.join(&*path);
+    }
+}
@@ -0,0 +1,508 @@
+//! Serialization of instance properties into Luau constructors.
+//!
+//! Only properties that scripts can assign and that differ from the class
+//! default are emitted. Instance references are deferred until the whole tree
+//! has been created, since they may point at instances registered later.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+
+use rbx_dom_weak::types::{Ref, Variant, VariantType};
+use rbx_dom_weak::Instance;
+use rbx_reflection::{
+    ClassDescriptor, DataType, PropertyDescriptor, PropertyKind, ReflectionDatabase, Scriptability,
+};
+
+use super::escape::append_luau_string;
+
+/// Properties that are emitted through dedicated runtime arguments, decided at
+/// build time (`RunContext` selects which scripts `__start` launches), or never assigned.
+const SKIPPED_PROPERTIES: [&str; 6] = ["Name", "Parent", "Source", "Attributes", "Tags", "RunContext"];
+
+/// Returns the reflection database, preferring a locally installed one.
+pub(crate) fn database() -> &'static ReflectionDatabase<'static> {
+    rbx_reflection_database::get().unwrap_or_else(|_| rbx_reflection_database::get_bundled())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Instance References
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A reference property waiting for its target to be registered.
+struct PendingRef {
+    holder_id: u32,
+    property: String,
+    target: Ref,
+}
+
+/// Collects registry IDs and reference properties during traversal so
+/// references can be written once every instance exists.
+#[derive(Default)]
+pub(crate) struct DeferredRefs {
+    ids: HashMap<Ref, u32>,
+    pending: Vec<PendingRef>,
+}
+
+impl DeferredRefs {
+    /// Records the runtime registry ID of an instance.
+    pub(crate) fn register(&mut self, referent: Ref, id: u32) {
+        self.ids.insert(referent, id);
+    }
+
+    /// Writes `__refs` calls for every reference whose target is in the bundle.
+    ///
+    /// References to instances outside the bundle cannot be resolved and are
+    /// skipped; the holder ID and property name of each are returned.
+    pub(crate) fn write(&self, output: &mut String) -> Vec<(u32, &str)> {
+        let mut current_holder: Option<u32> = None;
+        let mut skipped = Vec::new();
+
+        for pending in &self.pending {
+            let Some(target_id) = self.ids.get(&pending.target) else {
+                skipped.push((pending.holder_id, pending.property.as_str()));
+                continue;
+            };
+
+            if current_holder != Some(pending.holder_id) {
+                if current_holder.is_some() {
+                    output.push_str(" })\n");
+                }
+                let _ = write!(output, "__refs({}, {{ ", pending.holder_id);
+                current_holder = Some(pending.holder_id);
+            } else {
+                output.push_str(", ");
+            }
+
+            push_key(&pending.property, output);
+            let _ = write!(output, "{target_id}");
+        }
+
+        if current_holder.is_some() {
+            output.push_str(" })\n");
+        }
+        skipped
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Attributes & Tags
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Appends the trailing `attributes, tags` arguments of `__rbx`/`__lua`.
+///
+/// Nothing is appended when the instance has neither, and `nil` stands in for
+/// missing attributes when only tags are present.
+pub(crate) fn append_metadata(instance: &Instance, out: &mut String) {
+    let attributes = match instance.properties.get(&"Attributes".into()) {
+        Some(Variant::Attributes(attributes)) if !attributes.is_empty() => Some(attributes),
+        _ => None,
+    };
+    let tags = match instance.properties.get(&"Tags".into()) {
+        Some(Variant::Tags(tags)) if !tags.is_empty() => Some(tags),
+        _ => None,
+    };
+
+    if attributes.is_none() && tags.is_none() {
+        return;
+    }
+
+    out.push_str(", ");
+    match attributes {
+        Some(attributes) => {
+            out.push('{');
+            let mut value_buf = String::new();
+            let mut first = true;
+            for (key, value) in attributes.iter() {
+                value_buf.clear();
+                if !append_variant(value, None, &mut value_buf) {
+                    continue;
+                }
+                out.push_str(if first { " " } else { ", " });
+                first = false;
+                push_key(key, out);
+                out.push_str(&value_buf);
+            }
+            out.push_str(if first { "}" } else { " }" });
+        }
+        None => out.push_str("nil"),
+    }
+
+    if let Some(tags) = tags {
+        out.push_str(", { ");
+        for (i, tag) in tags.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            append_luau_string(tag, out);
+        }
+        out.push_str(" }");
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Property Writing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Writes a `__props` call assigning every non-default, scriptable property of
+/// an instance. Reference properties are handed to `refs` instead.
+pub(crate) fn write_properties(
+    output: &mut String,
+    instance: &Instance,
+    id: u32,
+    refs: &mut DeferredRefs,
+) {
+    let db = database();
+    let Some(class) = db.classes.get(instance.class.as_str()) else {
+        return;
+    };
+
+    let mut properties: Vec<(&str, &Variant)> = instance
+        .properties
+        .iter()
+        .map(|(k, v)| (k.as_str(), v))
+        .collect();
+    properties.sort_unstable_by_key(|(name, _)| *name);
+
+    let mut table = String::new();
+    let mut value_buf = String::new();
+
+    for (name, value) in properties {
+        if SKIPPED_PROPERTIES.contains(&name) {
+            continue;
+        }
+        let Some(descriptor) = find_property(db, class, name) else {
+            continue;
+        };
+        if !is_assignable(descriptor) {
+            continue;
+        }
+        if db.find_default_property(class, name) == Some(value) {
+            continue;
+        }
+
+        if let Variant::Ref(target) = value {
+            if target.is_some() {
+                refs.pending.push(PendingRef {
+                    holder_id: id,
+                    property: name.to_string(),
+                    target: *target,
+                });
+            }
+            continue;
+        }
+
+        let enum_name = match &descriptor.data_type {
+            DataType::Enum(enum_name) => Some(enum_name.as_ref()),
+            _ => None,
+        };
+
+        value_buf.clear();
+        if !append_variant(value, enum_name, &mut value_buf) {
+            continue;
+        }
+
+        if !table.is_empty() {
+            table.push_str(", ");
+        }
+        push_key(name, &mut table);
+        table.push_str(&value_buf);
+    }
+
+    if !table.is_empty() {
+        let _ = write!(output, "__props({id}, {{ ");
+        output.push_str(&table);
+        output.push_str(" })\n");
+    }
+}
+
+/// Returns the type of a class's property, if both are in the reflection database.
+pub(crate) fn property_type(class: &str, name: &str) -> Option<VariantType> {
+    let db = database();
+    let class = db.classes.get(class)?;
+    find_property(db, class, name).map(|descriptor| descriptor.data_type.ty())
+}
+
+/// Finds the descriptor of a property on a class or any of its superclasses.
+fn find_property<'db>(
+    db: &'db ReflectionDatabase<'db>,
+    class: &'db ClassDescriptor<'db>,
+    name: &str,
+) -> Option<&'db PropertyDescriptor<'db>> {
+    db.superclasses_iter(class)
+        .find_map(|descriptor| descriptor.properties.get(name))
+}
+
+/// Returns whether scripts can assign a property directly.
+fn is_assignable(descriptor: &PropertyDescriptor<'_>) -> bool {
+    matches!(descriptor.kind, PropertyKind::Canonical { .. })
+        && matches!(
+            descriptor.scriptability,
+            Scriptability::ReadWrite | Scriptability::Write
+        )
+}
+
+/// Appends a table key, using bracket syntax when the name is not an identifier.
+fn push_key(name: &str, out: &mut String) {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_identifier {
+        out.push_str(name);
+    } else {
+        out.push('[');
+        append_luau_string(name, out);
+        out.push(']');
+    }
+    out.push_str(" = ");
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Variant Conversion
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Appends the Luau expression constructing a property value.
+///
+/// `enum_name` names the enum type for [`Variant::Enum`] values, which do not
+/// carry it themselves. Returns `false` (leaving `out` untouched) for values
+/// that have no Luau representation, such as references and binary blobs.
+pub fn append_variant(value: &Variant, enum_name: Option<&str>, out: &mut String) -> bool {
+    let start = out.len();
+    let written = write_variant(value, enum_name, out);
+    if !written {
+        out.truncate(start);
+    }
+    written
+}
+
+fn write_variant(value: &Variant, enum_name: Option<&str>, out: &mut String) -> bool {
+    match value {
+        Variant::String(s) => append_luau_string(s, out),
+        Variant::ContentId(id) => append_luau_string(id.as_str(), out),
+        Variant::Content(content) => match content.as_uri() {
+            Some(uri) => {
+                out.push_str("Content.fromUri(");
+                append_luau_string(uri, out);
+                out.push(')');
+            }
+            None => return false,
+        },
+        Variant::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Variant::Int32(n) => push_display(*n, out),
+        Variant::Int64(n) => push_display(*n, out),
+        Variant::Float32(n) => push_float(*n, out),
+        Variant::Float64(n) => push_float(*n, out),
+        Variant::Vector2(v) => push_call("Vector2.new", &[v.x, v.y], out),
+        Variant::Vector2int16(v) => push_call("Vector2int16.new", &[v.x, v.y], out),
+        Variant::Vector3(v) => push_call("Vector3.new", &[v.x, v.y, v.z], out),
+        Variant::Vector3int16(v) => push_call("Vector3int16.new", &[v.x, v.y, v.z], out),
+        Variant::CFrame(cf) => push_cframe(cf, out),
+        Variant::OptionalCFrame(cf) => match cf {
+            Some(cf) => push_cframe(cf, out),
+            None => out.push_str("nil"),
+        },
+        Variant::Color3(c) => push_call("Color3.new", &[c.r, c.g, c.b], out),
+        Variant::Color3uint8(c) => push_call("Color3.fromRGB", &[c.r, c.g, c.b], out),
+        Variant::BrickColor(c) => push_call("BrickColor.new", &[*c as u16], out),
+        Variant::UDim(u) => {
+            out.push_str("UDim.new(");
+            push_float(u.scale, out);
+            out.push_str(", ");
+            push_display(u.offset, out);
+            out.push(')');
+        }
+        Variant::UDim2(u) => {
+            out.push_str("UDim2.new(");
+            push_float(u.x.scale, out);
+            out.push_str(", ");
+            push_display(u.x.offset, out);
+            out.push_str(", ");
+            push_float(u.y.scale, out);
+            out.push_str(", ");
+            push_display(u.y.offset, out);
+            out.push(')');
+        }
+        Variant::NumberRange(r) => push_call("NumberRange.new", &[r.min, r.max], out),
+        Variant::Rect(r) => push_call("Rect.new", &[r.min.x, r.min.y, r.max.x, r.max.y], out),
+        Variant::Ray(r) => {
+            out.push_str("Ray.new(");
+            push_call("Vector3.new", &[r.origin.x, r.origin.y, r.origin.z], out);
+            out.push_str(", ");
+            push_call("Vector3.new", &[r.direction.x, r.direction.y, r.direction.z], out);
+            out.push(')');
+        }
+        Variant::NumberSequence(seq) => {
+            out.push_str("NumberSequence.new({ ");
+            for (i, kp) in seq.keypoints.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                push_call("NumberSequenceKeypoint.new", &[kp.time, kp.value, kp.envelope], out);
+            }
+            out.push_str(" })");
+        }
+        Variant::ColorSequence(seq) => {
+            out.push_str("ColorSequence.new({ ");
+            for (i, kp) in seq.keypoints.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str("ColorSequenceKeypoint.new(");
+                push_float(kp.time, out);
+                out.push_str(", ");
+                push_call("Color3.new", &[kp.color.r, kp.color.g, kp.color.b], out);
+                out.push(')');
+            }
+            out.push_str(" })");
+        }
+        Variant::PhysicalProperties(props) => match props {
+            rbx_dom_weak::types::PhysicalProperties::Default => out.push_str("nil"),
+            rbx_dom_weak::types::PhysicalProperties::Custom(c) => push_call(
+                "PhysicalProperties.new",
+                &[
+                    c.density(),
+                    c.friction(),
+                    c.elasticity(),
+                    c.friction_weight(),
+                    c.elasticity_weight(),
+                ],
+                out,
+            ),
+        },
+        Variant::Faces(faces) => {
+            use rbx_dom_weak::types::Faces;
+            let items = [
+                (Faces::RIGHT, "Right"),
+                (Faces::TOP, "Top"),
+                (Faces::BACK, "Back"),
+                (Faces::LEFT, "Left"),
+                (Faces::BOTTOM, "Bottom"),
+                (Faces::FRONT, "Front"),
+            ];
+            let names = items.iter().filter(|(f, _)| faces.contains(*f)).map(|(_, n)| *n);
+            push_enum_list("Faces.new", "NormalId", names, out);
+        }
+        Variant::Axes(axes) => {
+            use rbx_dom_weak::types::Axes;
+            let items = [(Axes::X, "X"), (Axes::Y, "Y"), (Axes::Z, "Z")];
+            let names = items.iter().filter(|(a, _)| axes.contains(*a)).map(|(_, n)| *n);
+            push_enum_list("Axes.new", "Axis", names, out);
+        }
+        Variant::Font(font) => {
+            out.push_str("Font.new(");
+            append_luau_string(&font.family, out);
+            let _ = write!(out, ", Enum.FontWeight.{:?}, Enum.FontStyle.{:?})", font.weight, font.style);
+        }
+        Variant::Enum(value) => {
+            let Some(enum_name) = enum_name else {
+                return false;
+            };
+            return push_enum_item(enum_name, value.to_u32(), out);
+        }
+        Variant::EnumItem(item) => return push_enum_item(&item.ty, item.value, out),
+        _ => return false,
+    }
+    true
+}
+
+/// Appends `Enum.<Type>.<Item>`, looking the item name up by value.
+fn push_enum_item(enum_name: &str, value: u32, out: &mut String) -> bool {
+    let Some(descriptor) = database().enums.get(enum_name) else {
+        return false;
+    };
+    let Some((item, _)) = descriptor.items.iter().find(|(_, v)| **v == value) else {
+        return false;
+    };
+    let _ = write!(out, "Enum.{enum_name}.{item}");
+    true
+}
+
+/// Appends a constructor taking a list of enum items, e.g. `Faces.new(...)`.
+fn push_enum_list<'a>(
+    constructor: &str,
+    enum_name: &str,
+    items: impl Iterator<Item = &'a str>,
+    out: &mut String,
+) {
+    out.push_str(constructor);
+    out.push('(');
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let _ = write!(out, "Enum.{enum_name}.{item}");
+    }
+    out.push(')');
+}
+
+/// Appends `CFrame.new(x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22)`.
+fn push_cframe(cf: &rbx_dom_weak::types::CFrame, out: &mut String) {
+    let (p, m) = (cf.position, cf.orientation);
+    push_call(
+        "CFrame.new",
+        &[
+            p.x, p.y, p.z, m.x.x, m.x.y, m.x.z, m.y.x, m.y.y, m.y.z, m.z.x, m.z.y, m.z.z,
+        ],
+        out,
+    );
+}
+
+/// Appends `name(a, b, ...)` for a list of numeric arguments.
+fn push_call<T: LuauNumber>(name: &str, args: &[T], out: &mut String) {
+    out.push_str(name);
+    out.push('(');
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        arg.push_to(out);
+    }
+    out.push(')');
+}
+
+fn push_display(value: impl Display, out: &mut String) {
+    let _ = write!(out, "{value}");
+}
+
+/// Appends a float literal, spelling out the values Luau has no literal for.
+fn push_float<T: Into<f64> + Display + Copy>(value: T, out: &mut String) {
+    let wide: f64 = value.into();
+    if wide.is_nan() {
+        out.push_str("0/0");
+    } else if wide.is_infinite() {
+        out.push_str(if wide > 0.0 { "math.huge" } else { "-math.huge" });
+    } else {
+        push_display(value, out);
+    }
+}
+
+/// Numeric types that can appear as constructor arguments.
+trait LuauNumber: Copy {
+    fn push_to(self, out: &mut String);
+}
+
+impl LuauNumber for f32 {
+    fn push_to(self, out: &mut String) {
+        push_float(self, out);
+    }
+}
+
+impl LuauNumber for i16 {
+    fn push_to(self, out: &mut String) {
+        push_display(self, out);
+    }
+}
+
+impl LuauNumber for u8 {
+    fn push_to(self, out: &mut String) {
+        push_display(self, out);
+    }
+}
+
+impl LuauNumber for u16 {
+    fn push_to(self, out: &mut String) {
+        push_display(self, out);
+    }
+}
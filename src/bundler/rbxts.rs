@@ -0,0 +1,74 @@
+//! roblox-ts project loading.
+//!
+//! Maps the compiler output of a roblox-ts project onto the instance layout of
+//! the official model template, so `TS.import` and `TS.getModule` resolve
+//! inside the bundled runtime:
+//!
+//! ```text
+//! <project>            ← out/
+//! └── include          ← include/ (RuntimeLib, Promise)
+//!     └── node_modules
+//!         └── @rbxts   ← node_modules/@rbxts
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use serde_json::{json, Map, Value};
+
+use super::rojo::insert_node;
+
+/// Loads a roblox-ts project directory into a DOM.
+///
+/// The `out/` and `include/` directories are required; `node_modules/@rbxts`
+/// is optional for projects without packages.
+pub(crate) fn load_rbxts_project(project_dir: &Path) -> Result<WeakDom> {
+    for required in ["out", "include"] {
+        if !project_dir.join(required).is_dir() {
+            bail!(
+                "roblox-ts project is missing its '{}' directory (run rbxtsc first): {}",
+                required,
+                project_dir.display()
+            );
+        }
+    }
+
+    let name = project_dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().and_then(|n| n.to_str()).map(str::to_string))
+        .unwrap_or_else(|| "Project".to_string());
+
+    let tree = project_tree(project_dir);
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+    insert_node(&mut dom, root_ref, &name, &tree, project_dir, false, &mut Vec::new())?;
+
+    Ok(dom)
+}
+
+/// Returns the directories a roblox-ts project is built from.
+pub(crate) fn rbxts_sources(project_dir: &Path) -> Vec<PathBuf> {
+    ["out", "include", "node_modules/@rbxts"]
+        .into_iter()
+        .map(|dir| project_dir.join(dir))
+        .collect()
+}
+
+/// Builds the Rojo project tree equivalent to the roblox-ts model template.
+fn project_tree(project_dir: &Path) -> Map<String, Value> {
+    let mut include = json!({ "$path": "include" });
+    if project_dir.join("node_modules/@rbxts").is_dir() {
+        include["node_modules"] = json!({
+            "$className": "Folder",
+            "@rbxts": { "$path": "node_modules/@rbxts" },
+        });
+    }
+
+    let tree = json!({ "$path": "out", "include": include });
+    match tree {
+        Value::Object(map) => map,
+        _ => unreachable!("tree is a JSON object"),
+    }
+}
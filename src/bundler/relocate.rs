@@ -0,0 +1,117 @@
+//! Locating the instance tree of a release bundle in darklua's output.
+//!
+//! Darklua's generator decides the final layout, which is usually a single
+//! line, so the parts of the tree are found by parsing the output instead:
+//! the tree is the trailing run of top-level calls, and every instance wrote
+//! a known number of them before darklua ran.
+
+use darklua_core::nodes::{Prefix, Statement};
+use darklua_core::Parser;
+
+use super::report::BundleLayout;
+use super::sourcemap::ModuleMapping;
+
+/// Points the layout and the inlined scripts at where darklua placed them.
+///
+/// `source` is the final bundle, `header_bytes` the size of the header
+/// prepended to darklua's output. The original lines of the scripts are kept
+/// only when darklua retained the line layout, which also leaves the lines
+/// of `modules` valid once shifted by the header.
+///
+/// # Errors
+///
+/// Returns a description of the problem when darklua's output no longer
+/// ends with the tree's calls.
+pub(crate) fn relocate(
+    layout: &mut BundleLayout,
+    modules: &mut [ModuleMapping],
+    source: &str,
+    header_bytes: usize,
+    retains_lines: bool,
+) -> Result<(), String> {
+    let tree = &mut layout.tree;
+    let count = tree.nodes.iter().map(|node| node.statements).sum::<usize>() + tree.trailing_statements;
+    let starts = statement_starts(&source[header_bytes..], count)?;
+    let starts: Vec<usize> = starts.into_iter().map(|start| start + header_bytes).collect();
+
+    // The registration of each instance is its first statement
+    let mut registrations = Vec::with_capacity(tree.nodes.len());
+    let mut next = 0;
+    for node in &mut tree.nodes {
+        node.start = starts[next];
+        registrations.push(starts[next]..starts[next + 1]);
+        next += node.statements;
+        node.end = starts[next];
+    }
+    layout.runtime_bytes = starts[0] - header_bytes;
+    layout.header_bytes = header_bytes;
+
+    let lines = LineStarts::new(source);
+    let header_lines = source[..header_bytes].matches('\n').count();
+    for module in modules.iter_mut().filter(|module| module.chunk.is_none()) {
+        let registration = &registrations[module.id as usize - 1];
+        let end = source[..registration.end].trim_end_matches(|c: char| c.is_whitespace() || c == ';').len();
+        (module.end_line, module.end_column) = lines.position(end.max(registration.start + 1) - 1);
+
+        if retains_lines {
+            module.start_line += header_lines;
+            module.start_column = 1;
+        } else {
+            (module.start_line, module.start_column) = lines.position(registration.start);
+            module.original_line = None;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the byte offsets of the last `count` top-level statements, which
+/// must all be calls.
+fn statement_starts(source: &str, count: usize) -> Result<Vec<usize>, String> {
+    let block = Parser::default()
+        .preserve_tokens()
+        .parse(source)
+        .map_err(|e| format!("darklua's output does not parse: {e}"))?;
+    if block.get_last_statement().is_some() {
+        return Err("darklua's output ends with a return statement".to_string());
+    }
+    let statements: Vec<&Statement> = block.iter_statements().collect();
+    let Some(tree) = statements.len().checked_sub(count).map(|first| &statements[first..]) else {
+        return Err(format!("darklua's output has fewer than the {count} statements of the tree"));
+    };
+
+    tree.iter()
+        .map(|statement| match statement {
+            Statement::Call(call) => prefix_start(call.get_prefix(), source),
+            _ => None,
+        }
+        .ok_or_else(|| "darklua's output does not end with the calls of the tree".to_string()))
+        .collect()
+}
+
+/// Returns the byte offset of the first token of a prefix expression.
+fn prefix_start(prefix: &Prefix, source: &str) -> Option<usize> {
+    let token = match prefix {
+        Prefix::Identifier(identifier) => identifier.get_token()?,
+        Prefix::Call(call) => return prefix_start(call.get_prefix(), source),
+        Prefix::Field(field) => return prefix_start(field.get_prefix(), source),
+        Prefix::Index(index) => return prefix_start(index.get_prefix(), source),
+        Prefix::Parenthese(parenthese) => &parenthese.get_tokens()?.left_parenthese,
+    };
+    // Tokens read from the source they were parsed from, so the slice locates them
+    Some(token.read(source).as_ptr() as usize - source.as_ptr() as usize)
+}
+
+/// Byte offset of every line of a source.
+struct LineStarts(Vec<usize>);
+
+impl LineStarts {
+    fn new(source: &str) -> Self {
+        Self(std::iter::once(0).chain(source.match_indices('\n').map(|(offset, _)| offset + 1)).collect())
+    }
+
+    /// Returns the 1-based line and column of a byte offset.
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.0.partition_point(|&start| start <= offset);
+        (line, offset - self.0[line - 1] + 1)
+    }
+}
@@ -0,0 +1,126 @@
+//! Size reports breaking a bundle down by what contributes to it.
+//!
+//! Each part is measured by the bytes it spans in the final output, after
+//! darklua. Release bundles are located again in darklua's output, so the
+//! report holds whatever layout its generator picks.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::BundlerError;
+use super::traverse::TreeLayout;
+
+/// Size breakdown of a generated bundle, written next to it as `<output>.size.json`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SizeReport {
+    /// File name of the bundle this report describes.
+    pub file: String,
+    /// Size of the whole bundle.
+    pub total_bytes: usize,
+    /// Size of the header comment.
+    pub header_bytes: usize,
+    /// Size of the runtime shim the bundle starts with.
+    pub runtime_bytes: usize,
+    /// Size of everything else: non-script instances, properties, reference
+    /// assignments and the `__start` call.
+    pub tree_bytes: usize,
+    /// Every script, in registration order.
+    pub modules: Vec<ModuleSize>,
+    /// Number of instances by class, scripts included.
+    pub instances: BTreeMap<String, usize>,
+}
+
+/// What a single script contributes to a bundle.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ModuleSize {
+    /// Dotted instance path of the script.
+    pub path: String,
+    /// Class of the script (`Script`, `LocalScript` or `ModuleScript`).
+    pub class: String,
+    /// Size of the original source, before darklua.
+    pub source_bytes: usize,
+    /// Size of the script's whole registration in the bundle, after darklua.
+    pub output_bytes: usize,
+}
+
+impl SizeReport {
+    /// Returns the total size of the scripts' registrations.
+    #[must_use]
+    pub fn modules_bytes(&self) -> usize {
+        self.modules.iter().map(|module| module.output_bytes).sum()
+    }
+
+    /// Returns the total number of instances.
+    #[must_use]
+    pub fn instance_count(&self) -> usize {
+        self.instances.values().sum()
+    }
+
+    /// Reads a size report from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BundlerError::Io`] if the file cannot be read, or
+    /// [`BundlerError::Decode`] if it is not a valid size report.
+    pub fn load(path: &Path) -> Result<Self, BundlerError> {
+        let contents = fs::read_to_string(path).map_err(|e| BundlerError::io(path, e))?;
+        serde_json::from_str(&contents).map_err(|e| BundlerError::Decode {
+            path: path.to_path_buf(),
+            message: format!("Failed to parse size report {}: {e}", path.display()),
+        })
+    }
+
+    /// Writes the size report as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BundlerError::Io`] if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), BundlerError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| BundlerError::Internal(format!("Failed to serialize size report: {e}")))?;
+        fs::write(path, json).map_err(|e| BundlerError::io(path, e))
+    }
+}
+
+/// The bytes each part of a bundle spans, in order: header, runtime, then
+/// the instance tree.
+#[derive(Debug)]
+pub(crate) struct BundleLayout {
+    pub header_bytes: usize,
+    /// Size of the runtime shim, along with the comment opening the tree.
+    pub runtime_bytes: usize,
+    pub tree: TreeLayout,
+}
+
+impl BundleLayout {
+    /// Measures every part of the final bundle.
+    pub(crate) fn measure(&self, file: String, source: &str) -> SizeReport {
+        let mut modules = Vec::new();
+        let mut instances = BTreeMap::new();
+        for node in &self.tree.nodes {
+            *instances.entry(node.class.clone()).or_default() += 1;
+            if let Some(source_bytes) = node.source_bytes {
+                modules.push(ModuleSize {
+                    path: node.path.clone(),
+                    class: node.class.clone(),
+                    source_bytes,
+                    output_bytes: node.end - node.start,
+                });
+            }
+        }
+        let modules_bytes: usize = modules.iter().map(|module| module.output_bytes).sum();
+
+        SizeReport {
+            file,
+            total_bytes: source.len(),
+            header_bytes: self.header_bytes,
+            runtime_bytes: self.runtime_bytes,
+            tree_bytes: source.len().saturating_sub(self.header_bytes + self.runtime_bytes + modules_bytes),
+            modules,
+            instances,
+        }
+    }
+}
@@ -0,0 +1,889 @@
+//! Static analysis of the `require` calls between bundled scripts.
+//!
+//! Every script is parsed, and the argument of each `require` call is resolved
+//! against the DOM when it is a path from `script`:
+//!
+//! - `script.Parent.Module` and `script["Module"]`
+//! - `:FindFirstChild("Module")`, `:WaitForChild("Module")` and `:FindFirstAncestor("Name")`
+//! - roblox-ts `TS.import(script, <path>, "Module", ...)`, where `<path>` may
+//!   start from `TS.getModule(script, "@rbxts", "package")`
+//!
+//! A path may also start from a local variable bound once to such a path, as
+//! in `local Shared = script.Parent.Shared` followed by `require(Shared.Util)`.
+//!
+//! Paths from `game` or `workspace` and asset IDs are external: they can never
+//! reach a bundled instance. Anything else, like variables bound several times,
+//! is left to the runtime. Requires inside functions are lazy: they only run
+//! once the function is called, so they never take part in a load-time cycle.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use darklua_core::nodes::{
+    Arguments, AssignStatement, Block, CompoundAssignStatement, Expression, FieldExpression, FunctionCall,
+    FunctionExpression, FunctionStatement, GenericForStatement, Identifier, IndexExpression,
+    LocalAssignStatement, LocalFunctionStatement, NumericForStatement, Prefix, TypedIdentifier, Variable,
+};
+use darklua_core::process::{DefaultPostVisitor, NodePostProcessor, NodePostVisitor, NodeProcessor};
+use darklua_core::Parser;
+use rayon::prelude::*;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+
+use super::error::BundlerError;
+use super::thread_pool;
+use super::writer::script_source;
+
+/// Class names of the instances bundled as scripts.
+const SCRIPT_CLASSES: [&str; 3] = ["Script", "LocalScript", "ModuleScript"];
+
+/// Scope `TS.getModule` searches when it is given a package name only.
+const DEFAULT_PACKAGE_SCOPE: &str = "@rbxts";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Public Types
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// The scripts of a DOM and the `require` calls between them.
+#[derive(Clone, Debug)]
+pub struct RequireGraph {
+    scripts: Vec<ScriptNode>,
+    /// Index of each script in `scripts`, by referent.
+    index: HashMap<Ref, usize>,
+    issues: Vec<RequireIssue>,
+}
+
+/// A script and the `require` calls in its source.
+#[derive(Clone, Debug)]
+pub struct ScriptNode {
+    /// The script instance.
+    pub referent: Ref,
+    /// Dotted path of the script, as in source maps.
+    pub path: String,
+    /// Class of the script (`Script`, `LocalScript` or `ModuleScript`).
+    pub class: String,
+    /// Every `require` call, in source order.
+    pub requires: Vec<Require>,
+    /// Why the source could not be parsed, in which case `requires` is empty.
+    pub parse_error: Option<String>,
+}
+
+/// A single `require` call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Require {
+    /// 1-based line of the call in the script's source.
+    pub line: usize,
+    /// Whether the call is inside a function, so it does not run when the script loads.
+    pub lazy: bool,
+    /// What the call requires.
+    pub target: RequireTarget,
+}
+
+/// What a `require` call resolves to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequireTarget {
+    /// An instance of the DOM.
+    Instance(Ref),
+    /// A path from `script` that leads to no instance.
+    Missing {
+        /// The required path, e.g. `script.Parent.Module`.
+        path: String,
+        /// Where the path stops resolving.
+        reason: String,
+    },
+    /// A path from `game` or `workspace`, or an asset ID, which never
+    /// resolves to a bundled instance.
+    External,
+    /// Not a path from `script`, so only the runtime knows what it requires.
+    Dynamic,
+}
+
+/// A problem found in the require graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequireIssue {
+    /// Modules that require each other when they load, which always fails at runtime.
+    Cycle {
+        /// Paths of the modules in the cycle, starting and ending with the same module.
+        chain: Vec<String>,
+    },
+    /// A required path that leads to no instance.
+    Unresolved {
+        /// Path of the requiring script.
+        script: String,
+        /// Line of the call.
+        line: usize,
+        /// The required path.
+        path: String,
+        /// Where the path stops resolving.
+        reason: String,
+    },
+    /// A required instance that is not a `ModuleScript`.
+    NotAModule {
+        /// Path of the requiring script.
+        script: String,
+        /// Line of the call.
+        line: usize,
+        /// Path of the required instance.
+        target: String,
+        /// Class of the required instance.
+        class: String,
+    },
+}
+
+impl fmt::Display for RequireIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequireIssue::Cycle { chain } => {
+                write!(f, "circular require when loading: {}", chain.join(" → "))
+            }
+            RequireIssue::Unresolved { script, line, path, reason } => {
+                write!(f, "{script}:{line}: cannot resolve require of {path}: {reason}")
+            }
+            RequireIssue::NotAModule { script, line, target, class } => {
+                write!(f, "{script}:{line}: requires {target}, which is a {class}, not a ModuleScript")
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Analysis
+// ─────────────────────────────────────────────────────────────────────────────
+
+impl RequireGraph {
+    /// Parses every script of a DOM in parallel and resolves its `require` calls.
+    ///
+    /// Scripts that fail to parse are kept without requires; building them
+    /// reports the syntax error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread pool cannot be created.
+    pub fn analyze(dom: &WeakDom) -> Result<Self, BundlerError> {
+        let pool = thread_pool(dom, 0)?;
+        Ok(Self::analyze_in(dom, &pool))
+    }
+
+    /// Analyzes a DOM on an existing thread pool, whose stacks must be large
+    /// enough for its scripts.
+    pub(crate) fn analyze_in(dom: &WeakDom, pool: &rayon::ThreadPool) -> Self {
+        let scripts: Vec<(Ref, String)> = descendants(dom)
+            .into_iter()
+            .filter(|(referent, _)| {
+                dom.get_by_ref(*referent)
+                    .is_some_and(|instance| SCRIPT_CLASSES.contains(&instance.class.as_str()))
+            })
+            .collect();
+        let runtime_modules = runtime_modules(dom, &scripts);
+
+        let scripts: Vec<ScriptNode> = pool.install(|| {
+            scripts
+                .into_par_iter()
+                .map(|(referent, path)| analyze_script(dom, referent, path, &runtime_modules))
+                .collect()
+        });
+
+        let index = scripts.iter().enumerate().map(|(i, script)| (script.referent, i)).collect();
+        let mut graph = Self { scripts, index, issues: Vec::new() };
+        graph.issues = graph.find_issues(dom);
+        graph
+    }
+
+    /// Returns every script, in the order they are bundled.
+    #[must_use]
+    pub fn scripts(&self) -> &[ScriptNode] {
+        &self.scripts
+    }
+
+    /// Returns a script by referent.
+    #[must_use]
+    pub fn script(&self, referent: Ref) -> Option<&ScriptNode> {
+        self.index_of(referent).map(|index| &self.scripts[index])
+    }
+
+    /// Returns the unresolvable requires, requires of non-modules and
+    /// load-time cycles, in that order.
+    #[must_use]
+    pub fn issues(&self) -> &[RequireIssue] {
+        &self.issues
+    }
+
+    fn find_issues(&self, dom: &WeakDom) -> Vec<RequireIssue> {
+        let mut issues = Vec::new();
+
+        for script in &self.scripts {
+            for require in &script.requires {
+                match &require.target {
+                    RequireTarget::Missing { path, reason } => issues.push(RequireIssue::Unresolved {
+                        script: script.path.clone(),
+                        line: require.line,
+                        path: path.clone(),
+                        reason: reason.clone(),
+                    }),
+                    RequireTarget::Instance(target) => {
+                        let class = dom.get_by_ref(*target).map_or("", |inst| inst.class.as_str());
+                        if class != "ModuleScript" {
+                            issues.push(RequireIssue::NotAModule {
+                                script: script.path.clone(),
+                                line: require.line,
+                                target: instance_path(dom, *target),
+                                class: class.to_string(),
+                            });
+                        }
+                    }
+                    RequireTarget::External | RequireTarget::Dynamic => {}
+                }
+            }
+        }
+
+        issues.extend(self.load_cycles().into_iter().map(|chain| RequireIssue::Cycle { chain }));
+        issues
+    }
+
+    /// Finds the cycles of modules requiring each other when they load, with
+    /// a depth-first search reporting every edge back into the current path.
+    fn load_cycles(&self) -> Vec<Vec<String>> {
+        let edges: Vec<Vec<usize>> = self
+            .scripts
+            .iter()
+            .map(|script| {
+                script
+                    .requires
+                    .iter()
+                    .filter(|require| !require.lazy)
+                    .filter_map(|require| match require.target {
+                        RequireTarget::Instance(target) => self.index_of(target),
+                        _ => None,
+                    })
+                    .filter(|&index| self.scripts[index].class == "ModuleScript")
+                    .collect()
+            })
+            .collect();
+
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            InPath,
+            Done,
+        }
+
+        let mut state = vec![Visit::New; self.scripts.len()];
+        let mut cycles = Vec::new();
+
+        for start in 0..self.scripts.len() {
+            if state[start] != Visit::New {
+                continue;
+            }
+            // Current path, with the next edge to follow from each node
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            state[start] = Visit::InPath;
+
+            while let Some((node, next_edge)) = path.last_mut() {
+                let node = *node;
+                let Some(&target) = edges[node].get(*next_edge) else {
+                    state[node] = Visit::Done;
+                    path.pop();
+                    continue;
+                };
+                *next_edge += 1;
+
+                match state[target] {
+                    Visit::New => {
+                        state[target] = Visit::InPath;
+                        path.push((target, 0));
+                    }
+                    Visit::InPath => {
+                        let from = path.iter().position(|&(n, _)| n == target).unwrap_or(0);
+                        let mut chain: Vec<String> =
+                            path[from..].iter().map(|&(n, _)| self.scripts[n].path.clone()).collect();
+                        chain.push(self.scripts[target].path.clone());
+                        cycles.push(chain);
+                    }
+                    Visit::Done => {}
+                }
+            }
+        }
+
+        cycles
+    }
+
+    fn index_of(&self, referent: Ref) -> Option<usize> {
+        self.index.get(&referent).copied()
+    }
+}
+
+/// Returns the `node_modules` next to every `RuntimeLib`, where packages are
+/// looked up last.
+fn runtime_modules(dom: &WeakDom, scripts: &[(Ref, String)]) -> Vec<Ref> {
+    scripts
+        .iter()
+        .filter_map(|&(referent, _)| dom.get_by_ref(referent))
+        .filter(|inst| inst.name == "RuntimeLib" && inst.class == "ModuleScript")
+        .filter_map(|runtime| find_child(dom, runtime.parent(), "node_modules"))
+        .collect()
+}
+
+/// Parses a script and resolves its `require` calls, looking packages up in
+/// `runtime_modules` when the script has none of its own.
+fn analyze_script(dom: &WeakDom, referent: Ref, path: String, runtime_modules: &[Ref]) -> ScriptNode {
+    let instance = dom.get_by_ref(referent).expect("scripts are instances of the DOM");
+    let mut node = ScriptNode {
+        referent,
+        path,
+        class: instance.class.to_string(),
+        requires: Vec::new(),
+        parse_error: None,
+    };
+
+    let mut block = match Parser::default().preserve_tokens().parse(&script_source(instance)) {
+        Ok(block) => block,
+        Err(e) => {
+            node.parse_error = Some(e.to_string());
+            return node;
+        }
+    };
+    let mut bindings = BindingCounter::default();
+    DefaultPostVisitor::visit_block(&mut block, &mut bindings);
+    let mut collector = RequireCollector { bindings: bindings.counts, ..RequireCollector::default() };
+    DefaultPostVisitor::visit_block(&mut block, &mut collector);
+
+    node.requires = collector
+        .calls
+        .into_iter()
+        .map(|call| Require {
+            line: call.line,
+            lazy: call.lazy,
+            target: match call.target {
+                CallTarget::Static(path) => match path.resolve(dom, referent, runtime_modules) {
+                    Ok(target) => RequireTarget::Instance(target),
+                    Err(reason) => RequireTarget::Missing { path: path.to_string(), reason },
+                },
+                CallTarget::External => RequireTarget::External,
+                CallTarget::Dynamic => RequireTarget::Dynamic,
+            },
+        })
+        .collect();
+    node
+}
+
+/// Returns every instance below the DOM root with its dotted path, in the
+/// order the bundle registers them.
+fn descendants(dom: &WeakDom) -> Vec<(Ref, String)> {
+    let mut found = Vec::new();
+    let mut stack: Vec<(Ref, String)> = dom
+        .root()
+        .children()
+        .iter()
+        .rev()
+        .filter_map(|&child| Some((child, dom.get_by_ref(child)?.name.clone())))
+        .collect();
+
+    while let Some((referent, path)) = stack.pop() {
+        if let Some(instance) = dom.get_by_ref(referent) {
+            for &child in instance.children().iter().rev() {
+                if let Some(child_instance) = dom.get_by_ref(child) {
+                    stack.push((child, format!("{path}.{}", child_instance.name)));
+                }
+            }
+        }
+        found.push((referent, path));
+    }
+
+    found
+}
+
+/// Returns the dotted path of an instance below the DOM root.
+fn instance_path(dom: &WeakDom, referent: Ref) -> String {
+    let mut names = Vec::new();
+    let mut current = referent;
+    while current != dom.root_ref() {
+        let Some(instance) = dom.get_by_ref(current) else {
+            break;
+        };
+        names.push(instance.name.as_str());
+        current = instance.parent();
+    }
+    names.reverse();
+    names.join(".")
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Static Paths
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Where a static path starts.
+#[derive(Clone, Debug)]
+enum Anchor {
+    /// The requiring script.
+    Script,
+    /// A roblox-ts package, found like `TS.getModule` does at runtime.
+    Package { scope: String, name: String },
+}
+
+/// A single step of a static path.
+#[derive(Clone, Debug)]
+enum Step {
+    Parent,
+    Child(String),
+    Ancestor(String),
+}
+
+/// An instance path the analysis can follow without running code.
+#[derive(Clone, Debug)]
+struct StaticPath {
+    anchor: Anchor,
+    steps: Vec<Step>,
+}
+
+impl StaticPath {
+    fn with_step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Follows the path from a script, returning where it stops resolving on failure.
+    fn resolve(&self, dom: &WeakDom, script: Ref, runtime_modules: &[Ref]) -> Result<Ref, String> {
+        let mut current = match &self.anchor {
+            Anchor::Script => script,
+            Anchor::Package { scope, name } => find_package(dom, script, scope, name, runtime_modules)
+                .ok_or_else(|| format!("package {scope}/{name} is not in any node_modules"))?,
+        };
+
+        for step in &self.steps {
+            let instance = dom.get_by_ref(current).ok_or("instance missing from the DOM")?;
+            current = match step {
+                Step::Parent => {
+                    let parent = instance.parent();
+                    if parent == dom.root_ref() || parent.is_none() {
+                        return Err(format!("{} is a top-level instance with no parent", instance_path(dom, current)));
+                    }
+                    parent
+                }
+                Step::Child(name) => find_child(dom, current, name)
+                    .ok_or_else(|| format!("{} has no child named '{name}'", instance_path(dom, current)))?,
+                Step::Ancestor(name) => find_ancestor(dom, current, name)
+                    .ok_or_else(|| format!("{} has no ancestor named '{name}'", instance_path(dom, current)))?,
+            };
+        }
+
+        Ok(current)
+    }
+}
+
+impl fmt::Display for StaticPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.anchor {
+            Anchor::Script => f.write_str("script")?,
+            Anchor::Package { scope, name } => write!(f, "TS.getModule(script, \"{scope}\", \"{name}\")")?,
+        }
+        for step in &self.steps {
+            match step {
+                Step::Parent => f.write_str(".Parent")?,
+                Step::Child(name) if is_identifier(name) => write!(f, ".{name}")?,
+                Step::Child(name) => write!(f, "[\"{name}\"]")?,
+                Step::Ancestor(name) => write!(f, ":FindFirstAncestor(\"{name}\")")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn find_child(dom: &WeakDom, parent: Ref, name: &str) -> Option<Ref> {
+    dom.get_by_ref(parent)?
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|inst| inst.name == name))
+}
+
+fn find_ancestor(dom: &WeakDom, referent: Ref, name: &str) -> Option<Ref> {
+    let mut current = dom.get_by_ref(referent)?.parent();
+    while current != dom.root_ref() {
+        let instance = dom.get_by_ref(current)?;
+        if instance.name == name {
+            return Some(current);
+        }
+        current = instance.parent();
+    }
+    None
+}
+
+/// Finds a package like the roblox-ts runtime: in the `node_modules` of the
+/// script or its closest ancestor having one, then in `runtime_modules`, the
+/// `node_modules` next to `RuntimeLib`.
+fn find_package(dom: &WeakDom, script: Ref, scope: &str, name: &str, runtime_modules: &[Ref]) -> Option<Ref> {
+    let in_modules = |modules: Ref| find_child(dom, modules, scope).and_then(|scope| find_child(dom, scope, name));
+
+    let mut current = script;
+    while current != dom.root_ref() && !current.is_none() {
+        if let Some(found) = find_child(dom, current, "node_modules").and_then(in_modules) {
+            return Some(found);
+        }
+        current = dom.get_by_ref(current)?.parent();
+    }
+
+    runtime_modules.iter().find_map(|&modules| in_modules(modules))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Source Parsing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A `require` call found in a source.
+struct RequireCall {
+    line: usize,
+    lazy: bool,
+    target: CallTarget,
+}
+
+/// What a `require` call's argument is, before resolution.
+enum CallTarget {
+    Static(StaticPath),
+    External,
+    Dynamic,
+}
+
+impl CallTarget {
+    fn of(expression: &Expression, aliases: &Aliases) -> Self {
+        match expression_path(expression, aliases) {
+            Some(path) => CallTarget::Static(path),
+            None if is_external(expression, aliases) => CallTarget::External,
+            None => CallTarget::Dynamic,
+        }
+    }
+}
+
+/// What a local variable holds, when the analysis can tell.
+#[derive(Clone, Debug)]
+enum Alias {
+    Path(StaticPath),
+    External,
+}
+
+/// The aliases in scope, innermost block last.
+#[derive(Default)]
+struct Aliases {
+    scopes: Vec<HashMap<String, Alias>>,
+}
+
+impl Aliases {
+    fn get(&self, name: &str) -> Option<&Alias> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
+
+/// Counts how many times each name is bound or assigned in a source.
+///
+/// Only names bound exactly once can be aliases: the analysis does not track
+/// which of several bindings a use refers to.
+#[derive(Default)]
+struct BindingCounter {
+    counts: HashMap<String, usize>,
+}
+
+impl BindingCounter {
+    fn bind(&mut self, name: &str) {
+        *self.counts.entry(name.to_string()).or_default() += 1;
+    }
+
+    fn bind_all<'a>(&mut self, identifiers: impl Iterator<Item = &'a TypedIdentifier>) {
+        for identifier in identifiers {
+            self.bind(identifier.get_name());
+        }
+    }
+
+    fn bind_variable(&mut self, variable: &Variable) {
+        if let Variable::Identifier(identifier) = variable {
+            self.bind(identifier.get_name());
+        }
+    }
+}
+
+impl NodeProcessor for BindingCounter {
+    fn process_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        self.bind_all(statement.iter_variables());
+    }
+
+    fn process_local_function_statement(&mut self, function: &mut LocalFunctionStatement) {
+        self.bind(function.get_name());
+        self.bind_all(function.iter_parameters());
+    }
+
+    fn process_function_statement(&mut self, function: &mut FunctionStatement) {
+        let name = function.get_name();
+        if name.get_field_names().is_empty() && !name.has_method() {
+            self.bind(name.get_name().get_name());
+        }
+        self.bind_all(function.iter_parameters());
+    }
+
+    fn process_function_expression(&mut self, function: &mut FunctionExpression) {
+        self.bind_all(function.iter_parameters());
+    }
+
+    fn process_generic_for_statement(&mut self, statement: &mut GenericForStatement) {
+        self.bind_all(statement.iter_identifiers());
+    }
+
+    fn process_numeric_for_statement(&mut self, statement: &mut NumericForStatement) {
+        self.bind(statement.get_identifier().get_name());
+    }
+
+    fn process_assign_statement(&mut self, statement: &mut AssignStatement) {
+        for variable in statement.iter_variables() {
+            self.bind_variable(variable);
+        }
+    }
+
+    fn process_compound_assign_statement(&mut self, statement: &mut CompoundAssignStatement) {
+        self.bind_variable(statement.get_variable());
+    }
+}
+
+impl NodePostProcessor for BindingCounter {}
+
+/// Collects the `require` and `TS.import` calls of a source, tracking whether
+/// each one is inside a function and the local aliases of static paths.
+#[derive(Default)]
+struct RequireCollector {
+    function_depth: usize,
+    /// Number of bindings of each name, from [`BindingCounter`].
+    bindings: HashMap<String, usize>,
+    aliases: Aliases,
+    calls: Vec<RequireCall>,
+}
+
+impl NodeProcessor for RequireCollector {
+    fn process_block(&mut self, _: &mut Block) {
+        self.aliases.scopes.push(HashMap::new());
+    }
+
+    fn process_function_call(&mut self, call: &mut FunctionCall) {
+        if let Some((line, target)) = required_path(call, &self.aliases) {
+            self.calls.push(RequireCall { line, lazy: self.function_depth > 0, target });
+        }
+    }
+
+    fn process_function_expression(&mut self, _: &mut FunctionExpression) {
+        self.function_depth += 1;
+    }
+
+    fn process_function_statement(&mut self, _: &mut FunctionStatement) {
+        self.function_depth += 1;
+    }
+
+    fn process_local_function_statement(&mut self, _: &mut LocalFunctionStatement) {
+        self.function_depth += 1;
+    }
+}
+
+impl NodePostProcessor for RequireCollector {
+    fn process_after_block(&mut self, _: &mut Block) {
+        self.aliases.scopes.pop();
+    }
+
+    /// Records the variables bound once to a static or external path, once
+    /// their values are visited so they cannot refer to themselves.
+    fn process_after_local_assign_statement(&mut self, statement: &mut LocalAssignStatement) {
+        for (variable, value) in statement.iter_variables().zip(statement.iter_values()) {
+            let name = variable.get_name();
+            if self.bindings.get(name) != Some(&1) {
+                continue;
+            }
+            let alias = match expression_path(value, &self.aliases) {
+                Some(path) => Alias::Path(path),
+                None if is_external(value, &self.aliases) => Alias::External,
+                None => continue,
+            };
+            if let Some(scope) = self.aliases.scopes.last_mut() {
+                scope.insert(name.to_string(), alias);
+            }
+        }
+    }
+
+    fn process_after_function_expression(&mut self, _: &mut FunctionExpression) {
+        self.function_depth -= 1;
+    }
+
+    fn process_after_function_statement(&mut self, _: &mut FunctionStatement) {
+        self.function_depth -= 1;
+    }
+
+    fn process_after_local_function_statement(&mut self, _: &mut LocalFunctionStatement) {
+        self.function_depth -= 1;
+    }
+}
+
+/// Returns the line and target of a `require(...)` or `TS.import(script, ...)`
+/// call, or `None` if the call requires nothing.
+fn required_path(call: &FunctionCall, aliases: &Aliases) -> Option<(usize, CallTarget)> {
+    if call.get_method().is_some() {
+        return None;
+    }
+    let arguments = argument_list(call.get_arguments());
+
+    match call.get_prefix() {
+        Prefix::Identifier(identifier) if identifier.get_name() == "require" => {
+            let target = match arguments.as_slice() {
+                [argument] => CallTarget::of(argument, aliases),
+                _ => CallTarget::Dynamic,
+            };
+            Some((line_of(identifier), target))
+        }
+        Prefix::Field(field) if is_ts_function(field, "import") => {
+            let path = match arguments.as_slice() {
+                [script, base, names @ ..] if is_script(script) => {
+                    names.iter().try_fold(expression_path(base, aliases)?, |path, name| {
+                        Some(path.with_step(Step::Child(string_value(name)?)))
+                    })
+                }
+                _ => None,
+            };
+            Some((line_of(field.get_field()), path.map_or(CallTarget::Dynamic, CallTarget::Static)))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the static path of an expression, if it is one.
+fn expression_path(expression: &Expression, aliases: &Aliases) -> Option<StaticPath> {
+    match expression {
+        Expression::Identifier(identifier) => identifier_path(identifier, aliases),
+        Expression::Field(field) => field_path(field, aliases),
+        Expression::Index(index) => index_path(index, aliases),
+        Expression::Call(call) => call_path(call, aliases),
+        Expression::Parenthese(parenthese) => expression_path(parenthese.inner_expression(), aliases),
+        _ => None,
+    }
+}
+
+/// Returns the static path of a prefix expression, if it is one.
+fn prefix_path(prefix: &Prefix, aliases: &Aliases) -> Option<StaticPath> {
+    match prefix {
+        Prefix::Identifier(identifier) => identifier_path(identifier, aliases),
+        Prefix::Field(field) => field_path(field, aliases),
+        Prefix::Index(index) => index_path(index, aliases),
+        Prefix::Call(call) => call_path(call, aliases),
+        Prefix::Parenthese(parenthese) => expression_path(parenthese.inner_expression(), aliases),
+    }
+}
+
+fn identifier_path(identifier: &Identifier, aliases: &Aliases) -> Option<StaticPath> {
+    if identifier.get_name() == "script" {
+        return Some(StaticPath { anchor: Anchor::Script, steps: Vec::new() });
+    }
+    match aliases.get(identifier.get_name())? {
+        Alias::Path(path) => Some(path.clone()),
+        Alias::External => None,
+    }
+}
+
+fn field_path(field: &FieldExpression, aliases: &Aliases) -> Option<StaticPath> {
+    let path = prefix_path(field.get_prefix(), aliases)?;
+    Some(path.with_step(member_step(field.get_field().get_name())))
+}
+
+fn index_path(index: &IndexExpression, aliases: &Aliases) -> Option<StaticPath> {
+    let path = prefix_path(index.get_prefix(), aliases)?;
+    Some(path.with_step(member_step(&string_value(index.get_index())?)))
+}
+
+/// Returns the step for `.name`: the `Parent` property wins over a child named `Parent`.
+fn member_step(name: &str) -> Step {
+    if name == "Parent" {
+        Step::Parent
+    } else {
+        Step::Child(name.to_string())
+    }
+}
+
+/// Returns the static path of a child lookup method or `TS.getModule` call.
+fn call_path(call: &FunctionCall, aliases: &Aliases) -> Option<StaticPath> {
+    let arguments = argument_list(call.get_arguments());
+
+    let Some(method) = call.get_method() else {
+        let Prefix::Field(field) = call.get_prefix() else {
+            return None;
+        };
+        if !is_ts_function(field, "getModule") {
+            return None;
+        }
+        let (scope, name) = match arguments.as_slice() {
+            [script, name] if is_script(script) => (DEFAULT_PACKAGE_SCOPE.to_string(), string_value(name)?),
+            [script, scope, name] if is_script(script) => (string_value(scope)?, string_value(name)?),
+            _ => return None,
+        };
+        return Some(StaticPath { anchor: Anchor::Package { scope, name }, steps: Vec::new() });
+    };
+
+    let path = prefix_path(call.get_prefix(), aliases)?;
+    let step = match (method.get_name().as_str(), arguments.as_slice()) {
+        ("FindFirstChild", [name] | [name, Expression::False(_)]) => Step::Child(string_value(name)?),
+        ("WaitForChild", [name, ..]) => Step::Child(string_value(name)?),
+        ("FindFirstAncestor", [name]) => Step::Ancestor(string_value(name)?),
+        _ => return None,
+    };
+    Some(path.with_step(step))
+}
+
+/// Returns whether an expression is an asset ID or an instance path from
+/// `game`, `workspace` or an alias of one.
+fn is_external(expression: &Expression, aliases: &Aliases) -> bool {
+    let mut prefix = match expression {
+        Expression::Number(_) => return true,
+        Expression::Identifier(identifier) => return is_data_model(identifier, aliases),
+        Expression::Field(field) => field.get_prefix(),
+        Expression::Index(index) => index.get_prefix(),
+        Expression::Call(call) => call.get_prefix(),
+        Expression::Parenthese(parenthese) => return is_external(parenthese.inner_expression(), aliases),
+        _ => return false,
+    };
+    loop {
+        prefix = match prefix {
+            Prefix::Identifier(identifier) => return is_data_model(identifier, aliases),
+            Prefix::Field(field) => field.get_prefix(),
+            Prefix::Index(index) => index.get_prefix(),
+            Prefix::Call(call) => call.get_prefix(),
+            Prefix::Parenthese(parenthese) => return is_external(parenthese.inner_expression(), aliases),
+        };
+    }
+}
+
+fn is_data_model(identifier: &Identifier, aliases: &Aliases) -> bool {
+    let name = identifier.get_name();
+    matches!(name.as_str(), "game" | "workspace") || matches!(aliases.get(name), Some(Alias::External))
+}
+
+fn argument_list(arguments: &Arguments) -> Vec<Expression> {
+    arguments.clone().to_expressions()
+}
+
+fn is_script(expression: &Expression) -> bool {
+    matches!(expression, Expression::Identifier(identifier) if identifier.get_name() == "script")
+}
+
+/// Returns whether a field is a function of the roblox-ts runtime, e.g. `TS.import`.
+fn is_ts_function(field: &FieldExpression, name: &str) -> bool {
+    field.get_field().get_name() == name
+        && matches!(field.get_prefix(), Prefix::Identifier(identifier) if identifier.get_name() == "TS")
+}
+
+fn string_value(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::String(string) => string.get_string_value().map(str::to_string),
+        _ => None,
+    }
+}
+
+fn line_of(identifier: &Identifier) -> usize {
+    identifier
+        .get_token()
+        .and_then(|token| token.get_line_number())
+        .unwrap_or_default()
+}
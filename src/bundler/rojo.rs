@@ -0,0 +1,493 @@
+//! Rojo project loading.
+//!
+//! Builds a [`WeakDom`] from a `*.project.json` file, following the subset of
+//! Rojo's conventions that matter for bundling: project trees with `$path`,
+//! `$className`, `$properties` and `$attributes`, and the `init`/`.client`/`.server` script
+//! naming rules when syncing directories.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use rbx_dom_weak::types::{
+    Attributes, Color3, Color3uint8, Enum, Ref, Tags, UDim, UDim2, Variant, VariantType, Vector2, Vector3,
+};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use serde_json::{Map, Value};
+
+use super::input::read_model_file;
+use super::properties::property_type;
+
+/// File name Rojo looks for when a directory is used as a project.
+const DEFAULT_PROJECT: &str = "default.project.json";
+
+/// Suffix shared by every Rojo project file.
+const PROJECT_SUFFIX: &str = ".project.json";
+
+/// Returns the project file for an input path, if the input is a Rojo project.
+///
+/// Accepts either a `*.project.json` file or a directory containing a
+/// `default.project.json`.
+pub(crate) fn project_file(input: &Path) -> Option<PathBuf> {
+    if input.is_dir() {
+        let project = input.join(DEFAULT_PROJECT);
+        return project.is_file().then_some(project);
+    }
+
+    let name = input.file_name()?.to_str()?;
+    name.ends_with(PROJECT_SUFFIX).then(|| input.to_path_buf())
+}
+
+/// Returns the files and directories a project is built from: the project
+/// file itself and every `$path` in its tree, following nested projects.
+pub(crate) fn project_sources(project_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+    nested_sources(project_path, &mut Vec::new(), &mut sources)?;
+    Ok(sources)
+}
+
+/// Collects the sources of a project included by the ones in `loading`.
+fn nested_sources(project_path: &Path, loading: &mut Vec<PathBuf>, sources: &mut Vec<PathBuf>) -> Result<()> {
+    enter_project(project_path, loading, |loading| {
+        let (_, tree, base_dir) = read_project(project_path)?;
+        sources.push(project_path.to_path_buf());
+        collect_sources(&tree, &base_dir, loading, sources)
+    })
+}
+
+/// Collects the `$path` of a project node and its children.
+fn collect_sources(
+    node: &Map<String, Value>,
+    base_dir: &Path,
+    loading: &mut Vec<PathBuf>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if let Some(path) = node.get("$path").and_then(Value::as_str) {
+        let path = base_dir.join(path);
+        let is_project = path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(PROJECT_SUFFIX));
+
+        if is_project {
+            nested_sources(&path, loading, sources)?;
+        } else {
+            sources.push(path);
+        }
+    }
+
+    for (_, child) in node_children(node) {
+        collect_sources(child, base_dir, loading, sources)?;
+    }
+    Ok(())
+}
+
+/// Loads a Rojo project file into a DOM.
+///
+/// A `DataModel` tree contributes its services as top-level instances;
+/// any other tree becomes a single top-level instance named after the project.
+pub(crate) fn load_project(project_path: &Path) -> Result<WeakDom> {
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+
+    enter_project(project_path, &mut Vec::new(), |loading| {
+        let (name, tree, base_dir) = read_project(project_path)?;
+
+        if tree.get("$className").and_then(Value::as_str) == Some("DataModel") {
+            for (child_name, child) in node_children(&tree) {
+                insert_node(&mut dom, root_ref, child_name, child, &base_dir, true, loading)?;
+            }
+            Ok(())
+        } else {
+            insert_node(&mut dom, root_ref, &name, &tree, &base_dir, false, loading)
+        }
+    })?;
+
+    Ok(dom)
+}
+
+/// Runs `load` with a project file pushed onto `loading`, the canonical
+/// paths of the projects currently being loaded, so a project that
+/// includes itself fails instead of recursing forever.
+fn enter_project<T>(
+    project_path: &Path,
+    loading: &mut Vec<PathBuf>,
+    load: impl FnOnce(&mut Vec<PathBuf>) -> Result<T>,
+) -> Result<T> {
+    let canonical = project_path
+        .canonicalize()
+        .with_context(|| format!("Failed to read project file: {}", project_path.display()))?;
+    if loading.contains(&canonical) {
+        bail!("Project includes itself: {}", project_path.display());
+    }
+
+    loading.push(canonical);
+    let result = load(loading);
+    loading.pop();
+    result
+}
+
+/// Reads a project file, returning its name, tree and base directory.
+fn read_project(project_path: &Path) -> Result<(String, Map<String, Value>, PathBuf)> {
+    let content = fs::read_to_string(project_path)
+        .with_context(|| format!("Failed to read project file: {}", project_path.display()))?;
+    let project: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse project file: {}", project_path.display()))?;
+
+    let Some(name) = project.get("name").and_then(Value::as_str) else {
+        bail!("Project file is missing a 'name': {}", project_path.display());
+    };
+    let Some(tree) = project.get("tree").and_then(Value::as_object) else {
+        bail!("Project file is missing a 'tree': {}", project_path.display());
+    };
+
+    let base_dir = project_path.parent().unwrap_or(Path::new("")).to_path_buf();
+    Ok((name.to_string(), tree.clone(), base_dir))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Project Trees
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Returns the class of a project node without a `$path`.
+///
+/// Like Rojo, the class is only inferred from the name for services, the
+/// direct children of a `DataModel` tree.
+fn node_class<'a>(name: &'a str, node: &'a Map<String, Value>, is_service: bool) -> Result<&'a str> {
+    match node.get("$className").and_then(Value::as_str) {
+        Some(class) => Ok(class),
+        None if is_service => Ok(name),
+        None => bail!("Project node '{name}' needs a $className or a $path"),
+    }
+}
+
+/// Iterates over the child nodes of a project node, skipping `$` keys.
+fn node_children(node: &Map<String, Value>) -> impl Iterator<Item = (&str, &Map<String, Value>)> {
+    node.iter()
+        .filter(|(key, _)| !key.starts_with('$'))
+        .filter_map(|(key, value)| value.as_object().map(|obj| (key.as_str(), obj)))
+}
+
+/// Inserts a project node and its children under `parent`; `is_service`
+/// tells whether the node is a direct child of a `DataModel` tree, and
+/// `loading` lists the projects it is nested in.
+pub(crate) fn insert_node(
+    dom: &mut WeakDom,
+    parent: Ref,
+    name: &str,
+    node: &Map<String, Value>,
+    base_dir: &Path,
+    is_service: bool,
+    loading: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let referent = match node.get("$path").and_then(Value::as_str) {
+        Some(path) => {
+            let path = base_dir.join(path);
+            let Some(referent) = insert_path(dom, parent, &path, Some(name), loading)? else {
+                bail!("Project path cannot be turned into an instance: {}", path.display());
+            };
+            if let Some(class) = node.get("$className").and_then(Value::as_str) {
+                let instance = dom.get_by_ref_mut(referent).context("Referent missing from DOM tree")?;
+                instance.class = class.into();
+            }
+            referent
+        }
+        None => dom.insert(parent, InstanceBuilder::new(node_class(name, node, is_service)?).with_name(name)),
+    };
+
+    if let Some(properties) = node.get("$properties").and_then(Value::as_object) {
+        let instance = dom.get_by_ref_mut(referent).context("Referent missing from DOM tree")?;
+        for (key, value) in properties {
+            let ty = property_type(&instance.class, key);
+            let variant = json_to_variant(value, ty)
+                .with_context(|| format!("Invalid value for property '{key}' of '{name}'"))?;
+            instance.properties.insert(key.as_str().into(), variant);
+        }
+    }
+
+    if let Some(values) = node.get("$attributes").and_then(Value::as_object) {
+        let mut attributes = Attributes::new();
+        for (key, value) in values {
+            // Numeric attributes are always doubles
+            let variant = json_to_variant(value, None)
+                .with_context(|| format!("Invalid value for attribute '{key}' of '{name}'"))?;
+            attributes.insert(key.clone(), variant);
+        }
+        let instance = dom.get_by_ref_mut(referent).context("Referent missing from DOM tree")?;
+        instance.properties.insert("Attributes".into(), Variant::Attributes(attributes));
+    }
+
+    for (child_name, child) in node_children(node) {
+        insert_node(dom, referent, child_name, child, base_dir, false, loading)?;
+    }
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Filesystem Syncing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Kind of script produced by a Lua source file.
+fn script_class(file_name: &str) -> Option<(&str, &'static str)> {
+    const SUFFIXES: [(&str, &str); 6] = [
+        (".server.luau", "Script"),
+        (".server.lua", "Script"),
+        (".client.luau", "LocalScript"),
+        (".client.lua", "LocalScript"),
+        (".luau", "ModuleScript"),
+        (".lua", "ModuleScript"),
+    ];
+
+    SUFFIXES
+        .iter()
+        .find_map(|(suffix, class)| file_name.strip_suffix(suffix).map(|stem| (stem, *class)))
+}
+
+/// Inserts the instance for a file or directory, returning `None` for files
+/// that Rojo would not sync (unknown extensions, metadata).
+fn insert_path(
+    dom: &mut WeakDom,
+    parent: Ref,
+    path: &Path,
+    name_override: Option<&str>,
+    loading: &mut Vec<PathBuf>,
+) -> Result<Option<Ref>> {
+    if path.is_dir() {
+        return insert_directory(dom, parent, path, name_override, loading).map(Some);
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Invalid file name: {}", path.display()))?;
+
+    if file_name.ends_with(PROJECT_SUFFIX) {
+        enter_project(path, loading, |loading| {
+            let (name, tree, base_dir) = read_project(path)?;
+            let name = name_override.unwrap_or(&name).to_string();
+            insert_node(dom, parent, &name, &tree, &base_dir, false, loading)
+        })?;
+        return Ok(dom.get_by_ref(parent).and_then(|p| p.children().last().copied()));
+    }
+
+    if file_name.ends_with(".meta.json") {
+        return Ok(None);
+    }
+
+    if let Some((stem, class)) = script_class(file_name) {
+        let source = read_source(path)?;
+        let builder = InstanceBuilder::new(class)
+            .with_name(name_override.unwrap_or(stem))
+            .with_property("Source", source);
+        return Ok(Some(dom.insert(parent, builder)));
+    }
+
+    if let Some(stem) = file_name.strip_suffix(".txt") {
+        let builder = InstanceBuilder::new("StringValue")
+            .with_name(name_override.unwrap_or(stem))
+            .with_property("Value", read_source(path)?);
+        return Ok(Some(dom.insert(parent, builder)));
+    }
+
+    let model_stem = file_name
+        .strip_suffix(".rbxmx")
+        .or_else(|| file_name.strip_suffix(".rbxm"));
+    if let Some(stem) = model_stem {
+        return insert_model(dom, parent, path, name_override.unwrap_or(stem)).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Inserts a directory, honoring `init` scripts and nested default projects.
+fn insert_directory(
+    dom: &mut WeakDom,
+    parent: Ref,
+    dir: &Path,
+    name_override: Option<&str>,
+    loading: &mut Vec<PathBuf>,
+) -> Result<Ref> {
+    let dir_name = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Folder");
+    let name = name_override.unwrap_or(dir_name);
+
+    let nested_project = dir.join(DEFAULT_PROJECT);
+    if nested_project.is_file() {
+        enter_project(&nested_project, loading, |loading| {
+            let (_, tree, base_dir) = read_project(&nested_project)?;
+            insert_node(dom, parent, name, &tree, &base_dir, false, loading)
+        })?;
+        return dom
+            .get_by_ref(parent)
+            .and_then(|p| p.children().last().copied())
+            .context("Nested project produced no instance");
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    entries.sort();
+
+    let init = entries.iter().find_map(|path| {
+        let file_name = path.file_name()?.to_str()?;
+        let (stem, class) = script_class(file_name)?;
+        (stem == "init").then(|| (path.clone(), class))
+    });
+
+    let mut builder = InstanceBuilder::new("Folder").with_name(name);
+    if let Some((init_path, class)) = &init {
+        builder = builder
+            .with_class(*class)
+            .with_property("Source", read_source(init_path)?);
+    }
+    let referent = dom.insert(parent, builder);
+
+    for entry in &entries {
+        let is_init = init.as_ref().is_some_and(|(path, _)| path == entry);
+        let is_hidden = entry
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if is_init || is_hidden {
+            continue;
+        }
+        insert_path(dom, referent, entry, None, loading)?;
+    }
+
+    Ok(referent)
+}
+
+/// Inserts the single top-level instance of a model file, renamed to `name`.
+fn insert_model(dom: &mut WeakDom, parent: Ref, path: &Path, name: &str) -> Result<Ref> {
+    let mut model = read_model_file(path)
+        .with_context(|| format!("Failed to load model: {}", path.display()))?;
+
+    let roots = model.root().children().to_vec();
+    let [referent] = roots.as_slice() else {
+        bail!(
+            "Model files synced by a project must contain exactly one top-level instance: {}",
+            path.display()
+        );
+    };
+
+    model.transfer(*referent, dom, parent);
+    let instance = dom.get_by_ref_mut(*referent).context("Referent missing from DOM tree")?;
+    instance.name = name.to_string();
+    Ok(*referent)
+}
+
+/// Reads a source file, normalizing it to a string.
+fn read_source(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Property Values
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Converts a `$properties` value into a DOM variant.
+///
+/// Strings, booleans and numbers may be given implicitly; other types use
+/// Rojo's explicit `{ "Type": value }` form. Implicit numbers take the type
+/// `ty` of the property they are assigned to, defaulting to `Float64`.
+fn json_to_variant(value: &Value, ty: Option<VariantType>) -> Result<Variant> {
+    match value {
+        Value::String(s) => Ok(Variant::String(s.clone())),
+        Value::Bool(b) => Ok(Variant::Bool(*b)),
+        Value::Number(_) => match ty {
+            Some(VariantType::Float32) => explicit_variant("Float32", value),
+            Some(VariantType::Int32) => explicit_variant("Int32", value),
+            Some(VariantType::Int64) => explicit_variant("Int64", value),
+            Some(VariantType::Enum) => explicit_variant("Enum", value),
+            _ => explicit_variant("Float64", value),
+        },
+        Value::Object(obj) if obj.len() == 1 => {
+            let (ty, inner) = obj.iter().next().expect("object has one entry");
+            explicit_variant(ty, inner)
+        }
+        _ => bail!("Expected a string, number, boolean or explicitly typed value"),
+    }
+}
+
+/// Converts an explicitly typed `$properties` value.
+fn explicit_variant(ty: &str, value: &Value) -> Result<Variant> {
+    let variant = match ty {
+        "String" => Variant::String(value.as_str().context("Expected a string")?.to_string()),
+        "Bool" => Variant::Bool(value.as_bool().context("Expected a boolean")?),
+        "Float32" => Variant::Float32(number(value)? as f32),
+        "Float64" => Variant::Float64(number(value)?),
+        "Int32" => Variant::Int32(integer(value)? as i32),
+        "Int64" => Variant::Int64(integer(value)?),
+        "Enum" => Variant::Enum(Enum::from_u32(integer(value)? as u32)),
+        "Vector2" => {
+            let [x, y] = numbers(value)?;
+            Variant::Vector2(Vector2::new(x as f32, y as f32))
+        }
+        "Vector3" => {
+            let [x, y, z] = numbers(value)?;
+            Variant::Vector3(Vector3::new(x as f32, y as f32, z as f32))
+        }
+        "Color3" => {
+            let [r, g, b] = numbers(value)?;
+            Variant::Color3(Color3::new(r as f32, g as f32, b as f32))
+        }
+        "Color3uint8" => {
+            let [r, g, b] = numbers(value)?;
+            Variant::Color3uint8(Color3uint8::new(r as u8, g as u8, b as u8))
+        }
+        "UDim" => {
+            let [scale, offset] = numbers(value)?;
+            Variant::UDim(UDim::new(scale as f32, offset as i32))
+        }
+        "UDim2" => {
+            let axes = value.as_array().context("Expected [[scale, offset], [scale, offset]]")?;
+            let [x, y] = axes.as_slice() else {
+                bail!("Expected [[scale, offset], [scale, offset]]");
+            };
+            let ([xs, xo], [ys, yo]) = (numbers(x)?, numbers(y)?);
+            Variant::UDim2(UDim2::new(
+                UDim::new(xs as f32, xo as i32),
+                UDim::new(ys as f32, yo as i32),
+            ))
+        }
+        "Tags" => {
+            let tags = value.as_array().context("Expected an array of strings")?;
+            let tags = tags
+                .iter()
+                .map(|t| t.as_str().map(str::to_string).context("Expected an array of strings"))
+                .collect::<Result<Vec<_>>>()?;
+            Variant::Tags(Tags::from(tags))
+        }
+        _ => bail!("Unsupported property type '{ty}'"),
+    };
+    Ok(variant)
+}
+
+fn number(value: &Value) -> Result<f64> {
+    value.as_f64().context("Expected a number")
+}
+
+fn integer(value: &Value) -> Result<i64> {
+    value.as_i64().context("Expected an integer")
+}
+
+fn numbers<const N: usize>(value: &Value) -> Result<[f64; N]> {
+    let items = value
+        .as_array()
+        .with_context(|| format!("Expected an array of {N} numbers"))?;
+    if items.len() != N {
+        bail!("Expected an array of {N} numbers");
+    }
+
+    let mut out = [0.0; N];
+    for (slot, item) in out.iter_mut().zip(items) {
+        *slot = number(item)?;
+    }
+    Ok(out)
+}
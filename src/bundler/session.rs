@@ -0,0 +1,187 @@
+//! Long-lived build sessions that reuse work across builds.
+//!
+//! Every [`build`](super::build) starts from scratch: it decodes the model,
+//! runs darklua over every script and spawns a thread pool. A [`Bundler`]
+//! keeps all three between builds, so rebuilding a model after a small change
+//! only decodes it again if its contents changed, and only runs darklua over
+//! the scripts whose source changed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+use super::error::BundlerError;
+use super::input::{decode_model_file, load_model, InputKind};
+use super::minify::MinifyCache;
+use super::rojo::project_file;
+use super::types::{BuildConfig, BuildResult, MemoryBuildResult};
+use super::{estimate_thread_stack_size, memory_result, render_dom, thread_pool, write_build};
+
+/// A build session that caches work across builds.
+///
+/// Builds through a session produce exactly what the free functions of the
+/// same name do, but reuse:
+/// - the decoded DOM of model and place files, while their contents do not
+///   change (Rojo and roblox-ts projects are loaded on every build)
+/// - the darklua output of every script and release bundle, keyed by the
+///   hash and size of its source and by its darklua configuration; development
+///   targets also run darklua once over all of their changed scripts, rather
+///   than parsing the configuration again for each script
+/// - the thread pool, unless a deeper model needs larger stacks
+///
+/// Darklua output unused by the last few builds is dropped, so editing
+/// scripts does not grow the cache forever. Parsed darklua configurations are
+/// not cached: darklua takes ownership of the configuration it runs with and
+/// it cannot be cloned, so each darklua run parses its own. With the shared
+/// run of development targets, that is once per configuration and build.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::PathBuf;
+/// use rbxts_bundler::bundler::{BuildConfig, Bundler, Target};
+///
+/// let config = BuildConfig::new(PathBuf::from("input.rbxm"), PathBuf::from("dist"))
+///     .with_targets(vec![Target::Dev, Target::Rel]);
+///
+/// let mut bundler = Bundler::new();
+/// for _ in 0..2 {
+///     let result = bundler.build(&config).expect("Build failed");
+///     assert!(result.is_success());
+/// }
+/// ```
+#[derive(Default)]
+pub struct Bundler {
+    caches: BuildCaches,
+    /// Decoded model files by input path, with the contents they were decoded from.
+    models: HashMap<PathBuf, (Vec<u8>, WeakDom)>,
+}
+
+impl Bundler {
+    /// Creates a session with empty caches.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds one or more targets into the output directory, as
+    /// [`build`](super::build) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as [`build`](super::build).
+    pub fn build(&mut self, config: &BuildConfig) -> Result<BuildResult, BundlerError> {
+        let start_time = Instant::now();
+        let dom = self.load_input(config)?;
+        let rendered = render_dom(dom, config, &mut self.caches)?;
+        write_build(config, rendered, start_time)
+    }
+
+    /// Builds one or more targets without writing anything to disk, as
+    /// [`build_in_memory`](super::build_in_memory) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as [`build_in_memory`](super::build_in_memory).
+    pub fn build_in_memory(&mut self, config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+        let dom = self.load_input(config)?;
+        self.build_from_dom(dom, config)
+    }
+
+    /// Builds one or more targets from a loaded DOM, as
+    /// [`build_from_dom`](super::build_from_dom) does.
+    ///
+    /// The DOM itself is not cached, since the caller already holds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as [`build_from_dom`](super::build_from_dom).
+    pub fn build_from_dom(&mut self, dom: WeakDom, config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+        let start_time = Instant::now();
+        let rendered = render_dom(dom, config, &mut self.caches)?;
+        Ok(memory_result(config, rendered, start_time))
+    }
+
+    /// Builds one or more targets from the contents of a model or place file,
+    /// as [`build_from_bytes`](super::build_from_bytes) does.
+    ///
+    /// The decoded DOM is cached under `config.input`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as [`build_from_bytes`](super::build_from_bytes).
+    pub fn build_from_bytes(&mut self, contents: &[u8], config: &BuildConfig) -> Result<MemoryBuildResult, BundlerError> {
+        let dom = self.decode(&config.input, contents)?;
+        self.build_from_dom(dom, config)
+    }
+
+    /// Drops every cached model, darklua output and the thread pool.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Reads the configured input, reusing the DOM of an unchanged model file.
+    fn load_input(&mut self, config: &BuildConfig) -> Result<WeakDom, BundlerError> {
+        let input = &config.input;
+        if !input.exists() {
+            return Err(BundlerError::InputMissing { path: input.clone() });
+        }
+        if config.input_kind != InputKind::Auto || project_file(input).is_some() {
+            return load_model(input, config.input_kind);
+        }
+
+        let contents = fs::read(input).map_err(|e| BundlerError::io(input, e))?;
+        self.decode(input, &contents)
+    }
+
+    /// Decodes a model file, or copies its cached DOM if the contents are unchanged.
+    fn decode(&mut self, path: &Path, contents: &[u8]) -> Result<WeakDom, BundlerError> {
+        if let Some((cached_contents, dom)) = self.models.get(path) {
+            if cached_contents.as_slice() == contents {
+                return Ok(copy_dom(dom));
+            }
+        }
+
+        let dom = decode_model_file(path, contents)?;
+        let copy = copy_dom(&dom);
+        self.models.insert(path.to_path_buf(), (contents.to_vec(), dom));
+        Ok(copy)
+    }
+}
+
+/// Copies every top-level instance of a DOM, keeping references between them.
+fn copy_dom(dom: &WeakDom) -> WeakDom {
+    let root = dom.root();
+    let mut copy = WeakDom::new(InstanceBuilder::new(root.class.as_str()).with_name(root.name.as_str()));
+    let copy_root = copy.root_ref();
+    for referent in dom.clone_multiple_into_external(root.children(), &mut copy) {
+        copy.transfer_within(referent, copy_root);
+    }
+    copy
+}
+
+/// What the builds of a session share: the thread pool and darklua output.
+#[derive(Default)]
+pub(crate) struct BuildCaches {
+    /// Thread pool and the stack size its threads were created with.
+    pool: Option<(usize, rayon::ThreadPool)>,
+    minify: MinifyCache,
+}
+
+impl BuildCaches {
+    /// Starts a build of `dom`, returning the thread pool and darklua cache to use.
+    pub(crate) fn start(&mut self, dom: &WeakDom) -> Result<(&rayon::ThreadPool, &MinifyCache), BundlerError> {
+        self.minify.next_build();
+
+        let stack_size = estimate_thread_stack_size(dom);
+        let pool = match self.pool.take() {
+            Some((size, pool)) if size >= stack_size => (size, pool),
+            _ => (stack_size, thread_pool(dom, 0)?),
+        };
+        let (_, pool) = self.pool.insert(pool);
+        Ok((pool, &self.minify))
+    }
+}
@@ -0,0 +1,195 @@
+//! Tree shaking of the modules no launched script can require.
+//!
+//! The scripts `__start` launches are the roots, and every `ModuleScript` they
+//! require, directly or through other modules, is kept, lazy requires
+//! included. When a reachable script has a require that cannot be followed
+//! statically, or its source does not parse, any module may be required at
+//! runtime, so every module is kept and a warning names the script. The
+//! roblox-ts `RuntimeLib` is exempt: the `TS.import` calls it runs are
+//! followed at their call sites.
+
+use std::collections::{HashSet, VecDeque};
+
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use serde::Serialize;
+
+use super::diagnostics::{Diagnostic, DiagnosticCode};
+use super::error::BundlerError;
+use super::requires::{RequireGraph, RequireTarget, ScriptNode};
+use super::traverse::launches;
+use super::types::{BuildConfig, TreeShaking};
+use super::writer::script_source;
+
+/// The modules dropped by tree shaking.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TreeShakeReport {
+    /// Number of `ModuleScript`s kept in the bundle.
+    pub kept_modules: usize,
+    /// Every dropped `ModuleScript`, in bundle order.
+    pub removed: Vec<ShakenModule>,
+}
+
+/// A `ModuleScript` no launched script can require.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ShakenModule {
+    /// Dotted path of the module, as in source maps.
+    pub path: String,
+    /// Size of the module's source, which the bundle no longer contains.
+    pub source_bytes: usize,
+    /// Whether the module's instance was removed too.
+    pub instance_removed: bool,
+}
+
+impl TreeShakeReport {
+    /// Returns the total size of the dropped sources, in bytes.
+    #[must_use]
+    pub fn bytes_saved(&self) -> usize {
+        self.removed.iter().map(|module| module.source_bytes).sum()
+    }
+}
+
+/// Drops the modules no launched script can require.
+///
+/// With [`TreeShaking::Remove`], modules whose subtree holds no other script
+/// are destroyed; the rest are returned to be bundled without their source.
+/// Reachable scripts whose requires cannot all be followed are reported in
+/// `diagnostics`, and keep every module.
+///
+/// # Errors
+///
+/// Returns an error if the start policy and entry points launch no script,
+/// since every module would then be dropped.
+pub(crate) fn tree_shake(
+    dom: &mut WeakDom,
+    graph: &RequireGraph,
+    config: &BuildConfig,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(HashSet<Ref>, TreeShakeReport), BundlerError> {
+    let roots = launched_scripts(dom, config);
+    if roots.is_empty() {
+        return Err(BundlerError::InvalidConfig(format!(
+            "Tree shaking needs a launched script, but start policy '{}' launches none",
+            config.start_policy
+        )));
+    }
+    let (reachable, unfollowed) = reachable_scripts(dom, graph, roots);
+    diagnostics.extend(unfollowed.iter().map(|script| unfollowed_require(script)));
+
+    let shaken: Vec<&ScriptNode> = graph
+        .scripts()
+        .iter()
+        .filter(|script| {
+            script.class == "ModuleScript" && unfollowed.is_empty() && !reachable.contains(&script.referent)
+        })
+        .collect();
+    let source_bytes: Vec<usize> = shaken
+        .iter()
+        .map(|script| dom.get_by_ref(script.referent).map_or(0, |inst| script_source(inst).len()))
+        .collect();
+    let shaken_refs: HashSet<Ref> = shaken.iter().map(|script| script.referent).collect();
+
+    let mut stripped = HashSet::new();
+    let mut removed = Vec::with_capacity(shaken.len());
+    for (script, source_bytes) in shaken.into_iter().zip(source_bytes) {
+        // Descendants of a removed module are gone with it
+        let instance_removed = match dom.get_by_ref(script.referent) {
+            None => true,
+            Some(_) if config.tree_shaking == TreeShaking::Remove
+                && only_shaken_scripts(dom, graph, script.referent, &shaken_refs) =>
+            {
+                dom.destroy(script.referent);
+                true
+            }
+            Some(_) => {
+                stripped.insert(script.referent);
+                false
+            }
+        };
+        removed.push(ShakenModule { path: script.path.clone(), source_bytes, instance_removed });
+    }
+
+    let kept_modules = graph
+        .scripts()
+        .iter()
+        .filter(|script| script.class == "ModuleScript" && !shaken_refs.contains(&script.referent))
+        .count();
+    Ok((stripped, TreeShakeReport { kept_modules, removed }))
+}
+
+/// Returns the scripts `__start` launches, like the bundle does.
+fn launched_scripts(dom: &WeakDom, config: &BuildConfig) -> Vec<Ref> {
+    dom.root()
+        .children()
+        .iter()
+        .filter_map(|&root| dom.get_by_ref(root))
+        .filter(|root| config.entry_points.is_empty() || config.entry_points.contains(&root.name))
+        .flat_map(|root| dom.descendants_of(root.referent()))
+        .filter(|instance| launches(instance, config.start_policy))
+        .map(|instance| instance.referent())
+        .collect()
+}
+
+/// Follows the require graph from the roots, breadth first, returning the
+/// reachable scripts and those with a require it could not follow.
+fn reachable_scripts<'a>(
+    dom: &WeakDom,
+    graph: &'a RequireGraph,
+    roots: Vec<Ref>,
+) -> (HashSet<Ref>, Vec<&'a ScriptNode>) {
+    let mut reachable: HashSet<Ref> = roots.iter().copied().collect();
+    let mut queue: VecDeque<Ref> = roots.into();
+    let mut unfollowed = Vec::new();
+
+    while let Some(referent) = queue.pop_front() {
+        let Some(script) = graph.script(referent) else {
+            continue;
+        };
+        let mut visit = |target: Ref| {
+            if graph.script(target).is_some_and(|node| node.class == "ModuleScript") && reachable.insert(target) {
+                queue.push_back(target);
+            }
+        };
+
+        for require in &script.requires {
+            if let RequireTarget::Instance(target) = require.target {
+                visit(target);
+            }
+        }
+
+        if has_dynamic_require(script) && !is_runtime_lib(dom, script) {
+            unfollowed.push(script);
+        }
+    }
+
+    (reachable, unfollowed)
+}
+
+fn has_dynamic_require(script: &ScriptNode) -> bool {
+    script.parse_error.is_some() || script.requires.iter().any(|require| require.target == RequireTarget::Dynamic)
+}
+
+/// Returns the warning about a reachable script whose requires cannot all be followed.
+fn unfollowed_require(script: &ScriptNode) -> Diagnostic {
+    let reason = match script.requires.iter().find(|require| require.target == RequireTarget::Dynamic) {
+        Some(require) => format!("The require on line {} cannot be followed statically", require.line),
+        None => "The source does not parse".to_string(),
+    };
+    Diagnostic::warning(
+        DiagnosticCode::DynamicRequire,
+        &script.path,
+        format!("{reason}, so tree shaking keeps every module"),
+    )
+}
+
+/// Returns whether the only scripts in an instance's subtree are shaken modules.
+fn only_shaken_scripts(dom: &WeakDom, graph: &RequireGraph, referent: Ref, shaken: &HashSet<Ref>) -> bool {
+    dom.descendants_of(referent)
+        .filter(|instance| graph.script(instance.referent()).is_some())
+        .all(|instance| shaken.contains(&instance.referent()))
+}
+
+fn is_runtime_lib(dom: &WeakDom, script: &ScriptNode) -> bool {
+    script.class == "ModuleScript"
+        && dom.get_by_ref(script.referent).is_some_and(|inst| inst.name == "RuntimeLib")
+}
@@ -0,0 +1,242 @@
+//! Source maps from generated bundle lines back to the original scripts.
+//!
+//! Development bundles compile every script as its own `loadstring` chunk
+//! named after the script's path, so errors are located by chunk name. Release
+//! bundles inline every script, usually on a single line, so errors are
+//! located by their line and column in the bundle. Original lines are only
+//! known when darklua's generator keeps the line layout; otherwise locations
+//! map to the script alone.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::BundlerError;
+
+/// Version of the source map format written by this crate.
+pub const SOURCE_MAP_VERSION: u32 = 2;
+
+/// Location of one script's source within the generated code.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ModuleMapping {
+    /// Runtime registry ID of the script.
+    pub id: u32,
+    /// Dotted instance path of the script.
+    pub path: String,
+    /// Class of the script (`Script`, `LocalScript` or `ModuleScript`).
+    pub class: String,
+    /// Chunk name the script is compiled under, or `None` when its source is
+    /// inlined into the bundle and lines refer to the bundle file itself.
+    pub chunk: Option<String>,
+    /// First generated line holding the script's source (1-based).
+    pub start_line: usize,
+    /// Column of `start_line` the script's source starts at (1-based, in bytes).
+    pub start_column: usize,
+    /// Last generated line holding the script's source (inclusive).
+    pub end_line: usize,
+    /// Column of `end_line` the script's source ends at (inclusive).
+    pub end_column: usize,
+    /// Original line of the source found at `start_line`, or `None` when
+    /// darklua did not keep the line layout of the script.
+    pub original_line: Option<usize>,
+}
+
+impl ModuleMapping {
+    /// Returns whether a generated location falls in this script. Without a
+    /// column, any location on one of its lines does.
+    #[must_use]
+    pub fn contains(&self, line: usize, column: Option<usize>) -> bool {
+        (line, column.unwrap_or(usize::MAX)) >= (self.start_line, self.start_column)
+            && (line, column.unwrap_or(0)) <= (self.end_line, self.end_column)
+    }
+
+    /// Translates a generated line into the original line, if it falls in
+    /// this script and its line layout was kept.
+    #[must_use]
+    pub fn original_line_at(&self, line: usize) -> Option<usize> {
+        let original_line = self.original_line?;
+        (self.start_line..=self.end_line)
+            .contains(&line)
+            .then(|| original_line + (line - self.start_line))
+    }
+}
+
+/// Source map written next to a generated bundle.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Format version, see [`SOURCE_MAP_VERSION`].
+    pub version: u32,
+    /// File name of the bundle this map describes.
+    pub file: String,
+    /// Every script in the bundle, in registration order.
+    pub modules: Vec<ModuleMapping>,
+}
+
+impl SourceMap {
+    /// Creates a source map for a bundle.
+    #[must_use]
+    pub fn new(file: String, modules: Vec<ModuleMapping>) -> Self {
+        Self {
+            version: SOURCE_MAP_VERSION,
+            file,
+            modules,
+        }
+    }
+
+    /// Reads a source map from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BundlerError::Io`] if the file cannot be read, or
+    /// [`BundlerError::Decode`] if it is not a valid source map.
+    pub fn load(path: &Path) -> Result<Self, BundlerError> {
+        let contents = fs::read_to_string(path).map_err(|e| BundlerError::io(path, e))?;
+        serde_json::from_str(&contents).map_err(|e| BundlerError::Decode {
+            path: path.to_path_buf(),
+            message: format!("Failed to parse source map {}: {e}", path.display()),
+        })
+    }
+
+    /// Writes the source map as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BundlerError::Io`] if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), BundlerError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| BundlerError::Internal(format!("Failed to serialize source map: {e}")))?;
+        fs::write(path, json).map_err(|e| BundlerError::io(path, e))
+    }
+
+    /// Finds the script and original line for a generated location.
+    ///
+    /// `chunk` is the chunk name reported by the error. Locations in a chunk
+    /// that no script was compiled under are looked up among inlined scripts.
+    /// A location without a column on a line several scripts share is
+    /// ambiguous and resolves to nothing. The original line is `None` when
+    /// the script's line layout was not kept.
+    #[must_use]
+    pub fn resolve(&self, chunk: &str, line: usize, column: Option<usize>) -> Option<(&ModuleMapping, Option<usize>)> {
+        let is_script_chunk = self.modules.iter().any(|m| m.chunk.as_deref() == Some(chunk));
+
+        let mut candidates = self.modules.iter().filter(|m| {
+            let in_chunk = if is_script_chunk {
+                m.chunk.as_deref() == Some(chunk)
+            } else {
+                m.chunk.is_none()
+            };
+            in_chunk && m.contains(line, column)
+        });
+        let module = candidates.next()?;
+        if column.is_none() && candidates.next().is_some() {
+            return None;
+        }
+        Some((module, module.original_line_at(line)))
+    }
+
+    /// Rewrites every `chunk:line[:column]` location in an error message or
+    /// traceback into `path:line` of the original script, or just `path` when
+    /// the original line is unknown.
+    ///
+    /// Both `[string "chunk"]:line` and bare `chunk:line` locations are
+    /// recognized; the column is only used to find the script, since it no
+    /// longer applies. Locations outside of any script are left untouched.
+    #[must_use]
+    pub fn symbolicate(&self, trace: &str) -> String {
+        let mut out = String::with_capacity(trace.len());
+        let mut rest = trace;
+
+        while let Some(location) = find_location(rest) {
+            out.push_str(&rest[..location.start]);
+
+            match self.resolve(location.chunk, location.line, location.column) {
+                Some((module, original)) => {
+                    out.push_str(&module.path);
+                    if let Some(original) = original {
+                        out.push(':');
+                        out.push_str(&original.to_string());
+                    }
+                }
+                None => out.push_str(&rest[location.start..location.end]),
+            }
+
+            rest = &rest[location.end..];
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
+
+/// A `chunk:line[:column]` location found in a traceback.
+struct Location<'a> {
+    start: usize,
+    end: usize,
+    chunk: &'a str,
+    line: usize,
+    column: Option<usize>,
+}
+
+/// Finds the next location in the text.
+fn find_location(text: &str) -> Option<Location<'_>> {
+    let bytes = text.as_bytes();
+    let mut search_from = 0;
+
+    while let Some(colon) = text[search_from..].find(':').map(|i| i + search_from) {
+        search_from = colon + 1;
+
+        let digits = count_digits(&bytes[colon + 1..]);
+        if digits == 0 {
+            continue;
+        }
+        let Ok(line) = text[colon + 1..colon + 1 + digits].parse() else {
+            continue;
+        };
+
+        let Some((start, chunk)) = chunk_before(text, colon) else {
+            continue;
+        };
+
+        let mut end = colon + 1 + digits;
+        let mut column = None;
+        if bytes.get(end) == Some(&b':') {
+            let column_digits = count_digits(&bytes[end + 1..]);
+            if column_digits > 0 {
+                column = text[end + 1..end + 1 + column_digits].parse().ok();
+                end += 1 + column_digits;
+            }
+        }
+
+        return Some(Location {
+            start,
+            end,
+            chunk,
+            line,
+            column,
+        });
+    }
+
+    None
+}
+
+/// Counts the leading ASCII digits of a byte slice.
+fn count_digits(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// Returns the start offset and name of the chunk ending right before `colon`.
+fn chunk_before(text: &str, colon: usize) -> Option<(usize, &str)> {
+    let before = &text[..colon];
+
+    if let Some(quoted) = before.strip_suffix("\"]") {
+        let open = quoted.rfind("[string \"")?;
+        return Some((open, &quoted[open + "[string \"".len()..]));
+    }
+
+    let start = before
+        .rfind(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '\'' | '"' | ','))
+        .map_or(0, |i| i + 1);
+    let chunk = &before[start..];
+    (!chunk.is_empty()).then_some((start, chunk))
+}
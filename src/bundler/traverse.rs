@@ -1,69 +1,286 @@
-use anyhow::{Context, Result};
-use rbx_dom_weak::WeakDom;
-
-use super::escape::to_luau_string;
-use super::types::Mode;
-use super::writer::{write_instance, write_script};
-
-/// Recursively processes an instance and its children, writing to the output buffer.
-pub(crate) fn process_instance(
-    dom: &WeakDom,
-    output: &mut String,
-    mode: Mode,
-    referent: rbx_dom_weak::types::Ref,
-    full_path: &str,
-    parent_path_quoted: &str,
-    darklua_config: Option<&str>,
-) -> Result<()> {
-    let instance = dom
-        .get_by_ref(referent)
-        .context("Referent missing from DOM tree")?;
-
-    let current_path_quoted = to_luau_string(full_path);
-
-    match instance.class.as_str() {
-        "LocalScript" | "ModuleScript" => write_script(
-            output,
-            instance,
-            &instance.class,
-            &current_path_quoted,
-            parent_path_quoted,
-            mode,
-            darklua_config,
-        )?,
-        _ => write_instance(
-            output,
-            instance,
-            &instance.class,
-            &current_path_quoted,
-            parent_path_quoted,
-        )?,
-    }
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, WeakDom};
+
+use super::diagnostics::{Diagnostic, DiagnosticCode};
+use super::error::BundlerError;
+use super::minify::Minifier;
+use super::properties::{write_properties, DeferredRefs};
+use super::sourcemap::ModuleMapping;
+use super::types::{RunContext, StartPolicy};
+use super::writer::{write_instance, write_script, ScriptEmbedding, SourceSpan, CHUNK_SOURCE_LINE};
+
+/// What the instance tree contributed to the output, for size reports.
+#[derive(Debug, Default)]
+pub(crate) struct TreeLayout {
+    /// Every registered instance, in registration order.
+    pub nodes: Vec<NodeSpan>,
+    /// Number of top-level statements after the last instance: reference
+    /// assignments and the `__start` call.
+    pub trailing_statements: usize,
+}
+
+/// The bytes an instance's registration and properties span in the output.
+#[derive(Debug)]
+pub(crate) struct NodeSpan {
+    pub referent: Ref,
+    /// Index of the parent in [`TreeLayout::nodes`], `None` for top-level instances.
+    pub parent: Option<usize>,
+    pub path: String,
+    pub class: String,
+    /// Size of the original source of a bundled script, `None` for other instances.
+    pub source_bytes: Option<usize>,
+    /// Byte offset of the registration.
+    pub start: usize,
+    /// Byte offset right after the properties.
+    pub end: usize,
+    /// Number of top-level statements the registration and properties take.
+    pub statements: usize,
+}
+
+/// Walks the instance tree, writing registrations into an output buffer.
+///
+/// Every instance is assigned a numeric registry ID in traversal order, so the
+/// runtime never confuses same-named siblings or names containing dots.
+///
+/// Holds the state that must outlive a single instance, such as reference
+/// properties that can only be resolved once the whole tree is registered,
+/// the scripts `__start` should launch, and where each script's source landed.
+pub(crate) struct TreeWriter<'a> {
+    dom: &'a WeakDom,
+    embedding: ScriptEmbedding,
+    darklua: Option<Minifier<'a>>,
+    start_policy: StartPolicy,
+    /// Modules written as plain instances, without their source.
+    stripped: &'a HashSet<Ref>,
+    /// Whether scripts under the current root may be launched.
+    launch_root: bool,
+    next_id: u32,
+    refs: DeferredRefs,
+    launched: Vec<u32>,
+    modules: Vec<ModuleMapping>,
+    layout: TreeLayout,
+    /// Number of newlines in the output up to `counted_bytes`, so line numbers
+    /// are found without rescanning the whole output for every script.
+    counted_lines: usize,
+    counted_bytes: usize,
+}
 
-    // Reuse a buffer for child paths to avoid per-child allocations
-    let mut child_path_buf = String::with_capacity(full_path.len() + 64);
-    
-    for child_ref in instance.children() {
-        let child = dom
-            .get_by_ref(*child_ref)
-            .context("Child reference missing")?;
-
-        // Build child path by reusing buffer
-        child_path_buf.clear();
-        child_path_buf.push_str(full_path);
-        child_path_buf.push('.');
-        child_path_buf.push_str(&child.name);
-        
-        process_instance(
+impl<'a> TreeWriter<'a> {
+    pub(crate) fn new(
+        dom: &'a WeakDom,
+        embedding: ScriptEmbedding,
+        darklua: Option<Minifier<'a>>,
+        start_policy: StartPolicy,
+        stripped: &'a HashSet<Ref>,
+    ) -> Self {
+        Self {
             dom,
-            output,
-            mode,
-            *child_ref,
-            &child_path_buf,
-            &current_path_quoted,
-            darklua_config,
-        )?;
+            embedding,
+            darklua,
+            start_policy,
+            stripped,
+            launch_root: true,
+            next_id: 1,
+            refs: DeferredRefs::default(),
+            launched: Vec::new(),
+            modules: Vec::new(),
+            layout: TreeLayout::default(),
+            counted_lines: 0,
+            counted_bytes: 0,
+        }
+    }
+
+    /// Processes a top-level instance, optionally launching the scripts beneath it.
+    pub(crate) fn process_root(
+        &mut self,
+        output: &mut String,
+        referent: Ref,
+        name: &str,
+        launch: bool,
+    ) -> Result<(), BundlerError> {
+        self.launch_root = launch;
+        self.process_instance(output, referent, name, None)
+    }
+
+    /// Recursively processes an instance and its children, writing to the output buffer.
+    pub(crate) fn process_instance(
+        &mut self,
+        output: &mut String,
+        referent: Ref,
+        full_path: &str,
+        parent_id: Option<u32>,
+    ) -> Result<(), BundlerError> {
+        let dom = self.dom;
+        let instance = dom
+            .get_by_ref(referent)
+            .ok_or_else(|| BundlerError::Internal("Referent missing from DOM tree".to_string()))?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let start = output.len();
+
+        let source_bytes = match instance.class.as_str() {
+            "Script" | "LocalScript" | "ModuleScript" if !self.stripped.contains(&referent) => {
+                let span = write_script(
+                    output,
+                    instance,
+                    id,
+                    parent_id,
+                    full_path,
+                    self.embedding,
+                    self.darklua.as_ref(),
+                )?;
+                self.record_module(output, instance, id, full_path, &span);
+                Some(span.source_bytes)
+            }
+            _ => {
+                write_instance(output, instance, id, parent_id)?;
+                None
+            }
+        };
+
+        if self.launch_root && launches(instance, self.start_policy) {
+            self.launched.push(id);
+        }
+
+        self.refs.register(referent, id);
+        let registration_end = output.len();
+        write_properties(output, instance, id, &mut self.refs);
+        self.layout.nodes.push(NodeSpan {
+            referent,
+            // Registry IDs are assigned in the same order, starting at 1
+            parent: parent_id.map(|id| id as usize - 1),
+            path: full_path.to_string(),
+            class: instance.class.to_string(),
+            source_bytes,
+            start,
+            end: output.len(),
+            statements: if output.len() > registration_end { 2 } else { 1 },
+        });
+
+        // Reuse a buffer for child paths to avoid per-child allocations
+        let mut child_path_buf = String::with_capacity(full_path.len() + 64);
+
+        for child_ref in instance.children() {
+            let child = dom
+                .get_by_ref(*child_ref)
+                .ok_or_else(|| BundlerError::Internal("Child reference missing".to_string()))?;
+
+            // Build child path by reusing buffer
+            child_path_buf.clear();
+            child_path_buf.push_str(full_path);
+            child_path_buf.push('.');
+            child_path_buf.push_str(&child.name);
+
+            self.process_instance(output, *child_ref, &child_path_buf, Some(id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Records where a script's source was placed for the source map.
+    fn record_module(
+        &mut self,
+        output: &str,
+        instance: &Instance,
+        id: u32,
+        full_path: &str,
+        span: &SourceSpan,
+    ) {
+        let (chunk, (start_line, start_column), (end_line, end_column)) = match &span.inline {
+            Some(range) => {
+                let start = self.position_at(output, range.start);
+                let end = self.position_at(output, range.end.max(range.start + 1) - 1);
+                (None, start, end)
+            }
+            None => (
+                Some(full_path.to_string()),
+                (CHUNK_SOURCE_LINE, 1),
+                (CHUNK_SOURCE_LINE + span.lines - 1, span.last_line_bytes.max(1)),
+            ),
+        };
+
+        // Dev builds run darklua over every script, which may not keep its lines
+        let retains_lines = self.darklua.as_ref().is_none_or(Minifier::retains_lines);
+        self.modules.push(ModuleMapping {
+            id,
+            path: full_path.to_string(),
+            class: instance.class.to_string(),
+            chunk,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+            original_line: retains_lines.then_some(1),
+        });
+    }
+
+    /// Returns the 1-based line and column of a byte offset in the output.
+    ///
+    /// Offsets must be passed in increasing order.
+    fn position_at(&mut self, output: &str, offset: usize) -> (usize, usize) {
+        let newlines = output.as_bytes()[self.counted_bytes..offset]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        self.counted_lines += newlines;
+        self.counted_bytes = offset;
+        let line_start = output[..offset].rfind('\n').map_or(0, |i| i + 1);
+        (self.counted_lines + 1, offset - line_start + 1)
+    }
+
+    /// Writes the reference properties collected during traversal, followed
+    /// by the `__start` call listing the scripts to launch.
+    ///
+    /// Returns the location of every script and the layout of the tree,
+    /// relative to `output`, along with a warning for every reference that
+    /// had to be dropped.
+    pub(crate) fn finish(mut self, output: &mut String) -> (Vec<ModuleMapping>, TreeLayout, Vec<Diagnostic>) {
+        let refs_start = output.len();
+        let diagnostics: Vec<Diagnostic> = self
+            .refs
+            .write(output)
+            .into_iter()
+            .map(|(holder_id, property)| {
+                // Registry IDs index the layout, starting at 1
+                let holder = &self.layout.nodes[holder_id as usize - 1].path;
+                Diagnostic::warning(
+                    DiagnosticCode::UnresolvedReference,
+                    holder,
+                    format!("{property} references an instance outside the bundle and was left unset"),
+                )
+            })
+            .collect();
+
+        output.push_str("__start({");
+        for (i, id) in self.launched.iter().enumerate() {
+            output.push_str(if i == 0 { " " } else { ", " });
+            let _ = write!(output, "{id}");
+        }
+        output.push_str(if self.launched.is_empty() { "})\n" } else { " })\n" });
+
+        // Every reference assignment and the `__start` call take a line each
+        self.layout.trailing_statements = output[refs_start..].matches('\n').count();
+        (self.modules, self.layout, diagnostics)
+    }
+}
+
+/// Returns whether `__start` launches a script under a start policy.
+pub(crate) fn launches(instance: &Instance, start_policy: StartPolicy) -> bool {
+    let property = |name: &str| instance.properties.get(&name.into());
+
+    let disabled = matches!(property("Disabled"), Some(Variant::Bool(true)))
+        || matches!(property("Enabled"), Some(Variant::Bool(false)));
+    if disabled {
+        return false;
     }
 
-    Ok(())
+    let run_context = match property("RunContext") {
+        Some(Variant::Enum(value)) => RunContext::from_u32(value.to_u32()),
+        _ => RunContext::Legacy,
+    };
+    start_policy.launches(&instance.class, run_context)
 }
@@ -1,9 +1,21 @@
 //! Core types for the bundler library.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
+use serde::{Serialize, Serializer};
+
+use super::darklua::{DarkluaOverride, DefineValue};
+use super::diagnostics::{Diagnostic, Severity};
+use super::input::InputKind;
+use super::report::SizeReport;
+use super::shake::TreeShakeReport;
+use super::sourcemap::SourceMap;
+
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -25,54 +37,352 @@ impl fmt::Display for Mode {
     }
 }
 
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "development" => Ok(Mode::Development),
+            "production" => Ok(Mode::Production),
+            _ => Err(format!("unknown mode '{s}' (expected development or production)")),
+        }
+    }
+}
+
 /// Target configuration for a bundle output.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub enum Target {
     /// Development build without compatibility shims.
     Dev,
     /// Development build with compatibility shims.
     DevCompat,
+    /// Development build with scripts inlined instead of loaded with `loadstring`.
+    DevInline,
+    /// Development build with inlined scripts and compatibility shims.
+    DevInlineCompat,
     /// Release/production build without compatibility shims.
     Rel,
     /// Release/production build with compatibility shims.
     RelCompat,
+    /// User-defined target, see [`CustomTarget`].
+    Custom(Arc<CustomTarget>),
+}
+
+impl PartialEq for Target {
+    /// Targets are equal when they have the same name.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Target::Custom(a), Target::Custom(b)) => a.name == b.name,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
 }
 
+impl Eq for Target {}
+
 impl fmt::Display for Target {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Target::Dev => write!(f, "dev"),
             Target::DevCompat => write!(f, "dev-compat"),
+            Target::DevInline => write!(f, "dev-inline"),
+            Target::DevInlineCompat => write!(f, "dev-inline-compat"),
             Target::Rel => write!(f, "rel"),
             Target::RelCompat => write!(f, "rel-compat"),
+            Target::Custom(custom) => write!(f, "{}", custom.name),
         }
     }
 }
 
+/// Targets serialize as their name.
+impl Serialize for Target {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parses a built-in target from its CLI name (e.g. `rel-compat`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|target| target.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = Self::ALL.iter().map(ToString::to_string).collect();
+                format!("unknown target '{s}' (expected one of: {})", names.join(", "))
+            })
+    }
+}
+
 impl Target {
+    /// Every built-in target.
+    pub const ALL: [Target; 6] = [
+        Target::Dev,
+        Target::DevCompat,
+        Target::DevInline,
+        Target::DevInlineCompat,
+        Target::Rel,
+        Target::RelCompat,
+    ];
+
+    /// Creates a target from a user-defined configuration.
+    #[must_use]
+    pub fn custom(target: CustomTarget) -> Self {
+        Target::Custom(Arc::new(target))
+    }
+
     /// Returns the build mode for this target.
     #[must_use]
-    pub const fn mode(&self) -> Mode {
+    pub fn mode(&self) -> Mode {
         match self {
-            Target::Dev | Target::DevCompat => Mode::Development,
+            Target::Dev | Target::DevCompat | Target::DevInline | Target::DevInlineCompat => {
+                Mode::Development
+            }
             Target::Rel | Target::RelCompat => Mode::Production,
+            Target::Custom(custom) => custom.mode,
         }
     }
 
     /// Returns whether this target uses compatibility shims.
     #[must_use]
-    pub const fn compat(&self) -> bool {
-        matches!(self, Target::DevCompat | Target::RelCompat)
+    pub fn compat(&self) -> bool {
+        match self {
+            Target::Custom(custom) => custom.compat,
+            _ => matches!(
+                self,
+                Target::DevCompat | Target::DevInlineCompat | Target::RelCompat
+            ),
+        }
+    }
+
+    /// Returns whether scripts are compiled at runtime with `loadstring`.
+    ///
+    /// Other targets inline every script as a function in the bundle.
+    #[must_use]
+    pub fn uses_loadstring(&self) -> bool {
+        match self {
+            Target::Custom(custom) => custom.mode == Mode::Development && custom.loadstring,
+            _ => matches!(self, Target::Dev | Target::DevCompat),
+        }
     }
 
     /// Returns the file suffix for this target.
     #[must_use]
-    pub const fn file_suffix(&self) -> &'static str {
+    pub fn file_suffix(&self) -> &str {
         match self {
             Target::Dev => "debug",
             Target::DevCompat => "debug.c",
+            Target::DevInline => "debug.inline",
+            Target::DevInlineCompat => "debug.inline.c",
             Target::Rel => "release",
             Target::RelCompat => "release.c",
+            Target::Custom(custom) => &custom.file_suffix,
+        }
+    }
+}
+
+/// A user-defined target, derived from one of the built-in targets.
+///
+/// The base target provides the mode, compatibility shims, script embedding
+/// and darklua configuration, each of which can then be changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomTarget {
+    /// Name of the target (e.g. `rel-lua51`), used on the command line and for `{target}`.
+    pub name: String,
+    /// Build mode; also selects the built-in darklua configuration.
+    pub mode: Mode,
+    /// Whether compatibility shims are used; also selects the built-in darklua configuration.
+    pub compat: bool,
+    /// Whether development builds compile scripts with `loadstring` instead of inlining them.
+    pub loadstring: bool,
+    /// File suffix used for `{suffix}` in output names.
+    pub file_suffix: String,
+    /// Darklua configurations applied to the built-in one, before those in [`BuildConfig`].
+    pub darklua_overrides: Vec<DarkluaOverride>,
+    /// Global values injected into every script, taking precedence over those in [`BuildConfig`].
+    pub defines: BTreeMap<String, DefineValue>,
+}
+
+impl CustomTarget {
+    /// Create a target named `name` with the settings of a built-in target.
+    ///
+    /// The file suffix defaults to the name.
+    pub fn new(name: String, base: &Target) -> Self {
+        Self {
+            file_suffix: name.clone(),
+            name,
+            mode: base.mode(),
+            compat: base.compat(),
+            loadstring: base.uses_loadstring(),
+            darklua_overrides: Vec::new(),
+            defines: BTreeMap::new(),
+        }
+    }
+
+    /// Set the build mode.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set whether compatibility shims are used.
+    pub fn with_compat(mut self, compat: bool) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// Set whether development builds compile scripts with `loadstring`.
+    pub fn with_loadstring(mut self, loadstring: bool) -> Self {
+        self.loadstring = loadstring;
+        self
+    }
+
+    /// Set the file suffix.
+    pub fn with_file_suffix(mut self, file_suffix: String) -> Self {
+        self.file_suffix = file_suffix;
+        self
+    }
+
+    /// Add a darklua configuration that replaces or extends the built-in one.
+    pub fn with_darklua_override(mut self, darklua: DarkluaOverride) -> Self {
+        self.darklua_overrides.push(darklua);
+        self
+    }
+
+    /// Define a global value injected into every script of this target.
+    pub fn with_define(mut self, name: String, value: DefineValue) -> Self {
+        self.defines.insert(name, value);
+        self
+    }
+}
+
+/// Which scripts `__start` launches once the instance tree is built.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum StartPolicy {
+    /// Client-side scripts: `LocalScript`s and `Script`s with a `Client` run context.
+    #[default]
+    Client,
+    /// Server-side scripts: `Script`s with a `Legacy` or `Server` run context.
+    Server,
+    /// Every enabled script, regardless of run context.
+    All,
+    /// No scripts; modules are only registered for `require`.
+    None,
+}
+
+impl fmt::Display for StartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPolicy::Client => write!(f, "client"),
+            StartPolicy::Server => write!(f, "server"),
+            StartPolicy::All => write!(f, "all"),
+            StartPolicy::None => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for StartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(StartPolicy::Client),
+            "server" => Ok(StartPolicy::Server),
+            "all" => Ok(StartPolicy::All),
+            "none" => Ok(StartPolicy::None),
+            _ => Err(format!(
+                "unknown start policy '{s}' (expected one of: client, server, all, none)"
+            )),
+        }
+    }
+}
+
+impl StartPolicy {
+    /// Returns whether a script of the given class and `RunContext` should be launched.
+    ///
+    /// `Plugin` run contexts are never launched, and module scripts are never
+    /// launched since they only run when required.
+    #[must_use]
+    pub fn launches(&self, class_name: &str, run_context: RunContext) -> bool {
+        let is_client = match (class_name, run_context) {
+            ("LocalScript", _) => true,
+            ("Script", RunContext::Client) => true,
+            ("Script", RunContext::Legacy | RunContext::Server) => false,
+            _ => return false,
+        };
+
+        match self {
+            StartPolicy::Client => is_client,
+            StartPolicy::Server => !is_client,
+            StartPolicy::All => true,
+            StartPolicy::None => false,
+        }
+    }
+}
+
+/// How modules that no launched script can require are dropped from the bundle.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TreeShaking {
+    /// Every module is bundled.
+    #[default]
+    Off,
+    /// Unreachable modules are removed along with their instances, unless
+    /// they contain another script.
+    Remove,
+    /// Unreachable modules keep their instances but lose their source.
+    KeepInstances,
+}
+
+impl fmt::Display for TreeShaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeShaking::Off => write!(f, "off"),
+            TreeShaking::Remove => write!(f, "remove"),
+            TreeShaking::KeepInstances => write!(f, "keep-instances"),
+        }
+    }
+}
+
+impl FromStr for TreeShaking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(TreeShaking::Off),
+            "remove" => Ok(TreeShaking::Remove),
+            "keep-instances" => Ok(TreeShaking::KeepInstances),
+            _ => Err(format!(
+                "unknown tree shaking mode '{s}' (expected one of: off, remove, keep-instances)"
+            )),
+        }
+    }
+}
+
+/// The `RunContext` of a script.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum RunContext {
+    /// Runs according to its class and location.
+    #[default]
+    Legacy,
+    /// Runs on the server.
+    Server,
+    /// Runs on the client.
+    Client,
+    /// Runs in plugins.
+    Plugin,
+}
+
+impl RunContext {
+    /// Converts the raw `Enum.RunContext` value.
+    #[must_use]
+    pub const fn from_u32(value: u32) -> Self {
+        match value {
+            1 => RunContext::Server,
+            2 => RunContext::Client,
+            3 => RunContext::Plugin,
+            _ => RunContext::Legacy,
         }
     }
 }
@@ -80,14 +390,40 @@ impl Target {
 /// Configuration for a build operation.
 #[derive(Debug, Clone)]
 pub struct BuildConfig {
-    /// Path to the input model file (.rbxm).
+    /// Path to the input model or place file (.rbxm, .rbxmx, .rbxl or .rbxlx),
+    /// or a Rojo project (`*.project.json` or a directory containing `default.project.json`).
     pub input: PathBuf,
+    /// How the input path is interpreted.
+    pub input_kind: InputKind,
     /// Output directory for generated bundles.
     pub out_dir: PathBuf,
     /// One or more build targets.
     pub targets: Vec<Target>,
     /// Optional path to a custom header file content.
     pub header_content: Option<String>,
+    /// Names of the top-level instances whose scripts are launched on start.
+    /// All top-level instances are bundled; an empty list launches every one of them.
+    pub entry_points: Vec<String>,
+    /// Which scripts are launched on start.
+    pub start_policy: StartPolicy,
+    /// Optional dotted path (e.g. `ReplicatedStorage.Client`) selecting the subtree to bundle.
+    pub root_path: Option<String>,
+    /// User-supplied darklua configurations, applied in order to their target's built-in one.
+    pub darklua_overrides: Vec<(Target, DarkluaOverride)>,
+    /// Global values injected into every script at build time.
+    pub defines: BTreeMap<String, DefineValue>,
+    /// Optional output file name template; see [`BuildConfig::with_output_name`].
+    pub output_name: Option<String>,
+    /// Whether require issues are reported; see [`BuildConfig::with_check_requires`].
+    pub check_requires: bool,
+    /// How unreachable modules are dropped; see [`BuildConfig::with_tree_shaking`].
+    pub tree_shaking: TreeShaking,
+    /// Whether a size report is produced for every target; see [`BuildConfig::with_size_report`].
+    pub size_report: bool,
+    /// Whether an HTML analyzer page is produced for every target; see [`BuildConfig::with_analyzer`].
+    pub analyzer: bool,
+    /// Whether warnings fail their targets; see [`BuildConfig::with_deny_warnings`].
+    pub deny_warnings: bool,
 }
 
 impl BuildConfig {
@@ -95,12 +431,30 @@ impl BuildConfig {
     pub fn new(input: PathBuf, out_dir: PathBuf) -> Self {
         Self {
             input,
+            input_kind: InputKind::Auto,
             out_dir,
             targets: vec![Target::Dev],
             header_content: None,
+            entry_points: Vec::new(),
+            start_policy: StartPolicy::Client,
+            root_path: None,
+            darklua_overrides: Vec::new(),
+            defines: BTreeMap::new(),
+            output_name: None,
+            check_requires: true,
+            tree_shaking: TreeShaking::Off,
+            size_report: false,
+            analyzer: false,
+            deny_warnings: false,
         }
     }
 
+    /// Set how the input path is interpreted.
+    pub fn with_input_kind(mut self, input_kind: InputKind) -> Self {
+        self.input_kind = input_kind;
+        self
+    }
+
     /// Set the targets to build.
     pub fn with_targets(mut self, targets: Vec<Target>) -> Self {
         self.targets = targets;
@@ -112,33 +466,170 @@ impl BuildConfig {
         self.header_content = Some(header);
         self
     }
+
+    /// Set the top-level instances to use as entry points.
+    pub fn with_entry_points(mut self, entry_points: Vec<String>) -> Self {
+        self.entry_points = entry_points;
+        self
+    }
+
+    /// Set which scripts are launched on start.
+    pub fn with_start_policy(mut self, start_policy: StartPolicy) -> Self {
+        self.start_policy = start_policy;
+        self
+    }
+
+    /// Set the dotted instance path of the subtree to bundle.
+    pub fn with_root_path(mut self, root_path: String) -> Self {
+        self.root_path = Some(root_path);
+        self
+    }
+
+    /// Add a darklua configuration that replaces or extends a target's built-in one.
+    ///
+    /// Overrides for the same target are applied in the order they were added.
+    pub fn with_darklua_override(mut self, target: Target, darklua: DarkluaOverride) -> Self {
+        self.darklua_overrides.push((target, darklua));
+        self
+    }
+
+    /// Define a global value injected into every script at build time.
+    ///
+    /// References to the global are replaced with the value, so release builds
+    /// can drop branches that depend on it.
+    pub fn with_define(mut self, name: String, value: DefineValue) -> Self {
+        self.defines.insert(name, value);
+        self
+    }
+
+    /// Set the output file name template.
+    ///
+    /// `{name}` is replaced with the input name, `{target}` with the target
+    /// name (e.g. `rel-compat`) and `{suffix}` with the target's file suffix
+    /// (e.g. `release.c`). Defaults to `{name}.{suffix}.lua`.
+    pub fn with_output_name(mut self, template: String) -> Self {
+        self.output_name = Some(template);
+        self
+    }
+
+    /// Set whether require issues are reported.
+    ///
+    /// Before writing anything, the `require` calls of every script are
+    /// resolved statically. Unresolvable requires and requires of instances
+    /// that are not `ModuleScript`s are reported as warnings, and modules
+    /// requiring each other while loading as errors that fail every target.
+    /// Enabled by default.
+    pub fn with_check_requires(mut self, check_requires: bool) -> Self {
+        self.check_requires = check_requires;
+        self
+    }
+
+    /// Set how modules that are never required are dropped.
+    ///
+    /// The require graph is followed from the scripts `__start` launches; a
+    /// `ModuleScript` it never reaches is bundled without its source. Requires
+    /// that cannot be followed statically keep every module next to the
+    /// requiring script. Disabled by default.
+    pub fn with_tree_shaking(mut self, tree_shaking: TreeShaking) -> Self {
+        self.tree_shaking = tree_shaking;
+        self
+    }
+
+    /// Set whether a size report is produced for every target.
+    ///
+    /// The report breaks the bundle down into its header, runtime shim,
+    /// instance tree and the bytes each script contributes, and is written
+    /// next to the output as `<output>.size.json`. Disabled by default.
+    pub fn with_size_report(mut self, size_report: bool) -> Self {
+        self.size_report = size_report;
+        self
+    }
+
+    /// Set whether an HTML analyzer page is produced for every target.
+    ///
+    /// The page is a self-contained treemap of the instance tree, each
+    /// instance sized by the bytes it contributes to the bundle, with the
+    /// source of every script. It is written next to the output as
+    /// `<output>.analyzer.html`. Disabled by default.
+    pub fn with_analyzer(mut self, analyzer: bool) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+
+    /// Set whether warnings are promoted to errors.
+    ///
+    /// Warnings about the input then fail every target, and warnings raised
+    /// while generating a target fail that target; failed targets are not
+    /// written. Disabled by default.
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
 }
 
 /// Result of building a single target.
-#[derive(Debug, Clone)]
+///
+/// Serializes with paths as strings and the duration as `duration_ms`.
+#[derive(Debug, Clone, Serialize)]
 pub struct TargetResult {
     /// The target that was built.
     pub target: Target,
     /// Path to the output file.
     pub output_file: PathBuf,
+    /// Path to the source map written next to the output file.
+    pub source_map_file: PathBuf,
     /// Whether the build succeeded.
     pub success: bool,
     /// Error message if the build failed.
     pub error_message: Option<String>,
+    /// Size of the output file; 0 if the build failed.
+    pub output_bytes: usize,
+    /// Hash of the output file, if the build succeeded.
+    pub hash: Option<String>,
+    /// Size breakdown of the output, if enabled and the build succeeded.
+    pub size_report: Option<SizeReport>,
+    /// Path to the analyzer page, if enabled and the build succeeded.
+    pub analyzer_file: Option<PathBuf>,
+    /// Diagnostics raised while generating this target, such as dropped references.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Time spent building this target.
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
 }
 
 /// Result of a complete build operation.
-#[derive(Debug)]
+///
+/// Serializes with paths as strings and the duration as `duration_ms`.
+#[derive(Debug, Serialize)]
 pub struct BuildResult {
     /// Path to the input file.
     pub input_path: PathBuf,
     /// Results for each target.
+    #[serde(rename = "targets")]
     pub target_results: Vec<TargetResult>,
+    /// Diagnostics about the input, shared by every target.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Modules dropped by tree shaking, if enabled.
+    pub tree_shaking: Option<TreeShakeReport>,
     /// Total duration of the build.
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
     pub duration: Duration,
 }
 
 impl BuildResult {
+    /// Returns the diagnostics about the input followed by those of every target.
+    pub fn all_diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .chain(self.target_results.iter().flat_map(|r| &r.diagnostics))
+    }
+
+    /// Returns the number of diagnostics of a severity, across the input and all targets.
+    #[must_use]
+    pub fn diagnostic_count(&self, severity: Severity) -> usize {
+        self.all_diagnostics().filter(|d| d.severity == severity).count()
+    }
+
     /// Returns true if all targets built successfully.
     #[must_use]
     pub fn is_success(&self) -> bool {
@@ -161,6 +652,66 @@ impl BuildResult {
     }
 }
 
+/// A target built in memory by [`build_in_memory`](super::build_in_memory) or
+/// [`build_from_dom`](super::build_from_dom).
+#[derive(Debug, Clone)]
+pub struct TargetBundle {
+    /// The target that was built.
+    pub target: Target,
+    /// File name [`build`](super::build) would write the bundle to.
+    pub file_name: String,
+    /// Generated bundle; empty if the build failed.
+    pub source: String,
+    /// Source map of the bundle; without modules if the build failed.
+    pub source_map: SourceMap,
+    /// Size breakdown of the bundle, if enabled and the build succeeded.
+    pub size_report: Option<SizeReport>,
+    /// HTML analyzer page of the bundle, if enabled and the build succeeded.
+    pub analyzer: Option<String>,
+    /// Diagnostics raised while generating this target, such as dropped references.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Whether the build succeeded.
+    pub success: bool,
+    /// Error message if the build failed.
+    pub error_message: Option<String>,
+    /// Time spent building this target.
+    pub duration: Duration,
+}
+
+/// Result of building targets in memory.
+#[derive(Debug)]
+pub struct MemoryBuildResult {
+    /// Path to the input file, which is only named when building from memory.
+    pub input_path: PathBuf,
+    /// Bundles for each target.
+    pub bundles: Vec<TargetBundle>,
+    /// Diagnostics about the input, shared by every target.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Modules dropped by tree shaking, if enabled.
+    pub tree_shaking: Option<TreeShakeReport>,
+    /// Total duration of the build.
+    pub duration: Duration,
+}
+
+impl MemoryBuildResult {
+    /// Returns true if all targets built successfully.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.bundles.iter().all(|b| b.success)
+    }
+
+    /// Returns the number of successful targets.
+    #[must_use]
+    pub fn success_count(&self) -> usize {
+        self.bundles.iter().filter(|b| b.success).count()
+    }
+}
+
+/// Serializes a duration as fractional milliseconds.
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
 /// Shared context for bundler operations.
 pub struct BundlerContext<'a> {
     pub mode: Mode,
@@ -0,0 +1,190 @@
+//! Change detection for watch mode.
+//!
+//! Files are polled for modification times rather than subscribed to, which
+//! behaves the same on every platform and also catches files that editors
+//! and Studio replace instead of writing in place.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+
+use super::input::input_sources;
+use super::types::{BuildConfig, Target};
+
+/// Time between two scans of the watched files.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Time without further changes before a rebuild starts, so that files
+/// written in several steps are only built once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A file or directory a build depends on.
+#[derive(Debug, Clone)]
+pub struct WatchedPath {
+    /// File, or directory watched recursively.
+    pub path: PathBuf,
+    /// Target the file applies to, or `None` for every target.
+    pub target: Option<Target>,
+}
+
+impl WatchedPath {
+    /// A path every target depends on.
+    #[must_use]
+    pub fn all(path: PathBuf) -> Self {
+        Self { path, target: None }
+    }
+}
+
+/// Watches the input of a build, and any extra files, for changes.
+pub struct Watcher {
+    paths: Vec<WatchedPath>,
+    ignored: PathBuf,
+    snapshot: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    /// Creates a watcher for the input of a build and the given extra paths,
+    /// such as configuration files.
+    ///
+    /// The output directory is never watched, so builds do not trigger themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is a Rojo project that cannot be read.
+    pub fn new(config: &BuildConfig, extra: Vec<WatchedPath>) -> Result<Self> {
+        let mut paths: Vec<WatchedPath> = input_sources(&config.input, config.input_kind)?
+            .into_iter()
+            .map(WatchedPath::all)
+            .collect();
+        paths.extend(extra);
+        for watched in &mut paths {
+            watched.path = absolute(&watched.path);
+        }
+
+        let mut watcher = Self {
+            paths,
+            ignored: absolute(&config.out_dir),
+            snapshot: BTreeMap::new(),
+        };
+        watcher.snapshot = watcher.scan();
+        Ok(watcher)
+    }
+
+    /// Returns the watched files and directories.
+    #[must_use]
+    pub fn paths(&self) -> &[WatchedPath] {
+        &self.paths
+    }
+
+    /// Returns the files created, modified or deleted since the last poll.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let snapshot = self.scan();
+
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        for (path, modified) in &snapshot {
+            if self.snapshot.get(path) != Some(modified) {
+                changed.insert(path.clone());
+            }
+        }
+        for path in self.snapshot.keys() {
+            if !snapshot.contains_key(path) {
+                changed.insert(path.clone());
+            }
+        }
+
+        self.snapshot = snapshot;
+        changed.into_iter().collect()
+    }
+
+    /// Blocks until files change, then until they have stopped changing,
+    /// and returns every file that changed in the meantime.
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut last_change: Option<Instant> = None;
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let polled = self.poll();
+            if !polled.is_empty() {
+                changed.extend(polled);
+                last_change = Some(Instant::now());
+            } else if last_change.is_some_and(|time| time.elapsed() >= DEBOUNCE) {
+                return changed.into_iter().collect();
+            }
+        }
+    }
+
+    /// Returns the targets, among `targets`, that depend on any of the changed files.
+    #[must_use]
+    pub fn affected_targets(&self, changed: &[PathBuf], targets: &[Target]) -> Vec<Target> {
+        let mut affected: Vec<&Target> = Vec::new();
+
+        for path in changed {
+            let mut owners = self.paths.iter().filter(|watched| path.starts_with(&watched.path)).peekable();
+            if owners.peek().is_none() {
+                return targets.to_vec();
+            }
+
+            for watched in owners {
+                let Some(target) = &watched.target else {
+                    return targets.to_vec();
+                };
+                affected.extend(targets.iter().filter(|t| *t == target));
+            }
+        }
+
+        targets.iter().filter(|t| affected.contains(t)).cloned().collect()
+    }
+
+    /// Records the modification time of every watched file.
+    fn scan(&self) -> BTreeMap<PathBuf, SystemTime> {
+        let mut snapshot = BTreeMap::new();
+        for watched in &self.paths {
+            self.scan_path(&watched.path, &mut snapshot);
+        }
+        snapshot
+    }
+
+    /// Records a file, or every file under a directory.
+    ///
+    /// Hidden entries are skipped, and only `@rbxts` packages are scanned in
+    /// `node_modules` since nothing else in it is bundled.
+    fn scan_path(&self, path: &Path, snapshot: &mut BTreeMap<PathBuf, SystemTime>) {
+        if path.starts_with(&self.ignored) {
+            return;
+        }
+        let Ok(metadata) = fs::metadata(path) else {
+            return;
+        };
+
+        if !metadata.is_dir() {
+            if let Ok(modified) = metadata.modified() {
+                snapshot.insert(path.to_path_buf(), modified);
+            }
+            return;
+        }
+
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let in_node_modules = path.file_name().is_some_and(|name| name == "node_modules");
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || (in_node_modules && name != "@rbxts") {
+                continue;
+            }
+            self.scan_path(&entry.path(), snapshot);
+        }
+    }
+}
+
+/// Makes a path absolute, so paths given relative to different directories compare equal.
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,41 +1,73 @@
-use anyhow::Result;
+use std::fmt::Write;
+use std::ops::Range;
+
 use rbx_dom_weak::{types::Variant, Instance};
 
+use super::error::BundlerError;
 use super::escape::append_luau_string;
-use super::types::Mode;
-use super::minify::minify;
+use super::properties::append_metadata;
+use super::minify::Minifier;
+
+/// Appends the registry ID of a parent instance, or `nil` for top-level instances.
+fn push_parent_id(parent_id: Option<u32>, output: &mut String) {
+    match parent_id {
+        Some(id) => {
+            let _ = write!(output, "{id}");
+        }
+        None => output.push_str("nil"),
+    }
+}
 
 /// Writes a non-script instance registration.
 pub(crate) fn write_instance(
     output: &mut String,
     instance: &Instance,
-    class_name: &str,
-    full_path_quoted: &str,
-    parent_path_quoted: &str,
-) -> Result<()> {
+    id: u32,
+    parent_id: Option<u32>,
+) -> Result<(), BundlerError> {
     output.push_str("__rbx(");
     append_luau_string(&instance.name, output);
     output.push_str(", ");
-    append_luau_string(class_name, output);
-    output.push_str(", ");
-    output.push_str(full_path_quoted);
-    output.push_str(", ");
-    output.push_str(parent_path_quoted);
+    append_luau_string(&instance.class, output);
+    let _ = write!(output, ", {id}, ");
+    push_parent_id(parent_id, output);
+    append_metadata(instance, output);
     output.push_str(")\n");
     Ok(())
 }
 
-/// Writes a script registration (LocalScript or ModuleScript).
-pub(crate) fn write_script(
-    output: &mut String,
-    instance: &Instance,
-    class_name: &str,
-    full_path_quoted: &str,
-    parent_path_quoted: &str,
-    mode: Mode,
-    darklua_config: Option<&str>,
-) -> Result<()> {
-    let mut source_code = instance
+/// How a script's source is embedded in its registration.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ScriptEmbedding {
+    /// Compiled at runtime with `loadstring`, under a chunk named after the script's path.
+    Loadstring,
+    /// Inlined as a function wrapped in `__named`, which prefixes its errors
+    /// with the script's path.
+    Inline,
+    /// Inlined as a function, to be minified along with the rest of the bundle.
+    Minified,
+}
+
+/// Where a script's source was placed in the generated code.
+pub(crate) struct SourceSpan {
+    /// Byte range of the source in the output when it is inlined into the
+    /// bundle, or `None` when it is compiled as its own chunk.
+    pub inline: Option<Range<usize>>,
+    /// Number of lines the source spans.
+    pub lines: usize,
+    /// Size of the source's last line.
+    pub last_line_bytes: usize,
+    /// Size of the script's original source, before darklua.
+    pub source_bytes: usize,
+}
+
+/// Line of a `loadstring` chunk on which the script's source begins, after the
+/// line that binds `script` and `require`.
+pub(crate) const CHUNK_SOURCE_LINE: usize = 2;
+
+/// Returns the `Source` of a script, or an empty string if it has none.
+pub(crate) fn script_source(instance: &Instance) -> String {
+    instance
         .properties
         .iter()
         .find(|(k, _)| k.as_str() == "Source")
@@ -44,44 +76,75 @@ pub(crate) fn write_script(
             Variant::BinaryString(b) => String::from_utf8_lossy(b.as_ref()).into_owned(),
             _ => String::new(),
         })
-        .unwrap_or_default();
+        .unwrap_or_default()
+}
+
+/// Writes a script registration (Script, LocalScript or ModuleScript).
+///
+/// The human-readable `full_path` is only used as the chunk name or `__named`
+/// path, so errors point at the script's location in the tree.
+/// Darklua failures are attributed to it as well.
+pub(crate) fn write_script(
+    output: &mut String,
+    instance: &Instance,
+    id: u32,
+    parent_id: Option<u32>,
+    full_path: &str,
+    embedding: ScriptEmbedding,
+    darklua: Option<&Minifier<'_>>,
+) -> Result<SourceSpan, BundlerError> {
+    let mut source_code = script_source(instance);
+    let source_bytes = source_code.len();
 
     // Apply darklua transformations in development mode before stringification
-    if let Some(config) = darklua_config {
-        source_code = minify(&source_code, config)?;
+    if let Some(darklua) = darklua {
+        source_code = darklua.minify(&source_code).map_err(|e| e.in_module(full_path))?;
     }
 
     output.push_str("__lua(");
     append_luau_string(&instance.name, output);
     output.push_str(", ");
-    append_luau_string(class_name, output);
+    append_luau_string(&instance.class, output);
+    let _ = write!(output, ", {id}, ");
+    push_parent_id(parent_id, output);
     output.push_str(", ");
-    output.push_str(full_path_quoted);
-    output.push_str(", ");
-    output.push_str(parent_path_quoted);
-    output.push_str(", function()\n");
+    if embedding == ScriptEmbedding::Inline {
+        output.push_str("__named(");
+        append_luau_string(full_path, output);
+        output.push_str(", ");
+    }
+    output.push_str("function()\n");
 
-    if mode == Mode::Production {
-        output.push_str("\tlocal _=__env(");
-        output.push_str(full_path_quoted);
-        output.push_str(")\n\tlocal script,require=_.script,_.require\n\t");
+    let mut inline = None;
+    if embedding != ScriptEmbedding::Loadstring {
+        let _ = write!(output, "\tlocal _=__env({id})\n\tlocal script,require=_.script,_.require\n\t");
+        inline = Some(output.len()..output.len() + source_code.len());
         output.push_str(&source_code);
         output.push('\n');
     } else {
         // Reserve capacity for the wrapped code to avoid reallocations
-        let mut wrapped_code = String::with_capacity(source_code.len() + full_path_quoted.len() + 64);
-        wrapped_code.push_str("local _=(...)( ");
-        wrapped_code.push_str(full_path_quoted);
-        wrapped_code.push_str(" ) local script,require=_.script,_.require\n");
+        let mut wrapped_code = String::with_capacity(source_code.len() + 64);
+        let _ = write!(wrapped_code, "local _=(...)({id})");
+        wrapped_code.push_str(" local script,require=_.script,_.require\n");
         wrapped_code.push_str(&source_code);
         
         output.push_str("\treturn assert(loadstring(");
         append_luau_string(&wrapped_code, output);
         output.push_str(", ");
-        output.push_str(full_path_quoted);
+        append_luau_string(full_path, output);
         output.push_str("))(__env)\n");
     }
 
-    output.push_str("end)\n");
-    Ok(())
+    output.push_str("end");
+    if embedding == ScriptEmbedding::Inline {
+        output.push(')');
+    }
+    append_metadata(instance, output);
+    output.push_str(")\n");
+    Ok(SourceSpan {
+        inline,
+        lines: source_code.lines().count().max(1),
+        last_line_bytes: source_code.lines().last().map_or(0, str::len),
+        source_bytes,
+    })
 }
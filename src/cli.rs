@@ -1,33 +1,122 @@
 //! Command-line interface definitions.
 
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::bundler::{BuildConfig, Target};
+use crate::assets;
+use crate::bundler::{
+    BuildConfig, DarkluaOverride, DefineValue, InputKind, ProjectConfig, SourceMap, StartPolicy,
+    Target, TreeShaking, WatchedPath, PROJECT_FILE_NAME,
+};
 
-/// CLI-specific target enum that maps to bundler::Target
+/// CLI-specific start policy enum that maps to bundler::StartPolicy
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
-pub enum CliTarget {
-    Dev,
-    DevCompat,
-    Rel,
-    RelCompat,
+pub enum CliStartPolicy {
+    Client,
+    Server,
+    All,
+    None,
 }
 
-impl From<CliTarget> for Target {
-    fn from(value: CliTarget) -> Self {
+impl From<CliStartPolicy> for StartPolicy {
+    fn from(value: CliStartPolicy) -> Self {
         match value {
-            CliTarget::Dev => Self::Dev,
-            CliTarget::DevCompat => Self::DevCompat,
-            CliTarget::Rel => Self::Rel,
-            CliTarget::RelCompat => Self::RelCompat,
+            CliStartPolicy::Client => Self::Client,
+            CliStartPolicy::Server => Self::Server,
+            CliStartPolicy::All => Self::All,
+            CliStartPolicy::None => Self::None,
         }
     }
 }
 
+/// CLI-specific tree shaking mode that maps to bundler::TreeShaking
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum CliTreeShaking {
+    Off,
+    Remove,
+    KeepInstances,
+}
+
+impl From<CliTreeShaking> for TreeShaking {
+    fn from(value: CliTreeShaking) -> Self {
+        match value {
+            CliTreeShaking::Off => Self::Off,
+            CliTreeShaking::Remove => Self::Remove,
+            CliTreeShaking::KeepInstances => Self::KeepInstances,
+        }
+    }
+}
+
+/// How build results are reported.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored text on stderr
+    #[default]
+    Human,
+    /// A JSON document on stdout for every build
+    Json,
+}
+
+/// A darklua configuration file, optionally restricted to one target (`[TARGET=]PATH`).
+///
+/// The prefix is only known to be a target once custom targets are loaded, so
+/// the argument is kept as given until then.
+#[derive(Clone, Debug)]
+pub struct DarkluaArg(pub String);
+
+impl std::str::FromStr for DarkluaArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl DarkluaArg {
+    /// Splits off the target prefix if it names a target, built-in or defined in the project.
+    ///
+    /// An argument whose prefix names no target is only taken as a plain path
+    /// if that whole path is an existing file, so a mistyped target is reported.
+    fn resolve(&self, project: &ProjectConfig) -> Result<(Option<Target>, PathBuf)> {
+        if let Some((prefix, path)) = self.0.split_once('=') {
+            match project.target(prefix) {
+                Ok(target) => return Ok((Some(target), PathBuf::from(path))),
+                Err(_) if Path::new(&self.0).is_file() => {}
+                Err(e) => return Err(e.context(format!("Invalid darklua config '{}'", self.0))),
+            }
+        }
+
+        Ok((None, PathBuf::from(&self.0)))
+    }
+}
+
+/// A global value injected into every script (`NAME=VALUE`).
+#[derive(Clone, Debug)]
+pub struct DefineArg {
+    pub name: String,
+    pub value: DefineValue,
+}
+
+impl std::str::FromStr for DefineArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got '{s}'"))?;
+        let Ok(value) = value.parse();
+
+        Ok(Self {
+            name: name.to_string(),
+            value,
+        })
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(
     author,
@@ -45,7 +134,13 @@ pub struct Cli {
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Build one or more targets into the output directory
-    Build(BuildArgs),
+    Build(Box<BuildArgs>),
+    /// Create a rbxts-bundler.toml project file
+    Init(InitArgs),
+    /// Serve bundles over HTTP, rebuilding them whenever the input changes
+    Serve(Box<ServeArgs>),
+    /// Map locations in an error or traceback back to the original scripts
+    Symbolicate(SymbolicateArgs),
 }
 
 /// Verbosity level for CLI output.
@@ -60,23 +155,128 @@ pub enum Verbosity {
     Normal,
 }
 
+/// Output directory assumed when one is optional and not configured.
+const DEFAULT_OUT_DIR: &str = "dist";
+
 #[derive(clap::Args, Debug, Clone)]
 pub struct BuildArgs {
-    /// Path to the input model file (.rbxm)
-    pub input: PathBuf,
-
-    /// One or more build targets
-    #[arg(short = 't', long = "target", value_enum, default_values_t = [CliTarget::Dev])]
-    pub targets: Vec<CliTarget>,
+    #[command(flatten)]
+    pub options: BuildOptions,
 
     /// Output directory for generated bundles
     #[arg(short = 'o', long = "out-dir")]
-    pub out_dir: PathBuf,
+    pub out_dir: Option<PathBuf>,
+
+    /// Keep running and rebuild the affected targets whenever the input or configuration changes
+    #[arg(short = 'w', long)]
+    pub watch: bool,
+
+    /// Format of the build results
+    #[arg(long = "output-format", value_enum, default_value_t = OutputFormat::Human)]
+    pub output_format: OutputFormat,
+}
+
+impl BuildArgs {
+    /// Convert CLI arguments to a BuildConfig for the bundler library.
+    pub fn to_build_config(&self) -> Result<BuildConfig> {
+        self.options.resolve(self.out_dir.clone(), true)
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub options: BuildOptions,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Port to listen on
+    #[arg(short = 'p', long, default_value_t = 8374)]
+    pub port: u16,
+}
+
+impl ServeArgs {
+    /// Convert CLI arguments to a BuildConfig for the bundler library.
+    ///
+    /// Bundles are only kept in memory, so the output directory is optional.
+    pub fn to_build_config(&self) -> Result<BuildConfig> {
+        self.options.resolve(None, false)
+    }
+}
+
+/// Options shared by the commands that build targets.
+#[derive(clap::Args, Debug, Clone)]
+pub struct BuildOptions {
+    /// Path to the input model, place file or Rojo project (defaults to `input` in the project file)
+    pub input: Option<PathBuf>,
+
+    /// Project file to read instead of rbxts-bundler.toml in the working directory
+    #[arg(short = 'c', long = "config")]
+    pub config: Option<PathBuf>,
+
+    /// One or more build targets: dev, dev-compat, dev-inline, dev-inline-compat, rel,
+    /// rel-compat or a custom target from the project file (default: dev)
+    #[arg(short = 't', long = "target", value_name = "TARGET")]
+    pub targets: Vec<String>,
+
+    /// Output file name template, with {name}, {target} and {suffix} placeholders
+    #[arg(long = "output-name")]
+    pub output_name: Option<String>,
 
     /// Path to a custom header file
     #[arg(long)]
     pub header: Option<PathBuf>,
 
+    /// Treat the input as a roblox-ts project directory (out/, include/, node_modules/@rbxts)
+    #[arg(long)]
+    pub rbxts: bool,
+
+    /// Dotted instance path of the subtree to bundle (e.g. ReplicatedStorage.Client)
+    #[arg(long = "root")]
+    pub root_path: Option<String>,
+
+    /// Top-level instance whose scripts are launched on start (defaults to all)
+    #[arg(short = 'e', long = "entry")]
+    pub entry_points: Vec<String>,
+
+    /// Which scripts are launched on start (default: client)
+    #[arg(long = "start", value_enum)]
+    pub start_policy: Option<CliStartPolicy>,
+
+    /// Darklua config replacing the built-in one, as [TARGET=]PATH (all targets if TARGET is omitted)
+    #[arg(long = "darklua-config", value_name = "[TARGET=]PATH")]
+    pub darklua_configs: Vec<DarkluaArg>,
+
+    /// Darklua config extending the built-in one, as [TARGET=]PATH (all targets if TARGET is omitted)
+    #[arg(long = "darklua-extend", value_name = "[TARGET=]PATH")]
+    pub darklua_extends: Vec<DarkluaArg>,
+
+    /// Global value injected into every script, as NAME=VALUE
+    #[arg(short = 'D', long = "define", value_name = "NAME=VALUE")]
+    pub defines: Vec<DefineArg>,
+
+    /// Skip resolving requires, including the load-time cycle check
+    #[arg(long = "no-check-requires")]
+    pub no_check_requires: bool,
+
+    /// Drop modules no launched script can require (MODE defaults to remove)
+    #[arg(long = "tree-shake", value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "remove")]
+    pub tree_shaking: Option<CliTreeShaking>,
+
+    /// Write a size breakdown of every target next to its output and print it
+    #[arg(long = "report")]
+    pub report: bool,
+
+    /// Write an interactive HTML treemap of every target next to its output
+    #[arg(long = "analyze")]
+    pub analyze: bool,
+
+    /// Fail targets with warnings instead of writing them
+    #[arg(long = "deny-warnings")]
+    pub deny_warnings: bool,
+
     /// Suppress progress output, show only errors
     #[arg(short = 'q', long = "quiet", conflicts_with = "silent")]
     pub quiet: bool,
@@ -86,7 +286,7 @@ pub struct BuildArgs {
     pub silent: bool,
 }
 
-impl BuildArgs {
+impl BuildOptions {
     /// Get the verbosity level from CLI flags.
     pub fn verbosity(&self) -> Verbosity {
         if self.silent {
@@ -98,23 +298,202 @@ impl BuildArgs {
         }
     }
 
+    /// Returns the project file given with `--config`, or the one in the working directory.
+    fn project_path(&self) -> Result<Option<PathBuf>> {
+        Ok(match &self.config {
+            Some(path) => Some(path.clone()),
+            None => ProjectConfig::discover(&std::env::current_dir()?),
+        })
+    }
+
+    /// Reads the project file, if there is one.
+    fn project_config(&self) -> Result<Option<ProjectConfig>> {
+        self.project_path()?.map(|path| ProjectConfig::load(&path)).transpose()
+    }
+
+    /// Returns the files besides the input that a build depends on, for watch mode:
+    /// the project file and the header and darklua files it or the flags reference.
+    pub fn watched_paths(&self) -> Result<Vec<WatchedPath>> {
+        let mut paths: Vec<WatchedPath> = self.project_path()?.into_iter().map(WatchedPath::all).collect();
+        let project = self.project_config()?.unwrap_or_default();
+        paths.extend(project.watched_paths()?);
+        paths.extend(self.header.iter().cloned().map(WatchedPath::all));
+
+        for arg in self.darklua_configs.iter().chain(&self.darklua_extends) {
+            let (target, path) = arg.resolve(&project)?;
+            paths.push(WatchedPath { path, target });
+        }
+
+        Ok(paths)
+    }
+
     /// Convert CLI arguments to a BuildConfig for the bundler library.
-    pub fn to_build_config(&self) -> Result<BuildConfig> {
-        let header_content = self
-            .header
-            .as_ref()
-            .map(|p| fs::read_to_string(p).context("Failed to read header file"))
-            .transpose()?;
+    ///
+    /// The project file is converted by [`ProjectConfig::to_build_config`],
+    /// with the input, output directory, targets and header given on the
+    /// command line taken instead of its own; every other flag is layered on
+    /// top. Darklua configurations and defines from both are combined, with
+    /// the command line applied last. Without an output directory from
+    /// either, `required` decides between an error and [`DEFAULT_OUT_DIR`].
+    fn resolve(&self, out_dir: Option<PathBuf>, required: bool) -> Result<BuildConfig> {
+        let mut project = self.project_config()?.unwrap_or_default();
 
-        let targets = self.targets.iter().copied().map(Target::from).collect();
+        if let Some(input) = &self.input {
+            project.input = Some(input.clone());
+        }
+        if project.input.is_none() {
+            bail!("No input given; pass one or set `input` in {PROJECT_FILE_NAME}");
+        }
+        if let Some(out_dir) = out_dir {
+            project.out_dir = Some(out_dir);
+        }
+        if project.out_dir.is_none() {
+            if required {
+                bail!("No output directory given; pass --out-dir or set `out-dir` in {PROJECT_FILE_NAME}");
+            }
+            project.out_dir = Some(PathBuf::from(DEFAULT_OUT_DIR));
+        }
+        if !self.targets.is_empty() {
+            for name in &self.targets {
+                project.target(name)?;
+            }
+            project.targets = self.targets.clone();
+        }
+        if let Some(header) = &self.header {
+            project.header = Some(header.clone());
+        }
 
-        let mut config = BuildConfig::new(self.input.clone(), self.out_dir.clone())
-            .with_targets(targets);
+        let mut config = project.to_build_config()?;
 
-        if let Some(header) = header_content {
-            config = config.with_header(header);
+        if !self.entry_points.is_empty() {
+            config = config.with_entry_points(self.entry_points.clone());
+        }
+        if let Some(start_policy) = self.start_policy {
+            config = config.with_start_policy(start_policy.into());
+        }
+        if self.rbxts {
+            config = config.with_input_kind(InputKind::RobloxTs);
+        }
+        if let Some(root_path) = &self.root_path {
+            config = config.with_root_path(root_path.clone());
+        }
+        if let Some(output_name) = &self.output_name {
+            config = config.with_output_name(output_name.clone());
+        }
+        if self.no_check_requires {
+            config = config.with_check_requires(false);
+        }
+        if let Some(tree_shaking) = self.tree_shaking {
+            config = config.with_tree_shaking(tree_shaking.into());
+        }
+        if self.report {
+            config = config.with_size_report(true);
+        }
+        if self.analyze {
+            config = config.with_analyzer(true);
+        }
+        if self.deny_warnings {
+            config = config.with_deny_warnings(true);
+        }
+
+        // Replacements are applied first so extensions always build on top of them
+        let replacements = self.darklua_configs.iter().map(|arg| (arg, false));
+        let extensions = self.darklua_extends.iter().map(|arg| (arg, true));
+
+        for (arg, extend) in replacements.chain(extensions) {
+            let (target, path) = arg.resolve(&project)?;
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read darklua config {}", path.display()))?;
+
+            let targets: Vec<Target> = match target {
+                Some(target) => vec![target],
+                None => config.targets.clone(),
+            };
+            for target in targets {
+                let darklua = if extend {
+                    DarkluaOverride::Extend(content.clone())
+                } else {
+                    DarkluaOverride::Replace(content.clone())
+                };
+                config = config.with_darklua_override(target, darklua);
+            }
+        }
+
+        for define in &self.defines {
+            config = config.with_define(define.name.clone(), define.value.clone());
         }
 
         Ok(config)
     }
 }
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct InitArgs {
+    /// Directory to create the project file in (defaults to the working directory)
+    pub dir: Option<PathBuf>,
+
+    /// Overwrite an existing project file
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl InitArgs {
+    /// Writes a project file, guessing the input from the directory contents.
+    ///
+    /// Returns the path of the created file.
+    pub fn run(&self) -> Result<PathBuf> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        let path = dir.join(PROJECT_FILE_NAME);
+
+        if path.exists() && !self.force {
+            bail!("{} already exists (use --force to overwrite)", path.display());
+        }
+
+        let (input, rbxts) = if dir.join("default.project.json").is_file() {
+            ("default.project.json", "")
+        } else if dir.join("tsconfig.json").is_file() {
+            (".", "rbxts = true\n")
+        } else {
+            ("model.rbxm", "")
+        };
+
+        let contents = assets::PROJECT_TEMPLATE
+            .replace("{{INPUT}}", input)
+            .replace("{{RBXTS}}", rbxts);
+        fs::write(&path, contents).context("Failed to write project file")?;
+
+        Ok(path)
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SymbolicateArgs {
+    /// Source map written next to the bundle (e.g. dist/model.rel.lua.map)
+    pub map: PathBuf,
+
+    /// File containing the error or traceback (reads stdin if omitted)
+    pub trace: Option<PathBuf>,
+}
+
+impl SymbolicateArgs {
+    /// Reads the trace and rewrites its locations using the source map.
+    pub fn run(&self) -> Result<String> {
+        let map = SourceMap::load(&self.map)?;
+
+        let trace = match &self.trace {
+            Some(path) => fs::read_to_string(path).context("Failed to read trace file")?,
+            None => {
+                let mut trace = String::new();
+                io::stdin()
+                    .read_to_string(&mut trace)
+                    .context("Failed to read trace from stdin")?;
+                trace
+            }
+        };
+
+        Ok(map.symbolicate(&trace))
+    }
+}
@@ -33,8 +33,11 @@
 //!
 //! - `Target::Dev` - Development build (debug mode, no minification)
 //! - `Target::DevCompat` - Development build with compatibility shims
+//! - `Target::DevInline` - Development build without `loadstring`
+//! - `Target::DevInlineCompat` - Development build without `loadstring`, with compatibility shims
 //! - `Target::Rel` - Release build (production mode, minified)
 //! - `Target::RelCompat` - Release build with compatibility shims
+//! - `Target::Custom` - User-defined target derived from a built-in one, see `CustomTarget`
 
 pub mod assets;
 pub mod bundler;
@@ -44,6 +47,8 @@ pub mod bundler;
 pub mod cli;
 #[doc(hidden)]
 pub mod logging;
+#[doc(hidden)]
+pub mod serve;
 
 // Re-export commonly used types at the crate root for convenience
 pub use bundler::{build, BuildConfig, BuildResult, Mode, Target, TargetResult, PKG_NAME, PKG_VERSION};
@@ -4,18 +4,60 @@ use std::time::Duration;
 
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::json;
 
-use crate::bundler::{BuildResult, PKG_NAME, PKG_VERSION};
-use crate::cli::Verbosity;
+use crate::bundler::{
+    BuildResult, Diagnostic, MemoryBuildResult, Severity, SizeReport, Target, TreeShakeReport, PKG_NAME,
+    PKG_VERSION,
+};
+use crate::cli::{OutputFormat, Verbosity};
+use crate::serve::BundleServer;
+
+/// Number of changed files listed before a rebuild in watch mode.
+const MAX_LISTED_CHANGES: usize = 5;
+
+/// Number of modules dropped by tree shaking listed after a build.
+const MAX_LISTED_SHAKEN: usize = 5;
+
+/// Number of modules listed in a size report, largest first.
+const MAX_LISTED_SIZES: usize = 10;
+
+/// Number of instance classes listed in a size report, most common first.
+const MAX_LISTED_CLASSES: usize = 4;
+
+/// Number of distinct diagnostics listed after a build.
+const MAX_LISTED_DIAGNOSTICS: usize = 10;
 
 /// A spinner-based UI for displaying build progress.
+///
+/// With [`OutputFormat::Json`], progress is not shown at all, and results and
+/// errors are printed to stdout as JSON documents instead.
 pub struct BuildUI {
     spinner: Option<ProgressBar>,
     verbosity: Verbosity,
+    format: OutputFormat,
+}
+
+/// A build result as printed with [`OutputFormat::Json`].
+#[derive(Serialize)]
+struct JsonResult<'a> {
+    success: bool,
+    #[serde(flatten)]
+    result: &'a BuildResult,
 }
 
 impl BuildUI {
     pub fn new(verbosity: Verbosity) -> Self {
+        Self::with_format(verbosity, OutputFormat::Human)
+    }
+
+    /// Creates a UI reporting results in the given format.
+    pub fn with_format(verbosity: Verbosity, format: OutputFormat) -> Self {
+        let verbosity = match format {
+            OutputFormat::Human => verbosity,
+            OutputFormat::Json => Verbosity::Silent,
+        };
         let spinner = if verbosity == Verbosity::Normal {
             let style = ProgressStyle::default_spinner()
                 .template("{spinner:.cyan} {msg}")
@@ -28,7 +70,7 @@ impl BuildUI {
             None
         };
         
-        Self { spinner, verbosity }
+        Self { spinner, verbosity, format }
     }
     
     /// Print the header with version info.
@@ -54,6 +96,43 @@ impl BuildUI {
         }
     }
     
+    /// Restart the spinner after it was finished, for another build in watch mode.
+    pub fn restart_spinner(&self, msg: &str) {
+        if let Some(spinner) = &self.spinner {
+            spinner.reset();
+            spinner.enable_steady_tick(Duration::from_millis(80));
+            spinner.set_message(msg.to_string());
+        }
+    }
+    
+    /// Print the number of watched paths once watch mode starts.
+    pub fn print_watching(&self, path_count: usize) {
+        if self.verbosity != Verbosity::Normal {
+            return;
+        }
+        eprintln!(
+            "  {} Watching {} {} for changes (Ctrl+C to stop)",
+            "→".dimmed(),
+            path_count,
+            if path_count == 1 { "path" } else { "paths" }
+        );
+    }
+    
+    /// Print the files that triggered a rebuild.
+    pub fn print_changes(&self, changed: &[std::path::PathBuf]) {
+        if self.verbosity != Verbosity::Normal {
+            return;
+        }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        for path in changed.iter().take(MAX_LISTED_CHANGES) {
+            let display = path.strip_prefix(&cwd).unwrap_or(path);
+            eprintln!("  {} Changed: {}", "↻".cyan(), display.display());
+        }
+        if changed.len() > MAX_LISTED_CHANGES {
+            eprintln!("  {} ...and {} more", "↻".cyan(), changed.len() - MAX_LISTED_CHANGES);
+        }
+    }
+    
     /// Finish the spinner (called before printing results).
     pub fn finish_spinner(&self) {
         if let Some(spinner) = &self.spinner {
@@ -63,6 +142,11 @@ impl BuildUI {
     
     /// Display the final build result.
     pub fn display_result(&self, result: &BuildResult) {
+        if self.format == OutputFormat::Json {
+            let document = JsonResult { success: result.is_success(), result };
+            Self::print_json(&document);
+            return;
+        }
         if self.verbosity != Verbosity::Normal {
             return;
         }
@@ -74,57 +158,226 @@ impl BuildUI {
                 .and_then(|s| s.to_str())
                 .unwrap_or("unknown");
             
-            if target_result.success {
-                eprintln!(
-                    "  {} {} {} {}",
-                    "✔".green().bold(),
-                    target_result.target.to_string().dimmed(),
-                    "→".dimmed(),
-                    filename
-                );
-            } else {
-                let err_msg = target_result.error_message.as_deref().unwrap_or("unknown error");
-                eprintln!(
-                    "  {} {} {} {}",
-                    "✘".red().bold(),
-                    target_result.target.to_string().dimmed(),
-                    "→".dimmed(),
-                    err_msg.red()
-                );
-            }
+            Self::print_target(
+                &target_result.target,
+                filename,
+                target_result.success.then_some(()).ok_or(target_result.error_message.as_deref()),
+            );
+        }
+        
+        for size_report in result.target_results.iter().filter_map(|r| r.size_report.as_ref()) {
+            Self::print_size_report(size_report);
+        }
+        for analyzer_file in result.target_results.iter().filter_map(|r| r.analyzer_file.as_ref()) {
+            eprintln!("  {} Analyzer: {}", "◆".cyan(), analyzer_file.display());
+        }
+        if let Some(report) = &result.tree_shaking {
+            Self::print_tree_shaking(report);
+        }
+        Self::print_diagnostics(result.all_diagnostics());
+        Self::print_summary(result.target_results.len(), result.success_count(), result.duration);
+    }
+    
+    /// Display the result of a build served over HTTP, with the URL of each target.
+    pub fn display_served(&self, result: &MemoryBuildResult, server: &BundleServer) {
+        if self.verbosity != Verbosity::Normal {
+            return;
         }
         
-        // Print summary line
-        let elapsed = result.duration;
-        let target_count = result.target_results.len();
-        let success_count = result.success_count();
+        for bundle in &result.bundles {
+            let url = server.bundle_url(&bundle.target.to_string());
+            Self::print_target(
+                &bundle.target,
+                &url,
+                bundle.success.then_some(()).ok_or(bundle.error_message.as_deref()),
+            );
+        }
         
-        if result.is_success() {
+        for size_report in result.bundles.iter().filter_map(|b| b.size_report.as_ref()) {
+            Self::print_size_report(size_report);
+        }
+        if let Some(report) = &result.tree_shaking {
+            Self::print_tree_shaking(report);
+        }
+        Self::print_diagnostics(
+            result.diagnostics.iter().chain(result.bundles.iter().flat_map(|b| &b.diagnostics)),
+        );
+        Self::print_summary(result.bundles.len(), result.success_count(), result.duration);
+    }
+    
+    /// Print the loader URL of every target once serving starts.
+    pub fn print_loaders(&self, server: &BundleServer, targets: &[Target]) {
+        if self.verbosity != Verbosity::Normal {
+            return;
+        }
+        eprintln!("  {} Serving on {}", "→".dimmed(), server.url());
+        for target in targets {
+            let name = target.to_string();
             eprintln!(
-                "{} Built {} {} in {:.2?}",
+                "  {} Loader for {}: {}",
+                "→".dimmed(),
+                name,
+                format!("loadstring(game:HttpGet(\"{}\"))()", server.loader_url(&name)).cyan()
+            );
+        }
+    }
+    
+    /// Print the line of a single target: its destination, or its error.
+    fn print_target(target: &Target, destination: &str, outcome: Result<(), Option<&str>>) {
+        match outcome {
+            Ok(()) => eprintln!(
+                "  {} {} {} {}",
                 "✔".green().bold(),
-                target_count,
-                if target_count == 1 { "target" } else { "targets" },
-                elapsed
+                target.to_string().dimmed(),
+                "→".dimmed(),
+                destination
+            ),
+            Err(err_msg) => eprintln!(
+                "  {} {} {} {}",
+                "✘".red().bold(),
+                target.to_string().dimmed(),
+                "→".dimmed(),
+                err_msg.unwrap_or("unknown error").red()
+            ),
+        }
+    }
+    
+    /// Print the size breakdown of a bundle, with its largest modules.
+    fn print_size_report(report: &SizeReport) {
+        let row = |label: &str, bytes: usize, detail: String| {
+            let line = format!("    {:<10} {:>10}", label, format_size(bytes));
+            if detail.is_empty() {
+                eprintln!("{line}");
+            } else {
+                eprintln!("{line}  {}", detail.dimmed());
+            }
+        };
+
+        eprintln!("  {} Size of {}: {}", "■".cyan(), report.file, format_size(report.total_bytes).bold());
+        row("Header", report.header_bytes, String::new());
+        row("Runtime", report.runtime_bytes, String::new());
+
+        let mut classes: Vec<(&String, &usize)> = report.instances.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1));
+        let mut listed: Vec<String> = classes
+            .iter()
+            .take(MAX_LISTED_CLASSES)
+            .map(|(class, count)| format!("{count} {class}"))
+            .collect();
+        if classes.len() > MAX_LISTED_CLASSES {
+            listed.push(format!("{} more classes", classes.len() - MAX_LISTED_CLASSES));
+        }
+        row("Tree", report.tree_bytes, format!("{} instances: {}", report.instance_count(), listed.join(", ")));
+
+        let noun = if report.modules.len() == 1 { "script" } else { "scripts" };
+        row("Scripts", report.modules_bytes(), format!("{} {noun}", report.modules.len()));
+        let mut modules: Vec<_> = report.modules.iter().collect();
+        modules.sort_by_key(|module| std::cmp::Reverse(module.output_bytes));
+        for module in modules.iter().take(MAX_LISTED_SIZES) {
+            eprintln!(
+                "      {:>10}  {} {}",
+                format_size(module.output_bytes),
+                module.path,
+                format!("(source {})", format_size(module.source_bytes)).dimmed()
             );
+        }
+        if modules.len() > MAX_LISTED_SIZES {
+            eprintln!("      {:>10}  ...and {} more", "", modules.len() - MAX_LISTED_SIZES);
+        }
+    }
+    
+    /// Print the modules dropped by tree shaking and the bytes saved.
+    fn print_tree_shaking(report: &TreeShakeReport) {
+        let count = report.removed.len();
+        eprintln!(
+            "  {} Tree shaking dropped {} {} ({} bytes of source), kept {}",
+            "✂".cyan(),
+            count,
+            if count == 1 { "module" } else { "modules" },
+            report.bytes_saved(),
+            report.kept_modules
+        );
+        for module in report.removed.iter().take(MAX_LISTED_SHAKEN) {
+            eprintln!("    {} {}", "-".dimmed(), module.path.dimmed());
+        }
+        if count > MAX_LISTED_SHAKEN {
+            eprintln!("    {} ...and {} more", "-".dimmed(), count - MAX_LISTED_SHAKEN);
+        }
+    }
+    
+    /// Print the diagnostics of a build, once each since targets share their tree.
+    fn print_diagnostics<'a>(diagnostics: impl Iterator<Item = &'a Diagnostic>) {
+        let mut distinct: Vec<&Diagnostic> = Vec::new();
+        for diagnostic in diagnostics {
+            if !distinct.contains(&diagnostic) {
+                distinct.push(diagnostic);
+            }
+        }
+        for diagnostic in distinct.iter().take(MAX_LISTED_DIAGNOSTICS) {
+            let (icon, message) = match diagnostic.severity {
+                Severity::Warning => ("⚠".yellow().bold(), diagnostic.message.yellow()),
+                Severity::Error => ("✘".red().bold(), diagnostic.message.red()),
+            };
+            eprintln!(
+                "  {} {}: {} {}",
+                icon,
+                diagnostic.path,
+                message,
+                format!("[{}]", diagnostic.code).dimmed()
+            );
+        }
+        if distinct.len() > MAX_LISTED_DIAGNOSTICS {
+            eprintln!("    ...and {} more", distinct.len() - MAX_LISTED_DIAGNOSTICS);
+        }
+    }
+    
+    /// Print the summary line of a build.
+    fn print_summary(target_count: usize, success_count: usize, elapsed: Duration) {
+        let noun = if target_count == 1 { "target" } else { "targets" };
+        if success_count == target_count {
+            eprintln!("{} Built {} {} in {:.2?}", "✔".green().bold(), target_count, noun, elapsed);
         } else {
             eprintln!(
                 "{} Built {}/{} {} in {:.2?}",
                 "✘".red().bold(),
                 success_count,
                 target_count,
-                if target_count == 1 { "target" } else { "targets" },
+                noun,
                 elapsed
             );
         }
     }
     
+    /// Print a JSON document to stdout.
+    fn print_json(document: &impl Serialize) {
+        match serde_json::to_string_pretty(document) {
+            Ok(json) => println!("{json}"),
+            Err(e) => eprintln!("{} Failed to serialize build result: {e}", "error:".red().bold()),
+        }
+    }
+    
     /// Display an early error (before build starts).
-    /// Shown in Normal and Quiet modes, suppressed only in Silent mode.
+    /// Shown in Normal and Quiet modes, suppressed only in Silent mode;
+    /// always printed as a JSON document with [`OutputFormat::Json`].
     pub fn display_error(&self, msg: &str) {
         self.finish_spinner();
-        if self.verbosity != Verbosity::Silent {
+        if self.format == OutputFormat::Json {
+            Self::print_json(&json!({ "success": false, "error": msg }));
+        } else if self.verbosity != Verbosity::Silent {
             eprintln!("{} {}", "error:".red().bold(), msg);
         }
     }
 }
+
+/// Formats a size in bytes for display, e.g. `12.3 KB`.
+fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f < KB {
+        format!("{bytes} B")
+    } else if bytes_f < KB * KB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{:.2} MB", bytes_f / (KB * KB))
+    }
+}
@@ -2,21 +2,20 @@ use std::process::ExitCode;
 
 use clap::Parser;
 
-use rbxts_bundler::bundler;
-use rbxts_bundler::cli::{Cli, Commands};
+use rbxts_bundler::bundler::{self, BuildConfig, Watcher};
+use rbxts_bundler::cli::{BuildOptions, Cli, Commands, ServeArgs, Verbosity};
 use rbxts_bundler::logging::BuildUI;
+use rbxts_bundler::serve::BundleServer;
 
 fn main() -> ExitCode {
     let Cli { command } = Cli::parse();
     match command {
         Commands::Build(args) => {
-            let ui = BuildUI::new(args.verbosity());
+            let ui = BuildUI::with_format(args.options.verbosity(), args.output_format);
             
             ui.print_header();
-            ui.print_input(&args.input);
-            ui.set_status("Building targets...");
             
-            // Convert CLI args to library BuildConfig
+            // Convert CLI args (and the project file, if any) to library BuildConfig
             let config = match args.to_build_config() {
                 Ok(config) => config,
                 Err(e) => {
@@ -25,22 +24,149 @@ fn main() -> ExitCode {
                 }
             };
             
-            match bundler::build(&config) {
-                Ok(result) => {
-                    ui.finish_spinner();
-                    ui.display_result(&result);
-                    
-                    if result.is_success() {
-                        ExitCode::SUCCESS
-                    } else {
-                        ExitCode::FAILURE
+            ui.print_input(&config.input);
+            
+            if args.watch {
+                let watcher = match start_watcher(&args.options, &config) {
+                    Ok(watcher) => watcher,
+                    Err(e) => {
+                        ui.display_error(&format!("{e:#}"));
+                        return ExitCode::FAILURE;
                     }
-                }
-                Err(e) => {
-                    ui.display_error(&format!("{e:#}"));
-                    ExitCode::FAILURE
-                }
+                };
+                ui.set_status("Building targets...");
+                run_build(&config, &ui);
+                ui.print_watching(watcher.paths().len());
+                watch(&args.options, || args.to_build_config(), watcher, &ui, |config| {
+                    run_build(config, &ui);
+                });
+            }
+            
+            ui.set_status("Building targets...");
+            if run_build(&config, &ui) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Commands::Serve(args) => serve(&args),
+        Commands::Init(args) => match args.run() {
+            Ok(path) => {
+                println!("Created {}", path.display());
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                BuildUI::new(Verbosity::Normal).display_error(&format!("{e:#}"));
+                ExitCode::FAILURE
+            }
+        },
+        Commands::Symbolicate(args) => match args.run() {
+            Ok(output) => {
+                print!("{output}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                BuildUI::new(Verbosity::Normal).display_error(&format!("{e:#}"));
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+/// Runs a build and displays its result, returning whether every target succeeded.
+fn run_build(config: &BuildConfig, ui: &BuildUI) -> bool {
+    match bundler::build(config) {
+        Ok(result) => {
+            ui.finish_spinner();
+            ui.display_result(&result);
+            result.is_success()
+        }
+        Err(e) => {
+            ui.display_error(&format!("{e:#}"));
+            false
+        }
+    }
+}
+
+/// Creates a watcher for the input and configuration files of a build.
+fn start_watcher(options: &BuildOptions, config: &BuildConfig) -> anyhow::Result<Watcher> {
+    Watcher::new(config, options.watched_paths()?)
+}
+
+/// Rebuilds the affected targets whenever a watched file changes, forever.
+///
+/// The configuration is re-read before every rebuild, since configuration
+/// files are watched too; if it became invalid, the error is shown and the
+/// previous files stay watched.
+fn watch(
+    options: &BuildOptions,
+    to_build_config: impl Fn() -> anyhow::Result<BuildConfig>,
+    mut watcher: Watcher,
+    ui: &BuildUI,
+    mut rebuild: impl FnMut(&BuildConfig),
+) -> ! {
+    loop {
+        let changed = watcher.wait();
+        ui.print_changes(&changed);
+
+        let reloaded = to_build_config().and_then(|config| Ok((start_watcher(options, &config)?, config)));
+        let (new_watcher, config) = match reloaded {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                ui.display_error(&format!("{e:#}"));
+                continue;
             }
+        };
+
+        let targets = watcher.affected_targets(&changed, &config.targets);
+        watcher = new_watcher;
+        if targets.is_empty() {
+            continue;
+        }
+
+        ui.restart_spinner("Rebuilding targets...");
+        rebuild(&config.with_targets(targets));
+    }
+}
+
+/// Builds every target in memory and serves them over HTTP, rebuilding the
+/// affected ones whenever a watched file changes.
+fn serve(args: &ServeArgs) -> ExitCode {
+    let ui = BuildUI::new(args.options.verbosity());
+    ui.print_header();
+
+    let started = args.to_build_config().and_then(|config| {
+        let watcher = start_watcher(&args.options, &config)?;
+        let server = BundleServer::start(&format!("{}:{}", args.host, args.port))?;
+        Ok((config, watcher, server))
+    });
+    let (config, watcher, server) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            ui.display_error(&format!("{e:#}"));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    ui.print_input(&config.input);
+    ui.set_status("Building targets...");
+    serve_build(&config, &server, &ui);
+    ui.print_loaders(&server, &config.targets);
+    ui.print_watching(watcher.paths().len());
+
+    watch(&args.options, || args.to_build_config(), watcher, &ui, |config| {
+        serve_build(config, &server, &ui);
+    });
+}
+
+/// Builds targets in memory and publishes them to the server.
+fn serve_build(config: &BuildConfig, server: &BundleServer, ui: &BuildUI) {
+    match bundler::build_in_memory(config) {
+        Ok(result) => {
+            ui.finish_spinner();
+            server.publish(&result);
+            ui.display_served(&result, server);
         }
+        Err(e) => ui.display_error(&format!("{e:#}")),
     }
 }
@@ -0,0 +1,416 @@
+//! Local HTTP server for bundles built in memory.
+//!
+//! Every target is served under its name:
+//!
+//! - `GET /` lists the targets as JSON
+//! - `GET /<target>.lua` returns the bundle, with `ETag` and `X-Bundle-Hash` headers
+//! - `GET /<target>.lua.map` returns its source map
+//! - `GET /<target>/loader.lua` returns a Luau loader that runs the bundle and
+//!   runs it again after every rebuild
+//! - `GET /<target>/poll?hash=<hash>` waits until the bundle hash differs from
+//!   `hash`, then returns the new hash; returns `204 No Content` on timeout
+//!
+//! Target names are percent-encoded in paths. Requests are answered by a
+//! fixed pool of [`WORKERS`] threads; pending polls are parked until a
+//! rebuild or their timeout instead of holding one of them.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::assets;
+use crate::bundler::escape::to_luau_string;
+use crate::bundler::{content_hash, MemoryBuildResult, PKG_NAME, PKG_VERSION};
+
+/// How long a poll request waits for a rebuild before returning `204 No Content`.
+const POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often parked polls are checked for their timeout.
+const POLL_EXPIRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Number of threads answering requests.
+const WORKERS: usize = 16;
+
+/// The latest build of a target.
+#[derive(Clone, Debug, Default)]
+struct ServedBundle {
+    /// Last successfully built source, kept while later builds fail.
+    source: Option<Arc<String>>,
+    /// Source map of `source`, as JSON.
+    source_map: Option<Arc<String>>,
+    /// Hash of `source`, empty until a build succeeds.
+    hash: String,
+    /// Error of the latest build, if it failed.
+    error: Option<String>,
+}
+
+/// A poll waiting for its target to be rebuilt.
+struct ParkedPoll {
+    request: Request,
+    target: String,
+    /// Hash the client already has.
+    hash: String,
+    deadline: Instant,
+}
+
+/// Bundles shared between the build loop and the request handlers.
+///
+/// The bundles are always locked before the parked polls, so a poll is never
+/// parked after the rebuild that should answer it.
+#[derive(Default)]
+struct State {
+    bundles: Mutex<BTreeMap<String, ServedBundle>>,
+    polls: Mutex<Vec<ParkedPoll>>,
+}
+
+impl State {
+    fn bundles(&self) -> MutexGuard<'_, BTreeMap<String, ServedBundle>> {
+        self.bundles.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn polls(&self) -> MutexGuard<'_, Vec<ParkedPoll>> {
+        self.polls.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// `GET /<target>/poll?hash=<hash>`: answers right away if the bundle
+    /// hash already differs, or parks the request until it does.
+    fn poll(&self, request: Request, target: String, hash: String) {
+        let bundles = self.bundles();
+        let response = match bundles.get(&target) {
+            None => text(404, &format!("Unknown target '{target}'")),
+            Some(served) if !served.hash.is_empty() && served.hash != hash => text(200, &served.hash),
+            Some(_) => {
+                let deadline = Instant::now() + POLL_TIMEOUT;
+                self.polls().push(ParkedPoll { request, target, hash, deadline });
+                return;
+            }
+        };
+        drop(bundles);
+        let _ = request.respond(response);
+    }
+
+    /// Answers the parked polls whose target was rebuilt; `bundles` is the
+    /// lock the rebuild was published under.
+    fn wake_polls(&self, bundles: MutexGuard<'_, BTreeMap<String, ServedBundle>>) {
+        let mut polls = self.polls();
+        let (ready, parked): (Vec<_>, Vec<_>) = std::mem::take(&mut *polls).into_iter().partition(|poll| {
+            bundles
+                .get(&poll.target)
+                .is_some_and(|served| !served.hash.is_empty() && served.hash != poll.hash)
+        });
+        *polls = parked;
+        drop(polls);
+
+        let hashes: Vec<String> = ready.iter().map(|poll| bundles[&poll.target].hash.clone()).collect();
+        drop(bundles);
+        for (poll, hash) in ready.into_iter().zip(hashes) {
+            // The client may have gone away, e.g. a loader stopped while polling
+            let _ = poll.request.respond(text(200, &hash));
+        }
+    }
+
+    /// Answers the parked polls past their deadline with `204 No Content`.
+    fn expire_polls(&self) {
+        let now = Instant::now();
+        let mut polls = self.polls();
+        let (expired, parked): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut *polls).into_iter().partition(|poll| poll.deadline <= now);
+        *polls = parked;
+        drop(polls);
+
+        for poll in expired {
+            let _ = poll.request.respond(respond(204, String::new(), "text/plain; charset=utf-8"));
+        }
+    }
+}
+
+/// A running HTTP server for bundles.
+pub struct BundleServer {
+    state: Arc<State>,
+    url: String,
+}
+
+impl BundleServer {
+    /// Binds to an address (e.g. `127.0.0.1:8374`) and serves requests on
+    /// background threads, so polls do not block other requests.
+    pub fn start(address: &str) -> Result<Self> {
+        let server = Server::http(address).map_err(|e| anyhow!("Failed to listen on {address}: {e}"))?;
+        let url = match server.server_addr().to_ip() {
+            Some(addr) => format!("http://{addr}"),
+            None => format!("http://{address}"),
+        };
+
+        let state = Arc::new(State::default());
+        let server = Arc::new(server);
+        for _ in 0..WORKERS {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            let base_url = url.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(request, &state, &base_url);
+                }
+            });
+        }
+
+        let expiry_state = Arc::clone(&state);
+        thread::spawn(move || loop {
+            thread::sleep(POLL_EXPIRY_INTERVAL);
+            expiry_state.expire_polls();
+        });
+
+        Ok(Self { state, url })
+    }
+
+    /// Returns the base URL of the server, e.g. `http://127.0.0.1:8374`.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the URL of a target's bundle.
+    #[must_use]
+    pub fn bundle_url(&self, target: &str) -> String {
+        bundle_url(&self.url, target)
+    }
+
+    /// Returns the URL of a target's loader.
+    #[must_use]
+    pub fn loader_url(&self, target: &str) -> String {
+        format!("{}/{}/loader.lua", self.url, percent_encode(target))
+    }
+
+    /// Serves the bundles of a build and wakes up pending polls.
+    ///
+    /// Targets that failed keep serving their previous bundle, with the error
+    /// reported in the index. Targets missing from the build are left as they are.
+    pub fn publish(&self, result: &MemoryBuildResult) {
+        let mut bundles = self.state.bundles();
+        for bundle in &result.bundles {
+            let served = bundles.entry(bundle.target.to_string()).or_default();
+            if bundle.success {
+                let source_map = serde_json::to_string_pretty(&bundle.source_map).unwrap_or_default();
+                served.hash = content_hash(&bundle.source);
+                served.source = Some(Arc::new(bundle.source.clone()));
+                served.source_map = Some(Arc::new(source_map));
+                served.error = None;
+            } else {
+                served.error = bundle.error_message.clone();
+            }
+        }
+        self.state.wake_polls(bundles);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Request Handling
+// ─────────────────────────────────────────────────────────────────────────────
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// What to do with a request.
+enum Routed {
+    Respond(HttpResponse),
+    /// Park a poll for a target, given the hash the client already has.
+    Poll { target: String, hash: String },
+}
+
+impl From<HttpResponse> for Routed {
+    fn from(response: HttpResponse) -> Self {
+        Self::Respond(response)
+    }
+}
+
+/// Answers a single request.
+fn handle(request: Request, state: &State, base_url: &str) {
+    let routed = if matches!(request.method(), Method::Get | Method::Head) {
+        route(&request, state, base_url)
+    } else {
+        text(405, "Method not allowed").into()
+    };
+    match routed {
+        // The client may have gone away, e.g. a loader stopped while polling
+        Routed::Respond(response) => {
+            let _ = request.respond(response);
+        }
+        Routed::Poll { target, hash } => state.poll(request, target, hash),
+    }
+}
+
+/// Dispatches a request to the endpoint for its path.
+fn route(request: &Request, state: &State, base_url: &str) -> Routed {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let path = path.trim_start_matches('/');
+
+    if path.is_empty() {
+        return index(state, base_url).into();
+    }
+    // Suffixes are matched before decoding, so they are never part of a target name
+    let (endpoint, target) = if let Some(target) = path.strip_suffix(".lua.map") {
+        (Endpoint::SourceMap, target)
+    } else if let Some(target) = path.strip_suffix("/loader.lua") {
+        (Endpoint::Loader, target)
+    } else if let Some(target) = path.strip_suffix(".lua") {
+        (Endpoint::Bundle, target)
+    } else if let Some(target) = path.strip_suffix("/poll") {
+        (Endpoint::Poll, target)
+    } else {
+        return text(404, "Not found").into();
+    };
+    let Some(target) = percent_decode(target) else {
+        return text(400, "Invalid percent-encoding in path").into();
+    };
+
+    match endpoint {
+        Endpoint::SourceMap => source_map(state, &target).into(),
+        Endpoint::Loader => loader(state, &target, base_url).into(),
+        Endpoint::Bundle => bundle(request, state, &target).into(),
+        Endpoint::Poll => {
+            let hash = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("hash="))
+                .unwrap_or_default();
+            Routed::Poll { target, hash: hash.to_string() }
+        }
+    }
+}
+
+/// The endpoints served for every target.
+enum Endpoint {
+    SourceMap,
+    Loader,
+    Bundle,
+    Poll,
+}
+
+/// `GET /`: every target with its URLs, hash and latest error.
+fn index(state: &State, base_url: &str) -> HttpResponse {
+    let targets: Vec<_> = state
+        .bundles()
+        .iter()
+        .map(|(name, bundle)| {
+            json!({
+                "name": name,
+                "url": bundle_url(base_url, name),
+                "loader": format!("{base_url}/{}/loader.lua", percent_encode(name)),
+                "hash": bundle.hash,
+                "error": bundle.error,
+            })
+        })
+        .collect();
+
+    let body = json!({ "name": PKG_NAME, "version": PKG_VERSION, "targets": targets });
+    respond(200, body.to_string(), "application/json")
+}
+
+/// `GET /<target>.lua`: the bundle, or `304 Not Modified` if the client has it already.
+fn bundle(request: &Request, state: &State, target: &str) -> HttpResponse {
+    let served = state.bundles().get(target).cloned();
+    let Some(served) = served else {
+        return text(404, &format!("Unknown target '{target}'"));
+    };
+    let Some(source) = served.source else {
+        let error = served.error.unwrap_or_else(|| "Not built yet".to_string());
+        return text(503, &error);
+    };
+
+    let etag = format!("\"{}\"", served.hash);
+    let cached = request
+        .headers()
+        .iter()
+        .any(|header| header.field.equiv("If-None-Match") && header.value.as_str() == etag);
+
+    let response = if cached {
+        respond(304, String::new(), "text/plain; charset=utf-8")
+    } else {
+        respond(200, source.as_str().to_owned(), "text/plain; charset=utf-8")
+    };
+    response
+        .with_header(header("ETag", &etag))
+        .with_header(header("X-Bundle-Hash", &served.hash))
+}
+
+/// `GET /<target>.lua.map`: the source map of the served bundle.
+fn source_map(state: &State, target: &str) -> HttpResponse {
+    match state.bundles().get(target).and_then(|served| served.source_map.clone()) {
+        Some(map) => respond(200, map.as_str().to_owned(), "application/json"),
+        None => text(404, &format!("No source map for target '{target}'")),
+    }
+}
+
+/// `GET /<target>/loader.lua`: a loader pointing at this server.
+fn loader(state: &State, target: &str, base_url: &str) -> HttpResponse {
+    if !state.bundles().contains_key(target) {
+        return text(404, &format!("Unknown target '{target}'"));
+    }
+
+    // The target name is user-defined, so everything derived from it is a string literal
+    let loader = assets::SERVE_LOADER
+        .replace("{{NAME}}", PKG_NAME)
+        .replace("{{VERSION}}", PKG_VERSION)
+        .replace("{{TARGET}}", &to_luau_string(target))
+        .replace("{{CHUNK_NAME}}", &to_luau_string(&format!("={target}")))
+        .replace("{{BUNDLE_URL}}", &to_luau_string(&bundle_url(base_url, target)))
+        .replace("{{POLL_URL}}", &to_luau_string(&format!("{base_url}/{}/poll", percent_encode(target))));
+    respond(200, loader, "text/plain; charset=utf-8")
+}
+
+/// Returns the URL of a target's bundle on a server.
+fn bundle_url(base_url: &str, target: &str) -> String {
+    format!("{base_url}/{}.lua", percent_encode(target))
+}
+
+/// Percent-encodes every byte outside the unreserved characters of RFC 3986.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Decodes percent-encoded bytes, returning `None` for malformed escapes or
+/// invalid UTF-8.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Builds a response with a body and content type.
+fn respond(status: u16, body: String, content_type: &str) -> HttpResponse {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", content_type))
+        .with_header(header("Cache-Control", "no-cache"))
+}
+
+/// Builds a plain text response.
+fn text(status: u16, body: &str) -> HttpResponse {
+    respond(status, body.to_string(), "text/plain; charset=utf-8")
+}
+
+/// Builds a header from ASCII strings.
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("header names and values are ASCII")
+}
@@ -0,0 +1,191 @@
+//! Tests for resolving per-target darklua configurations.
+
+use std::collections::BTreeMap;
+
+use rbxts_bundler::bundler::darklua::{builtin_config, resolve_config};
+use rbxts_bundler::bundler::{CustomTarget, DarkluaOverride, DefineValue, Mode, Target};
+use serde_json::Value;
+
+fn resolve(target: Target, overrides: &[(Target, DarkluaOverride)]) -> Value {
+    serde_json::from_str(&resolve_config(&target, overrides, &BTreeMap::new()).unwrap()).unwrap()
+}
+
+fn rule_names(config: &Value) -> Vec<String> {
+    config["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|rule| match rule {
+            Value::String(name) => name.clone(),
+            rule => rule["rule"].as_str().unwrap().to_string(),
+        })
+        .collect()
+}
+
+mod builtin {
+    use super::*;
+
+    #[test]
+    fn used_without_overrides() {
+        assert_eq!(resolve_config(&Target::Rel, &[], &BTreeMap::new()).unwrap(), builtin_config(&Target::Rel));
+    }
+
+    #[test]
+    fn overrides_for_other_targets_are_ignored() {
+        let overrides = [(Target::Rel, DarkluaOverride::Replace(r#"{ "rules": [] }"#.to_string()))];
+        assert_eq!(resolve_config(&Target::Dev, &overrides, &BTreeMap::new()).unwrap(), builtin_config(&Target::Dev));
+    }
+}
+
+mod replace {
+    use super::*;
+
+    #[test]
+    fn discards_builtin_rules() {
+        let overrides = [(Target::Rel, DarkluaOverride::Replace(r#"{ "rules": ["remove_comments"] }"#.to_string()))];
+        assert_eq!(rule_names(&resolve(Target::Rel, &overrides)), ["remove_comments"]);
+    }
+}
+
+mod extend {
+    use super::*;
+
+    #[test]
+    fn appends_new_rules() {
+        let overrides = [(
+            Target::Dev,
+            DarkluaOverride::Extend(
+                r#"{ "rules": [{ "rule": "inject_global_value", "identifier": "DEBUG", "value": true }] }"#.to_string(),
+            ),
+        )];
+        let names = rule_names(&resolve(Target::Dev, &overrides));
+
+        assert_eq!(names.first().map(String::as_str), Some("remove_types"));
+        assert_eq!(names.last().map(String::as_str), Some("inject_global_value"));
+    }
+
+    #[test]
+    fn merges_rename_variables_globals() {
+        let overrides = [(
+            Target::Rel,
+            DarkluaOverride::Extend(r#"{ "rules": [{ "rule": "rename_variables", "globals": ["shared"] }] }"#.to_string()),
+        )];
+        let config = resolve(Target::Rel, &overrides);
+
+        let rename = config["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|rule| rule["rule"] == "rename_variables")
+            .unwrap();
+        let globals = rename["globals"].as_array().unwrap();
+        assert!(globals.contains(&Value::from("__rbx")));
+        assert!(globals.contains(&Value::from("shared")));
+        assert_eq!(rule_names(&config).iter().filter(|n| *n == "rename_variables").count(), 1);
+    }
+
+    #[test]
+    fn overrides_generator() {
+        let overrides = [(Target::Rel, DarkluaOverride::Extend(r#"{ "generator": "readable" }"#.to_string()))];
+        assert_eq!(resolve(Target::Rel, &overrides)["generator"], "readable");
+    }
+
+    #[test]
+    fn applies_on_top_of_replacement() {
+        let overrides = [
+            (Target::Rel, DarkluaOverride::Replace(r#"{ "rules": ["remove_comments"] }"#.to_string())),
+            (Target::Rel, DarkluaOverride::Extend(r#"{ "rules": ["remove_spaces"] }"#.to_string())),
+        ];
+        assert_eq!(rule_names(&resolve(Target::Rel, &overrides)), ["remove_comments", "remove_spaces"]);
+    }
+}
+
+mod defines {
+    use super::*;
+
+    #[test]
+    fn injected_before_builtin_rules() {
+        let defines = BTreeMap::from([("DEBUG".to_string(), DefineValue::Bool(false))]);
+        let config: Value = serde_json::from_str(&resolve_config(&Target::Rel, &[], &defines).unwrap()).unwrap();
+
+        let first = &config["rules"][0];
+        assert_eq!(first["rule"], "inject_global_value");
+        assert_eq!(first["identifier"], "DEBUG");
+        assert_eq!(first["value"], false);
+    }
+
+    #[test]
+    fn values_parse_from_strings() {
+        assert_eq!("true".parse::<DefineValue>().unwrap(), DefineValue::Bool(true));
+        assert_eq!("1.5".parse::<DefineValue>().unwrap(), DefineValue::Number(1.5));
+        assert_eq!("beta".parse::<DefineValue>().unwrap(), DefineValue::String("beta".to_string()));
+        for word in ["nan", "inf", "-infinity"] {
+            assert_eq!(word.parse::<DefineValue>().unwrap(), DefineValue::String(word.to_string()));
+        }
+    }
+
+    #[test]
+    fn inexact_integers_stay_strings() {
+        assert_eq!("9007199254740992".parse::<DefineValue>().unwrap(), DefineValue::Number(9007199254740992.0));
+        for digits in ["9007199254740993", "-12345678901234567890"] {
+            assert_eq!(digits.parse::<DefineValue>().unwrap(), DefineValue::String(digits.to_string()));
+        }
+    }
+}
+
+mod custom {
+    use super::*;
+
+    #[test]
+    fn builtin_follows_mode_and_compat() {
+        let custom = Target::custom(CustomTarget::new("lua51".to_string(), &Target::Dev).with_mode(Mode::Production).with_compat(true));
+        assert_eq!(builtin_config(&custom), builtin_config(&Target::RelCompat));
+    }
+
+    #[test]
+    fn own_overrides_come_first() {
+        let custom = Target::custom(
+            CustomTarget::new("staging".to_string(), &Target::Rel)
+                .with_darklua_override(DarkluaOverride::Replace(r#"{ "rules": ["remove_comments"] }"#.to_string())),
+        );
+        let overrides = [(custom.clone(), DarkluaOverride::Extend(r#"{ "rules": ["remove_spaces"] }"#.to_string()))];
+        assert_eq!(rule_names(&resolve(custom, &overrides)), ["remove_comments", "remove_spaces"]);
+    }
+
+    #[test]
+    fn own_defines_take_precedence() {
+        let custom = Target::custom(
+            CustomTarget::new("staging".to_string(), &Target::Rel)
+                .with_define("STAGE".to_string(), DefineValue::String("staging".to_string())),
+        );
+        let defines = BTreeMap::from([("STAGE".to_string(), DefineValue::String("prod".to_string()))]);
+        let config: Value = serde_json::from_str(&resolve_config(&custom, &[], &defines).unwrap()).unwrap();
+
+        assert_eq!(config["rules"][0]["value"], "staging");
+        assert_eq!(config["rules"][1], "remove_comments");
+    }
+}
+
+mod errors {
+    use super::*;
+
+    #[test]
+    fn unknown_rule() {
+        let overrides = [(Target::Rel, DarkluaOverride::Extend(r#"{ "rules": ["not_a_rule"] }"#.to_string()))];
+        let err = resolve_config(&Target::Rel, &overrides, &BTreeMap::new()).unwrap_err();
+        assert!(err.to_string().contains("target 'rel'"), "{err}");
+    }
+
+    #[test]
+    fn invalid_json() {
+        let overrides = [(Target::Dev, DarkluaOverride::Replace("{ rules".to_string()))];
+        assert!(resolve_config(&Target::Dev, &overrides, &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn not_an_object() {
+        let overrides = [(Target::Dev, DarkluaOverride::Extend("[]".to_string()))];
+        let err = resolve_config(&Target::Dev, &overrides, &BTreeMap::new()).unwrap_err();
+        assert!(err.to_string().contains("JSON object"), "{err}");
+    }
+}
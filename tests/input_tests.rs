@@ -0,0 +1,63 @@
+//! Tests for input model format detection.
+
+use std::path::Path;
+
+use rbxts_bundler::bundler::ModelFormat;
+
+mod sniff {
+    use super::*;
+
+    #[test]
+    fn binary_magic() {
+        assert_eq!(ModelFormat::sniff(b"<roblox!\x89\xff\r\n"), Some(ModelFormat::Binary));
+    }
+
+    #[test]
+    fn xml_root() {
+        assert_eq!(ModelFormat::sniff(b"<roblox version=\"4\">"), Some(ModelFormat::Xml));
+    }
+
+    #[test]
+    fn xml_declaration() {
+        assert_eq!(ModelFormat::sniff(b"<?xml version=\"1.0\"?>"), Some(ModelFormat::Xml));
+    }
+
+    #[test]
+    fn xml_with_bom_and_whitespace() {
+        assert_eq!(ModelFormat::sniff(b"\xEF\xBB\xBF\r\n  <roblox>"), Some(ModelFormat::Xml));
+    }
+
+    #[test]
+    fn unknown_contents() {
+        assert_eq!(ModelFormat::sniff(b"local x = 1"), None);
+        assert_eq!(ModelFormat::sniff(b""), None);
+    }
+}
+
+mod extension {
+    use super::*;
+
+    #[test]
+    fn known_extensions() {
+        assert_eq!(ModelFormat::from_extension(Path::new("a.rbxm")), Some(ModelFormat::Binary));
+        assert_eq!(ModelFormat::from_extension(Path::new("a.RBXMX")), Some(ModelFormat::Xml));
+    }
+
+    #[test]
+    fn unknown_extension() {
+        assert_eq!(ModelFormat::from_extension(Path::new("a.lua")), None);
+        assert_eq!(ModelFormat::from_extension(Path::new("model")), None);
+    }
+
+    #[test]
+    fn contents_take_precedence() {
+        let format = ModelFormat::detect(Path::new("model.rbxm"), b"<roblox version=\"4\">");
+        assert_eq!(format, Some(ModelFormat::Xml));
+    }
+
+    #[test]
+    fn falls_back_to_extension() {
+        let format = ModelFormat::detect(Path::new("model.rbxmx"), b"");
+        assert_eq!(format, Some(ModelFormat::Xml));
+    }
+}
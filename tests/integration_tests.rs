@@ -3,6 +3,7 @@
 use std::path::PathBuf;
 use std::process::Command;
 
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
 use rbxts_bundler::bundler::{build, BuildConfig, Target};
 
 fn fixtures_dir() -> PathBuf {
@@ -17,6 +18,24 @@ fn test_rbxm() -> PathBuf {
     fixtures_dir().join("build.rbxm")
 }
 
+/// Writes a model or place with the given top-level instances into the output directory.
+fn write_model(file_name: &str, roots: Vec<InstanceBuilder>) -> PathBuf {
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+    let refs: Vec<_> = roots.into_iter().map(|b| dom.insert(root_ref, b)).collect();
+
+    let path = output_dir().join(file_name);
+    let file = std::fs::File::create(&path).unwrap();
+    rbx_binary::to_writer(file, &dom, &refs).unwrap();
+    path
+}
+
+fn local_script(name: &str, source: &str) -> InstanceBuilder {
+    InstanceBuilder::new("LocalScript")
+        .with_name(name)
+        .with_property("Source", source)
+}
+
 fn cli_binary() -> PathBuf {
     let base = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target");
     let debug = base.join("debug/rbxts-bundler");
@@ -66,7 +85,14 @@ mod api {
 
         #[test]
         fn all_targets() {
-            let targets = vec![Target::Dev, Target::Rel, Target::DevCompat, Target::RelCompat];
+            let targets = vec![
+                Target::Dev,
+                Target::Rel,
+                Target::DevCompat,
+                Target::RelCompat,
+                Target::DevInline,
+                Target::DevInlineCompat,
+            ];
             let config = BuildConfig::new(test_rbxm(), output_dir())
                 .with_targets(targets.clone());
             let result = build(&config).unwrap();
@@ -118,89 +144,2170 @@ mod api {
         }
     }
 
-    mod errors {
+    mod roots {
         use super::*;
 
         #[test]
-        fn nonexistent_input() {
-            let config = BuildConfig::new(PathBuf::from("nonexistent.rbxm"), output_dir())
-                .with_targets(vec![Target::Dev]);
-            assert!(build(&config).is_err());
+        fn bundles_every_top_level_instance() {
+            let input = write_model("roots_all.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Client").with_child(local_script("Main", "print(1)")),
+                InstanceBuilder::new("Folder").with_name("Shared"),
+            ]);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("\"Client.Main\""));
+            assert!(content.contains("__rbx(\"Shared\", \"Folder\", 3, nil)"));
+            assert!(content.contains("__start({ 2 })"));
         }
 
         #[test]
-        fn empty_targets() {
+        fn entry_points_are_passed_to_start() {
+            let input = write_model("roots_entry.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Client").with_child(local_script("Main", "")),
+                InstanceBuilder::new("Folder").with_name("Server").with_child(local_script("Main", "")),
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_entry_points(vec!["Client".to_string()]);
+            let result = build(&config).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("__start({ 2 })"));
+        }
+
+        #[test]
+        fn duplicate_root_names() {
+            let input = write_model("roots_duplicate.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Client"),
+                InstanceBuilder::new("Folder").with_name("Client"),
+            ]);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("__rbx(\"Client\", \"Folder\", 1, nil)"));
+            assert!(content.contains("__rbx(\"Client\", \"Folder\", 2, nil)"));
+        }
+
+        #[test]
+        fn unknown_entry_point() {
+            let input = write_model("roots_unknown.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Client"),
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_entry_points(vec!["Server".to_string()]);
+            let err = build(&config).unwrap_err();
+            assert!(err.to_string().contains("available: Client"), "{err}");
+        }
+    }
+
+    mod registry {
+        use super::*;
+
+        #[test]
+        fn same_named_siblings_and_dotted_names_are_distinct() {
+            let second = InstanceBuilder::new("Folder").with_name("Item");
+            let second_ref = second.referent();
+            let input = write_model("registry_siblings.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(InstanceBuilder::new("Folder").with_name("Item"))
+                    .with_child(second)
+                    .with_child(InstanceBuilder::new("Folder").with_name("a.b"))
+                    .with_child(InstanceBuilder::new("ObjectValue").with_name("Link").with_property("Value", second_ref)),
+            ]);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("__rbx(\"Item\", \"Folder\", 2, 1)"));
+            assert!(content.contains("__rbx(\"Item\", \"Folder\", 3, 1)"));
+            assert!(content.contains("__rbx(\"a.b\", \"Folder\", 4, 1)"));
+            assert!(content.contains("__refs(5, { Value = 3 })"));
+        }
+    }
+
+    mod scripts {
+        use super::*;
+        use rbx_dom_weak::types::Enum;
+        use rbxts_bundler::bundler::StartPolicy;
+
+        fn script(name: &str, run_context: u32) -> InstanceBuilder {
+            InstanceBuilder::new("Script")
+                .with_name(name)
+                .with_property("Source", "print('server')")
+                .with_property("RunContext", Enum::from_u32(run_context))
+        }
+
+        fn start_call(file_name: &str, policy: StartPolicy) -> String {
+            let input = write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Local", ""))
+                    .with_child(local_script("Off", "").with_property("Disabled", true))
+                    .with_child(script("Legacy", 0))
+                    .with_child(script("Client", 2))
+                    .with_child(script("Plugin", 3)),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_start_policy(policy);
+            let result = build(&config).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("__lua(\"Legacy\", \"Script\""));
+            content.lines().find(|l| l.starts_with("__start(")).unwrap().to_string()
+        }
+
+        #[test]
+        fn client_policy() {
+            assert_eq!(
+                start_call("scripts_client.rbxm", StartPolicy::Client),
+                "__start({ 2, 5 })"
+            );
+        }
+
+        #[test]
+        fn server_policy() {
+            assert_eq!(
+                start_call("scripts_server.rbxm", StartPolicy::Server),
+                "__start({ 4 })"
+            );
+        }
+
+        #[test]
+        fn all_policy() {
+            assert_eq!(
+                start_call("scripts_all.rbxm", StartPolicy::All),
+                "__start({ 2, 4, 5 })"
+            );
+        }
+
+        #[test]
+        fn none_policy() {
+            assert_eq!(start_call("scripts_none.rbxm", StartPolicy::None), "__start({})");
+        }
+    }
+
+    mod properties {
+        use super::*;
+        use rbx_dom_weak::types::{Enum, Vector3};
+
+        fn bundled(file_name: &str, roots: Vec<InstanceBuilder>) -> String {
+            let input = write_model(file_name, roots);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            std::fs::read_to_string(&result.target_results[0].output_file).unwrap()
+        }
+
+        #[test]
+        fn emits_non_default_values() {
+            let content = bundled("properties_values.rbxm", vec![
+                InstanceBuilder::new("Configuration")
+                    .with_name("Config")
+                    .with_child(InstanceBuilder::new("StringValue").with_name("Title").with_property("Value", "hi"))
+                    .with_child(InstanceBuilder::new("BoolValue").with_name("Enabled").with_property("Value", false))
+                    .with_child(
+                        InstanceBuilder::new("Part")
+                            .with_name("Block")
+                            .with_property("Size", Vector3::new(2.0, 3.0, 4.0))
+                            .with_property("Material", Enum::from_u32(512)),
+                    ),
+            ]);
+
+            assert!(content.contains("__props(2, { Value = \"hi\" })"));
+            assert!(content.contains("Material = Enum.Material.Wood"));
+            assert!(content.contains("Size = Vector3.new(2, 3, 4)"));
+            // BoolValue.Value defaults to false
+            assert!(!content.contains("__props(3,"));
+        }
+
+        #[test]
+        fn resolves_references_after_the_tree() {
+            let target = InstanceBuilder::new("Folder").with_name("Target");
+            let target_ref = target.referent();
+            let content = bundled("properties_refs.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(InstanceBuilder::new("ObjectValue").with_name("Link").with_property("Value", target_ref))
+                    .with_child(target),
+            ]);
+
+            let refs = content.find("__refs(2, { Value = 3 })").unwrap();
+            let target_registered = content.find("__rbx(\"Target\"").unwrap();
+            assert!(refs > target_registered);
+        }
+    }
+
+    mod metadata {
+        use super::*;
+        use rbx_dom_weak::types::{Attributes, Tags, Vector3};
+
+        #[test]
+        fn attributes_and_tags_are_passed_to_registrations() {
+            let attributes = Attributes::new()
+                .with("Speed", 16.0f64)
+                .with("Spawn", Vector3::new(0.0, 5.0, 0.0));
+            let tags = Tags::from(vec!["Enemy".to_string(), "Boss".to_string()]);
+            let input = write_model("metadata_all.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_property("Attributes", attributes)
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("Mod")
+                            .with_property("Source", "return 1")
+                            .with_property("Tags", tags),
+                    ),
+            ]);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains(
+                "__rbx(\"Root\", \"Folder\", 1, nil, { Spawn = Vector3.new(0, 5, 0), Speed = 16 })"
+            ));
+            assert!(content.contains("end, nil, { \"Enemy\", \"Boss\" })"));
+        }
+
+        #[test]
+        fn rojo_attributes() {
+            let dir = output_dir().join("metadata_rojo");
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Folder", "$attributes": { "Debug": true } } }"#,
+            ).unwrap();
+
+            let result = build(&BuildConfig::new(dir, output_dir())).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+            assert!(content.contains("__rbx(\"App\", \"Folder\", 1, nil, { Debug = true })"));
+        }
+    }
+
+    mod places {
+        use super::*;
+
+        fn place(file_name: &str) -> PathBuf {
+            write_model(file_name, vec![
+                InstanceBuilder::new("ReplicatedStorage")
+                    .with_name("ReplicatedStorage")
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("Client")
+                            .with_child(local_script("Main", "print(1)")),
+                    ),
+                InstanceBuilder::new("Workspace").with_name("World"),
+            ])
+        }
+
+        fn bundled(config: &BuildConfig) -> String {
+            let result = build(config).unwrap();
+            std::fs::read_to_string(&result.target_results[0].output_file).unwrap()
+        }
+
+        #[test]
+        fn selects_subtree_by_path() {
+            let config = BuildConfig::new(place("places_path.rbxl"), output_dir())
+                .with_root_path("ReplicatedStorage.Client".to_string());
+            let content = bundled(&config);
+
+            assert!(content.contains("__rbx(\"Client\", \"Folder\", 1, nil)"));
+            assert!(content.contains("\"Client.Main\""));
+            assert!(!content.contains("ReplicatedStorage"));
+        }
+
+        #[test]
+        fn resolves_services_by_class_and_game_prefix() {
+            let config = BuildConfig::new(place("places_service.rbxl"), output_dir())
+                .with_root_path("game.Workspace".to_string());
+            let content = bundled(&config);
+
+            assert!(content.contains("__rbx(\"World\", \"Workspace\", 1, nil)"));
+        }
+
+        #[test]
+        fn unknown_path_lists_children() {
+            let config = BuildConfig::new(place("places_unknown.rbxl"), output_dir())
+                .with_root_path("ReplicatedStorage.Server".to_string());
+            let err = build(&config).unwrap_err().to_string();
+
+            assert!(err.contains("'Server' under ReplicatedStorage"), "{err}");
+            assert!(err.contains("available: Client"), "{err}");
+        }
+    }
+
+    mod rojo {
+        use super::*;
+        use std::fs;
+
+        /// Creates a fresh project directory under the output directory.
+        fn project_dir(name: &str) -> PathBuf {
+            let dir = output_dir().join(name);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("src/client/Controllers")).unwrap();
+            fs::write(dir.join("src/client/init.client.luau"), "require(script.Controllers.Input)").unwrap();
+            fs::write(dir.join("src/client/Controllers/Input.luau"), "return {}").unwrap();
+            fs::write(dir.join("src/client/Controllers/Boot.server.lua"), "print('server')").unwrap();
+            fs::write(dir.join("src/client/Controllers/notes.md"), "ignored").unwrap();
+            dir
+        }
+
+        fn bundled(input: PathBuf) -> String {
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+            assert!(result.is_success());
+            fs::read_to_string(&result.target_results[0].output_file).unwrap()
+        }
+
+        #[test]
+        fn syncs_directories_with_naming_conventions() {
+            let dir = project_dir("rojo_sync");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$path": "src/client" } }"#,
+            ).unwrap();
+
+            let content = bundled(dir.join("default.project.json"));
+            assert!(content.contains("__lua(\"App\", \"LocalScript\", 1, nil"));
+            assert!(content.contains("__lua(\"Input\", \"ModuleScript\""));
+            assert!(content.contains("\"App.Controllers.Input\""));
+            assert!(content.contains("\"Boot\", \"Script\""));
+            assert!(!content.contains("notes"));
+        }
+
+        #[test]
+        fn data_model_tree_with_properties() {
+            let dir = project_dir("rojo_datamodel");
+            fs::write(
+                dir.join("game.project.json"),
+                r#"{
+                    "name": "Game",
+                    "tree": {
+                        "$className": "DataModel",
+                        "ReplicatedStorage": {
+                            "Client": { "$path": "src/client" },
+                            "Config": {
+                                "$className": "StringValue",
+                                "$properties": { "Value": "hello" }
+                            }
+                        }
+                    }
+                }"#,
+            ).unwrap();
+
+            let config = BuildConfig::new(dir.join("game.project.json"), output_dir())
+                .with_root_path("ReplicatedStorage".to_string());
+            let result = build(&config).unwrap();
+            let content = fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(result.target_results[0].output_file.ends_with("game.debug.lua"));
+            assert!(content.contains("\"ReplicatedStorage.Client.Controllers.Input\""));
+            assert!(content.contains("__rbx(\"Config\", \"StringValue\""));
+        }
+
+        #[test]
+        fn directory_input_uses_default_project() {
+            let dir = project_dir("rojo_directory");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Folder", "Client": { "$path": "src/client" } } }"#,
+            ).unwrap();
+
+            let content = bundled(dir);
+            assert!(content.contains("\"App.Client.Controllers.Input\""));
+        }
+
+        #[test]
+        fn invalid_property_value() {
+            let dir = project_dir("rojo_invalid");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Part", "$properties": { "Size": [1, 2, 3] } } }"#,
+            ).unwrap();
+
+            let err = build(&BuildConfig::new(dir, output_dir())).unwrap_err();
+            assert!(format!("{err:#}").contains("property 'Size'"), "{err:#}");
+        }
+
+        #[test]
+        fn implicit_numbers_take_the_property_type() {
+            let dir = project_dir("rojo_numbers");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Part", "$properties": { "Material": 288, "Transparency": 0.5 } } }"#,
+            ).unwrap();
+
+            let content = bundled(dir);
+            assert!(content.contains("__props(1, { Material = Enum.Material.Neon, Transparency = 0.5 })"), "{content}");
+        }
+
+        #[test]
+        fn projects_including_themselves() {
+            let dir = project_dir("rojo_recursive");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Folder", "Again": { "$path": "default.project.json" } } }"#,
+            ).unwrap();
+
+            let err = build(&BuildConfig::new(dir, output_dir())).unwrap_err();
+            assert!(matches!(err, rbxts_bundler::bundler::BundlerError::Decode { .. }), "{err:?}");
+            assert!(err.to_string().contains("Project includes itself"), "{err}");
+        }
+
+        #[test]
+        fn classes_are_only_inferred_for_services() {
+            let dir = project_dir("rojo_unclassed");
+            fs::write(
+                dir.join("default.project.json"),
+                r#"{ "name": "App", "tree": { "$className": "Folder", "Part": {} } }"#,
+            ).unwrap();
+
+            let err = build(&BuildConfig::new(dir, output_dir())).unwrap_err();
+            assert!(matches!(err, rbxts_bundler::bundler::BundlerError::Decode { .. }), "{err:?}");
+            assert!(err.to_string().contains("'Part' needs a $className or a $path"), "{err}");
+        }
+    }
+
+    mod rbxts {
+        use super::*;
+        use rbxts_bundler::bundler::InputKind;
+        use std::fs;
+
+        fn project(name: &str, with_packages: bool) -> PathBuf {
+            let dir = output_dir().join(name);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("out/shared")).unwrap();
+            fs::create_dir_all(dir.join("include")).unwrap();
+            fs::write(dir.join("out/main.client.lua"), "local TS = require(script.Parent.include.RuntimeLib)").unwrap();
+            fs::write(dir.join("out/shared/util.lua"), "return {}").unwrap();
+            fs::write(dir.join("include/RuntimeLib.lua"), "return {}").unwrap();
+            fs::write(dir.join("include/Promise.lua"), "return {}").unwrap();
+            if with_packages {
+                fs::create_dir_all(dir.join("node_modules/@rbxts/services")).unwrap();
+                fs::write(dir.join("node_modules/@rbxts/services/init.lua"), "return {}").unwrap();
+                fs::write(dir.join("node_modules/@rbxts/services/index.d.ts"), "").unwrap();
+            }
+            dir
+        }
+
+        #[test]
+        fn maps_model_template_layout() {
+            let dir = project("rbxts_layout", true);
+            let config = BuildConfig::new(dir, output_dir()).with_input_kind(InputKind::RobloxTs);
+            let result = build(&config).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(content.contains("__rbx(\"rbxts_layout\", \"Folder\", 1, nil)"));
+            assert!(content.contains("\"rbxts_layout.main\""));
+            assert!(content.contains("\"rbxts_layout.shared.util\""));
+            assert!(content.contains("\"rbxts_layout.include.RuntimeLib\""));
+            assert!(content.contains("\"rbxts_layout.include.node_modules.@rbxts.services\""));
+        }
+
+        #[test]
+        fn packages_are_optional() {
+            let dir = project("rbxts_no_packages", false);
+            let config = BuildConfig::new(dir, output_dir()).with_input_kind(InputKind::RobloxTs);
+            assert!(build(&config).unwrap().is_success());
+        }
+
+        #[test]
+        fn missing_out_directory() {
+            let dir = project("rbxts_missing_out", false);
+            fs::remove_dir_all(dir.join("out")).unwrap();
+            let config = BuildConfig::new(dir, output_dir()).with_input_kind(InputKind::RobloxTs);
+            let err = build(&config).unwrap_err().to_string();
+            assert!(err.contains("'out'"), "{err}");
+        }
+    }
+
+    mod formats {
+        use super::*;
+
+        #[test]
+        fn xml_model_matches_binary() {
+            let dom = rbx_binary::from_reader(std::fs::File::open(test_rbxm()).unwrap()).unwrap();
+            let xml_path = output_dir().join("formats_xml.rbxmx");
+            let file = std::fs::File::create(&xml_path).unwrap();
+            rbx_xml::to_writer_default(file, &dom, dom.root().children()).unwrap();
+
+            let binary = build(&BuildConfig::new(test_rbxm(), output_dir())).unwrap();
+            let xml = build(&BuildConfig::new(xml_path, output_dir())).unwrap();
+            assert!(xml.is_success());
+
+            let read = |r: &rbxts_bundler::BuildResult| {
+                std::fs::read_to_string(&r.target_results[0].output_file).unwrap()
+            };
+            let (binary, xml) = (read(&binary), read(&xml));
+            let tree = |s: &str| s.split_once("-- Instance Tree Manifest").unwrap().1.to_string();
+            assert_eq!(tree(&binary), tree(&xml));
+        }
+
+        #[test]
+        fn unrecognized_format() {
+            let path = output_dir().join("formats_unknown.txt");
+            std::fs::write(&path, "not a model").unwrap();
+            let err = build(&BuildConfig::new(path, output_dir())).unwrap_err();
+            assert!(err.to_string().contains("Unrecognized model format"), "{err}");
+        }
+    }
+
+    mod inline_dev {
+        use super::*;
+        use rbxts_bundler::bundler::SourceMap;
+
+        #[test]
+        fn scripts_are_inlined_without_loadstring() {
+            let input = write_model("inline_dev.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "local value = 1\nerror(\"boom\")")),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_targets(vec![Target::DevInline]);
+            let result = build(&config).unwrap();
+            let target_result = &result.target_results[0];
+            let content = std::fs::read_to_string(&target_result.output_file).unwrap();
+
+            assert!(target_result.output_file.ends_with("inline_dev.debug.inline.lua"));
+            assert!(!content.contains("loadstring"));
+            assert!(content.contains("__lua(\"Main\", \"LocalScript\", 2, 1, __named(\"Root.Main\", function()"));
+            assert!(content.contains("\tlocal _=__env(2)\n"));
+
+            let map = SourceMap::load(&target_result.source_map_file).unwrap();
+            let module = &map.modules[0];
+            assert_eq!(module.chunk, None);
+            let lines: Vec<&str> = content.lines().collect();
+            assert_eq!(lines[module.end_line - 1], "error(\"boom\")");
+        }
+
+        #[test]
+        fn compat_variant() {
             let config = BuildConfig::new(test_rbxm(), output_dir())
-                .with_targets(vec![]);
-            assert!(build(&config).is_err());
+                .with_targets(vec![Target::DevInlineCompat]);
+            let result = build(&config).unwrap();
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+
+            assert!(result.target_results[0].output_file.ends_with("build.debug.inline.c.lua"));
+            assert!(!content.contains("loadstring"));
         }
     }
-}
 
-mod cli {
-    use super::*;
+    mod darklua {
+        use super::*;
+        use rbxts_bundler::bundler::DarkluaOverride;
 
-    mod info {
+        #[test]
+        fn extension_is_applied_to_its_target() {
+            let input = write_model("darklua_extend.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "if DEBUG_BUILD then print(\"debug\") end")),
+            ]);
+            let extension = r#"{ "rules": [{ "rule": "inject_global_value", "identifier": "DEBUG_BUILD", "value": false }] }"#;
+            let config = BuildConfig::new(input, output_dir())
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_darklua_override(Target::Rel, DarkluaOverride::Extend(extension.to_string()));
+            let result = build(&config).unwrap();
+
+            let dev = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+            let rel = std::fs::read_to_string(&result.target_results[1].output_file).unwrap();
+            assert!(dev.contains("DEBUG_BUILD"));
+            assert!(!rel.contains("DEBUG_BUILD"));
+        }
+
+        #[test]
+        fn invalid_config_fails_before_building() {
+            let input = write_model("darklua_invalid.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Root"),
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_darklua_override(Target::Dev, DarkluaOverride::Extend(r#"{ "rules": ["nope"] }"#.to_string()));
+
+            let err = build(&config).unwrap_err();
+            assert!(err.to_string().contains("target 'dev'"), "{err}");
+            assert!(!output_dir().join("darklua_invalid.debug.lua").exists());
+        }
+    }
+
+    mod requires {
         use super::*;
+        use rbxts_bundler::bundler::{DiagnosticCode, Severity};
+
+        fn cyclic_model(file_name: &str) -> PathBuf {
+            write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "require(script.Parent.A)"))
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("A")
+                            .with_property("Source", "local B = require(script.Parent.B)\nreturn {}"),
+                    )
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("B")
+                            .with_property("Source", "local A = require(script.Parent.A)\nreturn {}"),
+                    ),
+            ])
+        }
 
         #[test]
-        fn help() {
-            let out = Command::new(cli_binary()).arg("--help").output().unwrap();
-            assert!(out.status.success());
-            assert!(String::from_utf8_lossy(&out.stdout).contains("rbxts-bundler"));
+        fn cycles_are_warnings() {
+            let out_dir = output_dir().join("requires_cycle");
+            let config = BuildConfig::new(cyclic_model("requires_cycle.rbxm"), out_dir.clone())
+                .with_targets(vec![Target::Dev, Target::Rel]);
+            let result = build(&config).unwrap();
+            assert!(result.is_success());
+
+            let cycle = &result.diagnostics[0];
+            assert_eq!((cycle.severity, cycle.code), (Severity::Warning, DiagnosticCode::RequireCycle));
+            assert_eq!(cycle.path, "Root.A");
+            assert!(cycle.message.contains("Root.A → Root.B → Root.A"), "{}", cycle.message);
+
+            let denied = build(&config.with_deny_warnings(true)).unwrap();
+            for target_result in &denied.target_results {
+                assert_eq!(target_result.error_message.as_deref(), Some("Found 1 error in the input"));
+            }
         }
 
         #[test]
-        fn version() {
-            let out = Command::new(cli_binary()).arg("--version").output().unwrap();
-            assert!(out.status.success());
+        fn requires_inside_functions_are_not_cycles() {
+            let input = write_model("requires_lazy.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "require(script.Parent.A)"))
+                    .with_child(InstanceBuilder::new("ModuleScript").with_name("A").with_property(
+                        "Source",
+                        "local B = require(script.Parent.B)\nreturn { value = 1 }",
+                    ))
+                    .with_child(InstanceBuilder::new("ModuleScript").with_name("B").with_property(
+                        "Source",
+                        "return { get = function()\n\treturn require(script.Parent.A).value\nend }",
+                    )),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_deny_warnings(true);
+            let result = build(&config).unwrap();
+
+            assert!(result.is_success(), "{:?}", result.diagnostics);
+            assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+        }
+
+        #[test]
+        fn unresolved_requires_are_warnings() {
+            let input = write_model("requires_guarded.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Root").with_child(local_script(
+                    "Main",
+                    "if script.Parent:FindFirstChild(\"X\") then\n\trequire(script.Parent.X)\nend",
+                )),
+            ]);
+            let result = build(&BuildConfig::new(input.clone(), output_dir())).unwrap();
+            assert!(result.is_success());
+            let warning = &result.diagnostics[0];
+            assert_eq!((warning.severity, warning.code), (Severity::Warning, DiagnosticCode::UnresolvedRequire));
+            assert_eq!(warning.path, "Root.Main");
+            assert!(warning.message.contains("line 2"), "{}", warning.message);
+
+            let denied = build(&BuildConfig::new(input, output_dir()).with_deny_warnings(true)).unwrap();
+            assert!(!denied.is_success());
+        }
+
+        #[test]
+        fn check_can_be_disabled() {
+            let config = BuildConfig::new(cyclic_model("requires_unchecked.rbxm"), output_dir())
+                .with_check_requires(false);
+            assert!(build(&config).unwrap().is_success());
         }
     }
 
-    mod build_cmd {
+    mod tree_shaking {
         use super::*;
+        use rbxts_bundler::bundler::{build_in_memory, DiagnosticCode, MemoryBuildResult, StartPolicy, TreeShaking};
+
+        fn module(name: &str, source: &str) -> InstanceBuilder {
+            InstanceBuilder::new("ModuleScript")
+                .with_name(name)
+                .with_property("Source", source)
+        }
+
+        /// `Main` requires `Used`, which lazily requires `Lazy`; `Unused` and
+        /// `Holder` are never required, but `Holder` holds a server script.
+        fn shaken_model(file_name: &str, main_source: &str) -> PathBuf {
+            write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", main_source))
+                    .with_child(module("Used", "return function() return require(script.Parent.Lazy) end"))
+                    .with_child(module("Lazy", "return 'LAZY_MODULE'"))
+                    .with_child(
+                        module("Unused", "return 'UNUSED_MODULE'")
+                            .with_child(module("Nested", "return 'NESTED_MODULE'")),
+                    )
+                    .with_child(
+                        module("Holder", "return 'HOLDER_MODULE'").with_child(
+                            InstanceBuilder::new("Script")
+                                .with_name("Server")
+                                .with_property("Source", "print('server')"),
+                        ),
+                    ),
+            ])
+        }
+
+        fn shake(file_name: &str, main_source: &str, mode: TreeShaking) -> MemoryBuildResult {
+            let config = BuildConfig::new(shaken_model(file_name, main_source), output_dir())
+                .with_tree_shaking(mode);
+            build_in_memory(&config).unwrap()
+        }
 
         #[test]
-        fn single_target() {
-            let out = Command::new(cli_binary())
-                .args([
-                    "build", test_rbxm().to_str().unwrap(),
-                    "--out-dir", output_dir().to_str().unwrap(),
-                    "-t", "dev",
-                ])
-                .output()
-                .unwrap();
+        fn drops_unreachable_modules() {
+            let result = shake("shake_remove.rbxm", "require(script.Parent.Used)", TreeShaking::Remove);
+            let report = result.tree_shaking.unwrap();
+            let source = &result.bundles[0].source;
 
-            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            let removed: Vec<(&str, bool)> =
+                report.removed.iter().map(|m| (m.path.as_str(), m.instance_removed)).collect();
+            assert_eq!(
+                removed,
+                [("Root.Unused", true), ("Root.Unused.Nested", true), ("Root.Holder", false)]
+            );
+            assert_eq!(report.kept_modules, 2);
+            assert_eq!(report.bytes_saved(), "return 'UNUSED_MODULE'".len() * 3);
+
+            assert!(source.contains("LAZY_MODULE"));
+            for dropped in ["UNUSED_MODULE", "NESTED_MODULE", "HOLDER_MODULE", "\"Unused\""] {
+                assert!(!source.contains(dropped), "{dropped} in {source}");
+            }
+            assert!(source.contains("\"Holder\"") && source.contains("print('server')"));
         }
 
         #[test]
-        fn multiple_targets() {
-            let out = Command::new(cli_binary())
-                .args([
-                    "build", test_rbxm().to_str().unwrap(),
-                    "--out-dir", output_dir().to_str().unwrap(),
-                    "-t", "dev", "-t", "rel", "-t", "dev-compat", "-t", "rel-compat",
-                ])
-                .output()
-                .unwrap();
+        fn keeps_instances() {
+            let result = shake("shake_keep.rbxm", "require(script.Parent.Used)", TreeShaking::KeepInstances);
+            let report = result.tree_shaking.unwrap();
+            let source = &result.bundles[0].source;
 
-            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert!(report.removed.iter().all(|m| !m.instance_removed));
+            assert!(source.contains("__rbx(\"Unused\", \"ModuleScript\""), "{source}");
+            assert!(!source.contains("UNUSED_MODULE"));
         }
 
         #[test]
-        fn silent_mode() {
-            let out = Command::new(cli_binary())
-                .args([
-                    "build", test_rbxm().to_str().unwrap(),
-                    "--out-dir", output_dir().to_str().unwrap(),
-                    "-t", "dev", "--silent",
-                ])
-                .output()
-                .unwrap();
+        fn dynamic_requires_keep_every_module() {
+            let main = "local name = 'Unused'\nrequire(script.Parent[name])";
+            let result = shake("shake_dynamic.rbxm", main, TreeShaking::Remove);
 
-            assert!(out.status.success());
-            assert!(String::from_utf8_lossy(&out.stdout).lines().count() <= 2);
+            assert!(result.tree_shaking.unwrap().removed.is_empty());
+            assert!(result.bundles[0].source.contains("UNUSED_MODULE"));
+            let [diagnostic] = result.diagnostics.as_slice() else {
+                panic!("{:?}", result.diagnostics);
+            };
+            assert_eq!((diagnostic.code, diagnostic.path.as_str()), (DiagnosticCode::DynamicRequire, "Root.Main"));
+            assert!(diagnostic.message.contains("line 2"), "{diagnostic}");
+        }
+
+        #[test]
+        fn follows_aliased_paths() {
+            let main = "local Shared = script.Parent.Parent.Shared\nlocal Foo = require(Shared.Foo)\nprint(Foo)";
+            let input = write_model("shake_alias.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Game")
+                    .with_child(InstanceBuilder::new("Folder").with_name("Client").with_child(local_script("Main", main)))
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("Shared")
+                            .with_child(module("Foo", "return 'FOO_MODULE'"))
+                            .with_child(module("Bar", "return 'BAR_MODULE'")),
+                    ),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_tree_shaking(TreeShaking::Remove);
+            let result = build_in_memory(&config).unwrap();
+
+            let report = result.tree_shaking.unwrap();
+            let removed: Vec<&str> = report.removed.iter().map(|m| m.path.as_str()).collect();
+            assert_eq!(removed, ["Game.Shared.Bar"]);
+            assert!(result.bundles[0].source.contains("FOO_MODULE"));
+            assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+        }
+
+        #[test]
+        fn disabled_by_default() {
+            let config = BuildConfig::new(shaken_model("shake_off.rbxm", ""), output_dir());
+            let result = build_in_memory(&config).unwrap();
+
+            assert!(result.tree_shaking.is_none());
+            assert!(result.bundles[0].source.contains("UNUSED_MODULE"));
+        }
+
+        #[test]
+        fn needs_a_launched_script() {
+            let config = BuildConfig::new(shaken_model("shake_none.rbxm", ""), output_dir())
+                .with_start_policy(StartPolicy::None)
+                .with_tree_shaking(TreeShaking::Remove);
+            let err = build_in_memory(&config).unwrap_err();
+            assert!(err.to_string().contains("launches none"), "{err}");
+        }
+    }
+
+    mod project_file {
+        use super::*;
+        use rbxts_bundler::bundler::ProjectConfig;
+
+        fn project_dir(name: &str, project: &str) -> PathBuf {
+            let dir = output_dir().join(name);
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::copy(test_rbxm(), dir.join("model.rbxm")).unwrap();
+            std::fs::write(dir.join("rbxts-bundler.toml"), project).unwrap();
+            dir
+        }
+
+        #[test]
+        fn paths_are_relative_to_the_project_file() {
+            let dir = project_dir("project_relative", "input = \"model.rbxm\"\nout-dir = \"dist\"\ntargets = [\"dev\", \"rel\"]\n");
+            let project = ProjectConfig::load(&dir.join("rbxts-bundler.toml")).unwrap();
+            let result = build(&project.to_build_config().unwrap()).unwrap();
+
+            assert!(dir.join("dist/model.debug.lua").exists());
+            assert!(dir.join("dist/model.release.lua").exists());
+            assert_eq!(result.target_results.len(), 2);
+        }
+
+        #[test]
+        fn defines_are_injected() {
+            let input = write_model("project_defines.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(BUILD_NAME)")),
+            ]);
+            let dir = project_dir("project_defines", "out-dir = \"dist\"\n[defines]\nBUILD_NAME = \"nightly\"\n");
+            let mut project = ProjectConfig::load(&dir.join("rbxts-bundler.toml")).unwrap();
+            project.input = Some(input);
+            let result = build(&project.to_build_config().unwrap()).unwrap();
+
+            let content = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+            assert!(content.contains("nightly"));
+            assert!(!content.contains("BUILD_NAME"));
+        }
+
+        #[test]
+        fn missing_input() {
+            let dir = project_dir("project_missing_input", "out-dir = \"dist\"\n");
+            let project = ProjectConfig::load(&dir.join("rbxts-bundler.toml")).unwrap();
+
+            let err = project.to_build_config().unwrap_err();
+            assert!(err.to_string().contains("`input`"), "{err}");
+        }
+    }
+
+    mod custom_targets {
+        use super::*;
+        use rbxts_bundler::bundler::{CustomTarget, DefineValue};
+
+        #[test]
+        fn builds_with_own_suffix_and_defines() {
+            let input = write_model("custom_target.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(STAGE)")),
+            ]);
+            let staging = Target::custom(
+                CustomTarget::new("staging".to_string(), &Target::Rel)
+                    .with_define("STAGE".to_string(), DefineValue::String("staging".to_string())),
+            );
+            let config = BuildConfig::new(input, output_dir()).with_targets(vec![Target::Rel, staging.clone()]);
+            let result = build(&config).unwrap();
+
+            assert_eq!(result.target_results[1].target, staging);
+            assert!(result.target_results[1].output_file.ends_with("custom_target.staging.lua"));
+            let rel = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+            let custom = std::fs::read_to_string(&result.target_results[1].output_file).unwrap();
+            assert!(rel.contains("STAGE"));
+            assert!(custom.contains("print'staging'"));
+        }
+
+        #[test]
+        fn builtin_name() {
+            let custom = Target::custom(CustomTarget::new("rel".to_string(), &Target::Dev).with_file_suffix("custom".to_string()));
+            let config = BuildConfig::new(test_rbxm(), output_dir()).with_targets(vec![custom]);
+
+            let err = build(&config).unwrap_err();
+            assert!(err.to_string().contains("built-in"), "{err}");
+        }
+    }
+
+    mod output_names {
+        use super::*;
+
+        #[test]
+        fn template_placeholders() {
+            let config = BuildConfig::new(test_rbxm(), output_dir())
+                .with_targets(vec![Target::Dev, Target::RelCompat])
+                .with_output_name("{name}-{target}.lua".to_string());
+            let result = build(&config).unwrap();
+
+            assert!(result.target_results[0].output_file.ends_with("build-dev.lua"));
+            assert!(result.target_results[1].output_file.ends_with("build-rel-compat.lua"));
+        }
+
+        #[test]
+        fn duplicate_names() {
+            let config = BuildConfig::new(test_rbxm(), output_dir())
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_output_name("bundle.lua".to_string());
+
+            let err = build(&config).unwrap_err();
+            assert!(err.to_string().contains("bundle.lua"), "{err}");
+        }
+    }
+
+    mod source_maps {
+        use super::*;
+        use rbxts_bundler::bundler::{build_in_memory, DarkluaOverride, SourceMap};
+
+        fn build_map(file_name: &str, target: Target) -> (String, SourceMap) {
+            let input = write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "local value = 1\n\nerror(\"boom\")")),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_targets(vec![target]);
+            let result = build(&config).unwrap();
+            let target_result = &result.target_results[0];
+
+            let content = std::fs::read_to_string(&target_result.output_file).unwrap();
+            (content, SourceMap::load(&target_result.source_map_file).unwrap())
+        }
+
+        #[test]
+        fn dev_scripts_map_to_their_chunks() {
+            let (_, map) = build_map("sourcemap_dev.rbxm", Target::Dev);
+
+            assert_eq!(map.file, "sourcemap_dev.debug.lua");
+            let module = &map.modules[0];
+            assert_eq!(module.path, "Root.Main");
+            assert_eq!(module.chunk.as_deref(), Some("Root.Main"));
+            assert_eq!((module.start_line, module.end_line), (2, 4));
+            assert_eq!(map.resolve("Root.Main", 4, None).unwrap().1, Some(3));
+        }
+
+        #[test]
+        fn rel_columns_point_into_the_bundle() {
+            let (content, map) = build_map("sourcemap_rel.rbxm", Target::Rel);
+
+            let module = &map.modules[0];
+            assert_eq!(module.chunk, None);
+            assert_eq!(module.original_line, None);
+            assert_eq!(module.start_line, module.end_line);
+
+            let line = content.lines().nth(module.start_line - 1).unwrap();
+            let registration = &line[module.start_column - 1..module.end_column];
+            assert!(registration.ends_with(')'), "{registration}");
+            let column = line.rfind("error").unwrap() + 1;
+            assert!(column > module.start_column && column < module.end_column);
+
+            let trace = format!("[string \"bundle\"]:{}:{column}: boom", module.start_line);
+            assert_eq!(map.symbolicate(&trace), "Root.Main: boom");
+            let runtime = format!("[string \"bundle\"]:{}:1: boom", module.start_line);
+            assert_eq!(map.symbolicate(&runtime), runtime);
+        }
+
+        #[test]
+        fn rel_lines_when_the_generator_retains_them() {
+            let input = write_model("sourcemap_rel_lines.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "local value = 1\n\nerror(\"boom\")")),
+            ]);
+            let extension = r#"{ "generator": "retain_lines" }"#;
+            let config = BuildConfig::new(input, output_dir())
+                .with_targets(vec![Target::Rel])
+                .with_darklua_override(Target::Rel, DarkluaOverride::Extend(extension.to_string()));
+            let bundle = build_in_memory(&config).unwrap().bundles.remove(0);
+
+            let module = &bundle.source_map.modules[0];
+            assert_eq!(module.original_line, Some(1));
+            let lines: Vec<&str> = bundle.source.lines().collect();
+            assert!(lines[module.start_line + 1].contains("error"), "{}", lines[module.start_line + 1]);
+            let trace = format!("[string \"bundle\"]:{}: boom", module.start_line + 2);
+            assert_eq!(bundle.source_map.symbolicate(&trace), "Root.Main:3: boom");
+        }
+    }
+
+    mod size_reports {
+        use super::*;
+        use rbxts_bundler::bundler::{build_in_memory, DarkluaOverride, SizeReport};
+
+        #[test]
+        fn written_next_to_outputs() {
+            let out_dir = output_dir().join("size_reports");
+            let config = BuildConfig::new(test_rbxm(), out_dir)
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_size_report(true);
+            let result = build(&config).unwrap();
+
+            for target_result in &result.target_results {
+                let report = target_result.size_report.as_ref().unwrap();
+                let output = std::fs::read_to_string(&target_result.output_file).unwrap();
+                let path = target_result.output_file.with_extension("lua.size.json");
+                assert_eq!(&SizeReport::load(&path).unwrap(), report);
+
+                assert_eq!(report.total_bytes, output.len());
+                assert_eq!(
+                    report.header_bytes + report.runtime_bytes + report.tree_bytes + report.modules_bytes(),
+                    report.total_bytes
+                );
+                assert!(report.header_bytes > 0 && report.runtime_bytes > 0);
+                assert!(!report.modules.is_empty());
+                assert!(report.instance_count() >= report.modules.len());
+            }
+        }
+
+        #[test]
+        fn modules_measured_after_darklua() {
+            let source = "-- a long comment that minification drops\nlocal value = 1 + 1\n\n\nreturn value\n";
+            let input = write_model("size_report_module.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "require(script.Parent.Module)"))
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("Module")
+                            .with_property("Source", source),
+                    ),
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_targets(vec![Target::Rel])
+                .with_size_report(true);
+            let bundle = build_in_memory(&config).unwrap().bundles.remove(0);
+            let report = bundle.size_report.unwrap();
+
+            let module = report.modules.iter().find(|m| m.path == "Root.Module").unwrap();
+            assert_eq!(module.source_bytes, source.len());
+            assert!(module.output_bytes > 0);
+            assert!(!bundle.source.contains("a long comment"));
+            assert_eq!(report.instances.get("ModuleScript"), Some(&1));
+            assert_eq!(report.instances.get("Folder"), Some(&1));
+        }
+
+        #[test]
+        fn layout_follows_the_generator() {
+            let input = write_model("size_report_generators.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "require(script.Parent.Module)"))
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("Module")
+                            .with_property("Source", "local value = 1\nreturn value\n"),
+                    ),
+            ]);
+            let mut header_bytes = None;
+            for generator in ["retain_lines", "dense", "readable"] {
+                let extension = format!(r#"{{ "generator": "{generator}" }}"#);
+                let config = BuildConfig::new(input.clone(), output_dir())
+                    .with_targets(vec![Target::Rel])
+                    .with_darklua_override(Target::Rel, DarkluaOverride::Extend(extension))
+                    .with_size_report(true);
+                let bundle = build_in_memory(&config).unwrap().bundles.remove(0);
+                let report = bundle.size_report.unwrap();
+
+                assert!(bundle.source[..report.header_bytes].ends_with(".\n"), "{generator}");
+                assert_eq!(*header_bytes.get_or_insert(report.header_bytes), report.header_bytes);
+                assert!(report.runtime_bytes > 0, "{generator}");
+                assert!(report.modules.iter().all(|module| module.output_bytes > 0), "{generator}");
+                assert!(report.tree_bytes > 0, "{generator}");
+                assert!(bundle.diagnostics.is_empty(), "{generator}");
+            }
+        }
+
+        #[test]
+        fn disabled_by_default() {
+            let out_dir = output_dir().join("size_reports_off");
+            let result = build(&BuildConfig::new(test_rbxm(), out_dir)).unwrap();
+            let target_result = &result.target_results[0];
+
+            assert!(target_result.size_report.is_none());
+            assert!(!target_result.output_file.with_extension("lua.size.json").exists());
+        }
+    }
+
+    mod analyzer {
+        use super::*;
+        use rbxts_bundler::bundler::build_in_memory;
+
+        /// Returns the data embedded into an analyzer page.
+        fn embedded_data(page: &str) -> serde_json::Value {
+            let start = page.find(r#"<script type="application/json" id="data">"#).unwrap();
+            let data = &page[start..];
+            let data = &data[data.find('>').unwrap() + 1..data.find("</script>").unwrap()];
+            serde_json::from_str(data).unwrap()
+        }
+
+        fn total(node: &serde_json::Value) -> u64 {
+            node["size"].as_u64().unwrap()
+                + node["children"].as_array().unwrap().iter().map(total).sum::<u64>()
+        }
+
+        #[test]
+        fn written_next_to_outputs() {
+            let out_dir = output_dir().join("analyzer");
+            let config = BuildConfig::new(test_rbxm(), out_dir)
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_analyzer(true);
+            let result = build(&config).unwrap();
+
+            for target_result in &result.target_results {
+                let analyzer_file = target_result.analyzer_file.as_ref().unwrap();
+                assert_eq!(analyzer_file, &target_result.output_file.with_extension("lua.analyzer.html"));
+
+                let page = std::fs::read_to_string(analyzer_file).unwrap();
+                assert!(!page.contains("{{"), "unreplaced placeholder");
+                let data_start = page.find(r#"id="data">"#).unwrap();
+                let page_code = format!("{}{}", &page[..data_start], &page[page.rfind("<script>").unwrap()..]);
+                assert!(!page_code.contains("http://") && !page_code.contains("https://"), "network dependency");
+
+                let data = embedded_data(&page);
+                let output = std::fs::read_to_string(&target_result.output_file).unwrap();
+                assert_eq!(data["total_bytes"], output.len());
+                assert_eq!(total(&data["tree"]), output.len() as u64);
+            }
+        }
+
+        #[test]
+        fn embeds_sources_safely() {
+            let source = "print('</script><script>alert(1)</script>')";
+            let input = write_model("analyzer_escape.rbxm", vec![local_script("Main", source)]);
+            let config = BuildConfig::new(input, output_dir()).with_analyzer(true);
+            let page = build_in_memory(&config).unwrap().bundles.remove(0).analyzer.unwrap();
+
+            assert!(!page.contains("<script>alert"));
+            let data = embedded_data(&page);
+            let main = data["tree"]["children"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|node| node["path"] == "Main")
+                .unwrap();
+            assert_eq!(main["class"], "LocalScript");
+            assert_eq!(main["source"], source);
+        }
+
+        #[test]
+        fn disabled_by_default() {
+            let result = build(&BuildConfig::new(test_rbxm(), output_dir().join("analyzer_off"))).unwrap();
+            assert!(result.target_results[0].analyzer_file.is_none());
+        }
+    }
+
+    mod json_results {
+        use super::*;
+
+        #[test]
+        fn serializes_targets_with_sizes_and_hashes() {
+            let out_dir = output_dir().join("json_results");
+            let config = BuildConfig::new(test_rbxm(), out_dir).with_targets(vec![Target::Dev, Target::Rel]);
+            let result = build(&config).unwrap();
+            let json = serde_json::to_value(&result).unwrap();
+
+            assert!(json["duration_ms"].as_f64().unwrap() > 0.0);
+            let targets = json["targets"].as_array().unwrap();
+            assert_eq!(targets[0]["target"], "dev");
+            assert_eq!(targets[1]["target"], "rel");
+            for (target, target_result) in targets.iter().zip(&result.target_results) {
+                let output = std::fs::read(&target_result.output_file).unwrap();
+                assert_eq!(target["output_file"], target_result.output_file.to_str().unwrap());
+                assert_eq!(target["output_bytes"], output.len());
+                assert_eq!(target["hash"].as_str().unwrap().len(), 16);
+                assert!(target["duration_ms"].is_number());
+            }
+            assert_ne!(targets[0]["hash"], targets[1]["hash"]);
+        }
+
+        #[test]
+        fn same_output_same_hash() {
+            let hash = |dir: &str| {
+                let result = build(&BuildConfig::new(test_rbxm(), output_dir().join(dir))).unwrap();
+                result.target_results[0].hash.clone().unwrap()
+            };
+            assert_eq!(hash("json_hash_a"), hash("json_hash_b"));
+        }
+
+        #[test]
+        fn serializes_diagnostics() {
+            let outside = InstanceBuilder::new("Folder").with_name("Outside");
+            let outside_ref = outside.referent();
+            let input = write_model("json_diagnostics.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Root").with_child(
+                    InstanceBuilder::new("Folder").with_name("Client").with_child(
+                        InstanceBuilder::new("ObjectValue").with_name("Link").with_property("Value", outside_ref),
+                    ),
+                ),
+                outside,
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_root_path("Root.Client".to_string());
+            let json = serde_json::to_value(build(&config).unwrap()).unwrap();
+
+            assert_eq!(json["diagnostics"], serde_json::json!([]));
+            assert_eq!(
+                json["targets"][0]["diagnostics"],
+                serde_json::json!([{
+                    "severity": "warning",
+                    "code": "unresolved-reference",
+                    "path": "Client.Link",
+                    "message": "Value references an instance outside the bundle and was left unset",
+                }])
+            );
+        }
+    }
+
+    mod diagnostics {
+        use super::*;
+        use rbx_dom_weak::types::{BinaryString, Variant};
+        use rbxts_bundler::bundler::{build_from_dom, build_in_memory, DiagnosticCode, Severity};
+
+        fn codes(diagnostics: &[rbxts_bundler::bundler::Diagnostic]) -> Vec<(DiagnosticCode, &str)> {
+            diagnostics.iter().map(|d| (d.code, d.path.as_str())).collect()
+        }
+
+        fn suspicious_model(file_name: &str) -> PathBuf {
+            write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(1)"))
+                    .with_child(InstanceBuilder::new("ModuleScript").with_name("Util").with_property("Source", "return 1"))
+                    .with_child(InstanceBuilder::new("Folder").with_name("Util"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Part"))
+                    .with_child(InstanceBuilder::new("Part").with_name("Part"))
+                    .with_child(InstanceBuilder::new("NotARealClass").with_name("Mystery")),
+            ])
+        }
+
+        #[test]
+        fn input_warnings_do_not_fail_the_build() {
+            let result = build(&BuildConfig::new(suspicious_model("diagnostics_input.rbxm"), output_dir())).unwrap();
+
+            assert!(result.is_success());
+            // Same-named parts hold no script, so only `Util` is ambiguous
+            assert_eq!(
+                codes(&result.diagnostics),
+                vec![(DiagnosticCode::DuplicateName, "Root.Util"), (DiagnosticCode::UnknownClass, "Root.Mystery")]
+            );
+            assert!(result.diagnostics.iter().all(|d| d.severity == Severity::Warning));
+            assert_eq!(result.diagnostic_count(Severity::Warning), 2);
+        }
+
+        #[test]
+        fn lossy_sources() {
+            // Model decoders already replace invalid bytes, so only a DOM built
+            // in memory can hold them
+            let source = Variant::BinaryString(BinaryString::from(b"print('\xff')".to_vec()));
+            let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let root_ref = dom.root_ref();
+            dom.insert(root_ref, InstanceBuilder::new("LocalScript").with_name("Main").with_property("Source", source));
+            let result = build_from_dom(dom, &BuildConfig::new(PathBuf::from("lossy.rbxm"), PathBuf::new())).unwrap();
+
+            assert!(result.is_success());
+            assert_eq!(codes(&result.diagnostics), vec![(DiagnosticCode::LossySource, "Main")]);
+        }
+
+        #[test]
+        fn replacement_characters_are_not_lossy() {
+            let input = write_model("diagnostics_replacement.rbxm", vec![local_script("Main", "print('\u{FFFD}')")]);
+            let config = BuildConfig::new(input, output_dir()).with_deny_warnings(true);
+            let result = build(&config).unwrap();
+
+            assert!(result.is_success());
+            assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+        }
+
+        #[test]
+        fn ambiguous_root_path() {
+            let input = write_model("diagnostics_root.rbxm", vec![
+                InstanceBuilder::new("Folder").with_name("Client").with_child(local_script("First", "print(1)")),
+                InstanceBuilder::new("Folder").with_name("Client").with_child(local_script("Second", "print(2)")),
+            ]);
+            let config = BuildConfig::new(input, output_dir()).with_root_path("Client".to_string());
+            let result = build_in_memory(&config).unwrap();
+
+            assert_eq!(codes(&result.diagnostics), vec![(DiagnosticCode::AmbiguousRoot, "Client")]);
+            assert!(result.bundles[0].source.contains("Client.First"));
+        }
+
+        #[test]
+        fn denied_input_warnings_fail_every_target() {
+            let out_dir = output_dir().join("diagnostics_denied");
+            let config = BuildConfig::new(suspicious_model("diagnostics_denied.rbxm"), out_dir.clone())
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_deny_warnings(true);
+            let result = build(&config).unwrap();
+
+            assert!(!result.is_success());
+            assert!(result.diagnostics.iter().all(|d| d.severity == Severity::Error));
+            for target_result in &result.target_results {
+                assert_eq!(target_result.error_message.as_deref(), Some("Found 2 errors in the input"));
+                assert!(!target_result.output_file.exists());
+            }
+        }
+
+        #[test]
+        fn denied_target_warnings_fail_the_target() {
+            let outside = InstanceBuilder::new("Folder").with_name("Outside");
+            let outside_ref = outside.referent();
+            let input = write_model("diagnostics_denied_refs.rbxm", vec![
+                InstanceBuilder::new("ObjectValue").with_name("Link").with_property("Value", outside_ref),
+                outside,
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_root_path("Link".to_string())
+                .with_deny_warnings(true);
+            let bundle = build_in_memory(&config).unwrap().bundles.remove(0);
+
+            assert!(!bundle.success);
+            assert!(bundle.source.is_empty());
+            assert_eq!(bundle.error_message.as_deref(), Some("Denied 1 warning"));
+            assert_eq!(codes(&bundle.diagnostics), vec![(DiagnosticCode::UnresolvedReference, "Link")]);
+            assert!(bundle.diagnostics[0].is_error());
+        }
+    }
+
+    mod in_memory {
+        use super::*;
+        use rbxts_bundler::bundler::{build_from_bytes, build_from_dom, build_in_memory, BundlerError, SourceMap};
+
+        #[test]
+        fn matches_written_bundles() {
+            let out_dir = output_dir().join("in_memory");
+            let config = BuildConfig::new(test_rbxm(), out_dir.clone())
+                .with_targets(vec![Target::Dev, Target::Rel]);
+            let written = build(&config).unwrap();
+            let in_memory = build_in_memory(&config).unwrap();
+
+            assert!(in_memory.is_success());
+            for (bundle, target_result) in in_memory.bundles.iter().zip(&written.target_results) {
+                assert_eq!(bundle.target, target_result.target);
+                assert_eq!(bundle.source, std::fs::read_to_string(&target_result.output_file).unwrap());
+                assert_eq!(bundle.source_map, SourceMap::load(&target_result.source_map_file).unwrap());
+                assert_eq!(out_dir.join(&bundle.file_name), target_result.output_file);
+            }
+        }
+
+        #[test]
+        fn writes_nothing() {
+            let out_dir = output_dir().join("in_memory_unused");
+            let _ = std::fs::remove_dir_all(&out_dir);
+            let result = build_in_memory(&BuildConfig::new(test_rbxm(), out_dir.clone())).unwrap();
+
+            assert_eq!(result.success_count(), result.bundles.len());
+            assert!(!out_dir.exists());
+        }
+
+        #[test]
+        fn from_bytes_matches_file_input() {
+            let out_dir = output_dir().join("in_memory_bytes");
+            let _ = std::fs::remove_dir_all(&out_dir);
+            let contents = std::fs::read(test_rbxm()).unwrap();
+            let from_file = build_in_memory(&BuildConfig::new(test_rbxm(), out_dir.clone())).unwrap();
+
+            // The input path only names the bundles and is never read
+            let config = BuildConfig::new(PathBuf::from("build.rbxm"), out_dir.clone());
+            let from_bytes = build_from_bytes(&contents, &config).unwrap();
+
+            assert!(from_bytes.is_success());
+            assert_eq!(from_bytes.bundles[0].file_name, from_file.bundles[0].file_name);
+            assert_eq!(from_bytes.bundles[0].source_map, from_file.bundles[0].source_map);
+            assert!(!out_dir.exists());
+        }
+
+        #[test]
+        fn from_dom() {
+            let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let root_ref = dom.root_ref();
+            dom.insert(root_ref, InstanceBuilder::new("Folder")
+                .with_name("Client")
+                .with_child(local_script("Main", "print(\"from dom\")")));
+
+            let config = BuildConfig::new(PathBuf::from("tooling.rbxm"), PathBuf::new())
+                .with_targets(vec![Target::Dev, Target::Rel]);
+            let result = build_from_dom(dom, &config).unwrap();
+
+            assert!(result.is_success());
+            assert_eq!(result.bundles[0].file_name, "tooling.debug.lua");
+            assert!(result.bundles.iter().all(|bundle| bundle.source.contains("from dom")));
+        }
+
+        #[test]
+        fn from_empty_dom() {
+            let dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+            let err = build_from_dom(dom, &BuildConfig::new(PathBuf::from("empty.rbxm"), PathBuf::new())).unwrap_err();
+            assert!(matches!(err, BundlerError::EmptyModel { .. }), "{err}");
+        }
+
+        #[test]
+        fn from_invalid_bytes() {
+            let config = BuildConfig::new(PathBuf::from("model"), PathBuf::new());
+            let err = build_from_bytes(b"not a model", &config).unwrap_err();
+            assert!(matches!(err, BundlerError::Decode { .. }), "{err}");
+        }
+    }
+
+    mod session {
+        use super::*;
+        use rbxts_bundler::bundler::{build_in_memory, Bundler, BundlerError};
+
+        fn session_model(file_name: &str, source: &str) -> PathBuf {
+            write_model(file_name, vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(\"main\")"))
+                    .with_child(local_script("Other", source)),
+            ])
+        }
+
+        #[test]
+        fn matches_fresh_builds() {
+            let config = BuildConfig::new(test_rbxm(), output_dir().join("session"))
+                .with_targets(vec![Target::Dev, Target::Rel]);
+            let fresh = build_in_memory(&config).unwrap();
+
+            let mut bundler = Bundler::new();
+            for _ in 0..2 {
+                let result = bundler.build_in_memory(&config).unwrap();
+                assert!(result.is_success());
+                for (bundle, expected) in result.bundles.iter().zip(&fresh.bundles) {
+                    assert_eq!(bundle.source, expected.source);
+                    assert_eq!(bundle.source_map, expected.source_map);
+                }
+            }
+        }
+
+        #[test]
+        fn writes_bundles() {
+            let out_dir = output_dir().join("session_written");
+            let config = BuildConfig::new(test_rbxm(), out_dir).with_targets(vec![Target::Dev]);
+            let mut bundler = Bundler::new();
+            bundler.build(&config).unwrap();
+            let result = bundler.build(&config).unwrap();
+
+            assert!(result.is_success());
+            let written = std::fs::read_to_string(&result.target_results[0].output_file).unwrap();
+            assert_eq!(written, build_in_memory(&config).unwrap().bundles[0].source);
+        }
+
+        #[test]
+        fn picks_up_changed_scripts() {
+            let config = BuildConfig::new(session_model("session_changed.rbxm", "print(\"before\")"), output_dir())
+                .with_targets(vec![Target::Dev, Target::Rel]);
+            let mut bundler = Bundler::new();
+            let before = bundler.build_in_memory(&config).unwrap();
+            assert!(before.bundles.iter().all(|bundle| bundle.source.contains("before")));
+
+            session_model("session_changed.rbxm", "print(\"after\")");
+            let after = bundler.build_in_memory(&config).unwrap();
+            for (bundle, expected) in after.bundles.iter().zip(&build_in_memory(&config).unwrap().bundles) {
+                assert!(!bundle.source.contains("before"));
+                assert_eq!(bundle.source, expected.source);
+            }
+        }
+
+        #[test]
+        fn from_bytes() {
+            let contents = std::fs::read(test_rbxm()).unwrap();
+            let config = BuildConfig::new(PathBuf::from("build.rbxm"), PathBuf::new());
+            let mut bundler = Bundler::new();
+            let first = bundler.build_from_bytes(&contents, &config).unwrap();
+            let second = bundler.build_from_bytes(&contents, &config).unwrap();
+
+            assert!(second.is_success());
+            assert_eq!(first.bundles[0].source, second.bundles[0].source);
+            let err = bundler.build_from_bytes(b"not a model", &config).unwrap_err();
+            assert!(matches!(err, BundlerError::Decode { .. }), "{err}");
+        }
+
+        #[test]
+        fn syntax_errors_name_the_module() {
+            let config = BuildConfig::new(session_model("session_syntax.rbxm", "local = 2"), output_dir())
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_check_requires(false);
+            let mut bundler = Bundler::new();
+
+            for _ in 0..2 {
+                let result = bundler.build_in_memory(&config).unwrap();
+                for bundle in &result.bundles {
+                    let message = bundle.error_message.as_deref().unwrap();
+                    assert!(message.contains("Darklua failed on Root.Other"), "{message}");
+                }
+            }
+        }
+
+        #[test]
+        fn clear() {
+            let config = BuildConfig::new(test_rbxm(), PathBuf::new()).with_targets(vec![Target::Rel]);
+            let mut bundler = Bundler::new();
+            let first = bundler.build_in_memory(&config).unwrap();
+            bundler.clear();
+            let second = bundler.build_in_memory(&config).unwrap();
+            assert_eq!(first.bundles[0].source, second.bundles[0].source);
+        }
+    }
+
+    mod errors {
+        use super::*;
+        use rbxts_bundler::bundler::BundlerError;
+
+        #[test]
+        fn nonexistent_input() {
+            let config = BuildConfig::new(PathBuf::from("nonexistent.rbxm"), output_dir())
+                .with_targets(vec![Target::Dev]);
+            let err = build(&config).unwrap_err();
+            assert!(matches!(&err, BundlerError::InputMissing { path } if path.ends_with("nonexistent.rbxm")), "{err}");
+        }
+
+        #[test]
+        fn empty_targets() {
+            let config = BuildConfig::new(test_rbxm(), output_dir())
+                .with_targets(vec![]);
+            assert!(matches!(build(&config), Err(BundlerError::InvalidConfig(_))));
+        }
+
+        #[test]
+        fn empty_model() {
+            let input = write_model("errors_empty.rbxm", vec![]);
+            let err = build(&BuildConfig::new(input, output_dir())).unwrap_err();
+            assert!(matches!(err, BundlerError::EmptyModel { .. }), "{err}");
+        }
+
+        #[test]
+        fn undecodable_model() {
+            let input = output_dir().join("errors_corrupt.rbxm");
+            std::fs::write(&input, b"<roblox!garbage").unwrap();
+            let err = build(&BuildConfig::new(input.clone(), output_dir())).unwrap_err();
+            assert!(matches!(&err, BundlerError::Decode { path, .. } if *path == input), "{err}");
+        }
+
+        #[test]
+        fn syntax_errors_name_the_module() {
+            let input = write_model("errors_syntax.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(\"ok\")"))
+                    .with_child(local_script("Broken", "local x = 1\nlocal y =\nlocal = 2")),
+            ]);
+            let config = BuildConfig::new(input, output_dir())
+                .with_targets(vec![Target::Dev, Target::Rel])
+                .with_check_requires(false);
+            let result = build(&config).unwrap();
+
+            for target_result in &result.target_results {
+                assert!(!target_result.success);
+                let message = target_result.error_message.as_deref().unwrap();
+                assert!(message.contains("Darklua failed on Root.Broken"), "{message}");
+            }
+        }
+
+        #[test]
+        fn converts_into_anyhow() {
+            fn run(config: &BuildConfig) -> anyhow::Result<()> {
+                build(config)?;
+                Ok(())
+            }
+
+            let config = BuildConfig::new(PathBuf::from("nonexistent.rbxm"), output_dir());
+            let err = run(&config).unwrap_err();
+            assert!(err.to_string().contains("Input file does not exist"));
+            assert!(matches!(err.downcast_ref(), Some(BundlerError::InputMissing { .. })));
+        }
+    }
+}
+
+mod cli {
+    use super::*;
+
+    mod info {
+        use super::*;
+
+        #[test]
+        fn help() {
+            let out = Command::new(cli_binary()).arg("--help").output().unwrap();
+            assert!(out.status.success());
+            assert!(String::from_utf8_lossy(&out.stdout).contains("rbxts-bundler"));
+        }
+
+        #[test]
+        fn version() {
+            let out = Command::new(cli_binary()).arg("--version").output().unwrap();
+            assert!(out.status.success());
+        }
+    }
+
+    mod build_cmd {
+        use super::*;
+
+        #[test]
+        fn single_target() {
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                    "-t", "dev",
+                ])
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+
+        #[test]
+        fn multiple_targets() {
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                    "-t", "dev", "-t", "rel", "-t", "dev-compat", "-t", "rel-compat",
+                ])
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+
+        #[test]
+        fn silent_mode() {
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                    "-t", "dev", "--silent",
+                ])
+                .output()
+                .unwrap();
+
+            assert!(out.status.success());
+            assert!(String::from_utf8_lossy(&out.stdout).lines().count() <= 2);
+        }
+    }
+
+    mod darklua_flags {
+        use super::*;
+
+        #[test]
+        fn extend_for_one_target() {
+            let extension = output_dir().join("darklua_cli_extend.json");
+            std::fs::write(&extension, r#"{ "rules": ["remove_spaces"] }"#).unwrap();
+
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                    "-t", "dev", "-t", "rel",
+                ])
+                .arg("--darklua-extend")
+                .arg(format!("dev={}", extension.display()))
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+
+        #[test]
+        fn invalid_config() {
+            let replacement = output_dir().join("darklua_cli_invalid.json");
+            std::fs::write(&replacement, r#"{ "rules": ["nope"] }"#).unwrap();
+
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                    "--darklua-config",
+                ])
+                .arg(&replacement)
+                .output()
+                .unwrap();
+
+            assert!(!out.status.success());
+            assert!(String::from_utf8_lossy(&out.stderr).contains("darklua"));
+        }
+
+        #[test]
+        fn unknown_target_prefix() {
+            let extension = output_dir().join("darklua_cli_typo.json");
+            std::fs::write(&extension, r#"{ "rules": ["remove_spaces"] }"#).unwrap();
+
+            let out = Command::new(cli_binary())
+                .args([
+                    "build", test_rbxm().to_str().unwrap(),
+                    "--out-dir", output_dir().to_str().unwrap(),
+                ])
+                .arg("--darklua-extend")
+                .arg(format!("rell={}", extension.display()))
+                .output()
+                .unwrap();
+
+            assert!(!out.status.success());
+            assert!(String::from_utf8_lossy(&out.stderr).contains("unknown target 'rell'"), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+    }
+
+    mod require_checks {
+        use super::*;
+
+        #[test]
+        fn reported_and_skippable() {
+            let input = write_model("requires_cli.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "require(script.Parent.Missing)")),
+            ]);
+            let run = |extra: &[&str]| {
+                Command::new(cli_binary())
+                    .arg("build")
+                    .arg(&input)
+                    .args(["--out-dir", output_dir().to_str().unwrap()])
+                    .args(extra)
+                    .output()
+                    .unwrap()
+            };
+
+            let checked = run(&[]);
+            let stderr = String::from_utf8_lossy(&checked.stderr);
+            assert!(checked.status.success(), "{stderr}");
+            assert!(stderr.contains("Root.Main: The require of script.Parent.Missing on line 1"), "{stderr}");
+            assert!(stderr.contains("[unresolved-require]"), "{stderr}");
+
+            assert!(!run(&["--deny-warnings"]).status.success());
+
+            let unchecked = run(&["--no-check-requires"]);
+            let stderr = String::from_utf8_lossy(&unchecked.stderr);
+            assert!(unchecked.status.success(), "{stderr}");
+            assert!(!stderr.contains("unresolved-require"), "{stderr}");
+        }
+    }
+
+    mod report_flag {
+        use super::*;
+
+        #[test]
+        fn prints_table_and_writes_json() {
+            let out_dir = output_dir().join("report_cli");
+            let output = Command::new(cli_binary())
+                .arg("build")
+                .arg(test_rbxm())
+                .args(["--out-dir", out_dir.to_str().unwrap(), "--report"])
+                .output()
+                .unwrap();
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(output.status.success(), "{stderr}");
+            assert!(stderr.contains("Size of build.debug.lua"), "{stderr}");
+            assert!(stderr.contains("Runtime"), "{stderr}");
+            assert!(out_dir.join("build.debug.lua.size.json").exists());
+        }
+    }
+
+    mod analyze_flag {
+        use super::*;
+
+        #[test]
+        fn writes_page() {
+            let out_dir = output_dir().join("analyze_cli");
+            let output = Command::new(cli_binary())
+                .arg("build")
+                .arg(test_rbxm())
+                .args(["--out-dir", out_dir.to_str().unwrap(), "--analyze"])
+                .output()
+                .unwrap();
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(output.status.success(), "{stderr}");
+            assert!(stderr.contains("Analyzer:"), "{stderr}");
+            assert!(out_dir.join("build.debug.lua.analyzer.html").exists());
+        }
+    }
+
+    mod deny_warnings {
+        use super::*;
+
+        #[test]
+        fn fails_with_warnings() {
+            let input = write_model("deny_warnings_cli.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(1)"))
+                    .with_child(local_script("Main", "print(2)")),
+            ]);
+            let run = |deny: bool| {
+                let mut command = Command::new(cli_binary());
+                command.arg("build").arg(&input).args(["--out-dir", output_dir().to_str().unwrap()]);
+                if deny {
+                    command.arg("--deny-warnings");
+                }
+                command.output().unwrap()
+            };
+
+            let allowed = run(false);
+            let stderr = String::from_utf8_lossy(&allowed.stderr);
+            assert!(allowed.status.success(), "{stderr}");
+            assert!(stderr.contains("Root.Main: 2 siblings are named 'Main'"), "{stderr}");
+            assert!(stderr.contains("[duplicate-name]"), "{stderr}");
+
+            let denied = run(true);
+            let stderr = String::from_utf8_lossy(&denied.stderr);
+            assert!(!denied.status.success(), "{stderr}");
+            assert!(stderr.contains("Found 1 error in the input"), "{stderr}");
+        }
+    }
+
+    mod output_format {
+        use super::*;
+
+        #[test]
+        fn json_result_on_stdout() {
+            let out_dir = output_dir().join("json_cli");
+            let output = Command::new(cli_binary())
+                .arg("build")
+                .arg(test_rbxm())
+                .args(["--out-dir", out_dir.to_str().unwrap(), "-t", "dev", "-t", "rel", "--output-format", "json"])
+                .output()
+                .unwrap();
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(output.status.success(), "{stderr}");
+            assert!(stderr.is_empty(), "{stderr}");
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            assert_eq!(json["success"], true);
+            assert_eq!(json["targets"].as_array().unwrap().len(), 2);
+            assert!(json["targets"][1]["output_file"].as_str().unwrap().ends_with("build.release.lua"));
+        }
+
+        #[test]
+        fn json_errors() {
+            let output = Command::new(cli_binary())
+                .args(["build", "nonexistent.rbxm", "--out-dir", "dist", "--output-format", "json"])
+                .output()
+                .unwrap();
+
+            assert!(!output.status.success());
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            assert_eq!(json["success"], false);
+            assert!(json["error"].as_str().unwrap().contains("does not exist"));
+        }
+    }
+
+    mod tree_shaking {
+        use super::*;
+
+        #[test]
+        fn reports_dropped_modules() {
+            let input = write_model("shake_cli.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print('main')"))
+                    .with_child(
+                        InstanceBuilder::new("ModuleScript")
+                            .with_name("Unused")
+                            .with_property("Source", "return {}"),
+                    ),
+            ]);
+
+            let output = Command::new(cli_binary())
+                .arg("build")
+                .arg(&input)
+                .args(["--out-dir", output_dir().to_str().unwrap(), "--tree-shake"])
+                .output()
+                .unwrap();
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(output.status.success(), "{stderr}");
+            assert!(stderr.contains("Tree shaking dropped 1 module (9 bytes of source), kept 0"), "{stderr}");
+            assert!(stderr.contains("Root.Unused"), "{stderr}");
+        }
+    }
+
+    mod project_file {
+        use super::*;
+
+        fn project_dir(name: &str) -> PathBuf {
+            let dir = output_dir().join(name);
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        fn discovered_in_working_directory() {
+            let dir = project_dir("cli_project");
+            std::fs::copy(test_rbxm(), dir.join("model.rbxm")).unwrap();
+            std::fs::write(
+                dir.join("rbxts-bundler.toml"),
+                "input = \"model.rbxm\"\nout-dir = \"dist\"\ntargets = [\"rel\"]\n",
+            )
+            .unwrap();
+
+            let out = Command::new(cli_binary())
+                .args(["build", "-t", "dev", "-D", "DEBUG=true"])
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert!(dir.join("dist/model.debug.lua").exists());
+            assert!(!dir.join("dist/model.release.lua").exists());
+        }
+
+        #[test]
+        fn explicit_config_path() {
+            let dir = project_dir("cli_project_explicit");
+            std::fs::copy(test_rbxm(), dir.join("model.rbxm")).unwrap();
+            let config = dir.join("bundle.toml");
+            std::fs::write(&config, "input = \"model.rbxm\"\nout-dir = \"dist\"\noutput-name = \"{target}.lua\"\n").unwrap();
+
+            let out = Command::new(cli_binary())
+                .args(["build", "--config"])
+                .arg(&config)
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert!(dir.join("dist/dev.lua").exists());
+        }
+
+        #[test]
+        fn custom_target() {
+            let dir = project_dir("cli_project_custom");
+            std::fs::copy(test_rbxm(), dir.join("model.rbxm")).unwrap();
+            std::fs::write(
+                dir.join("rbxts-bundler.toml"),
+                "input = \"model.rbxm\"\nout-dir = \"dist\"\n[custom-targets.rel-lua51]\nbase = \"rel-compat\"\nsuffix = \"release.lua51\"\n",
+            )
+            .unwrap();
+
+            let out = Command::new(cli_binary())
+                .args(["build", "-t", "rel-lua51", "-t", "dev"])
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert!(dir.join("dist/model.release.lua51.lua").exists());
+            assert!(dir.join("dist/model.debug.lua").exists());
+        }
+
+        #[test]
+        fn flags_override_project_settings() {
+            let dir = project_dir("cli_project_override");
+            let model = write_model("cli_project_override.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(STAGE, BUILD)")),
+            ]);
+            std::fs::copy(model, dir.join("model.rbxm")).unwrap();
+            std::fs::write(
+                dir.join("rbxts-bundler.toml"),
+                "input = \"model.rbxm\"\nout-dir = \"dist\"\noutput-name = \"{target}.lua\"\n[defines]\nSTAGE = \"project\"\nBUILD = 7\n",
+            )
+            .unwrap();
+
+            let out = Command::new(cli_binary())
+                .args(["build", "--output-name", "bundle.lua", "-D", "STAGE=cli"])
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert!(!dir.join("dist/dev.lua").exists());
+            let content = std::fs::read_to_string(dir.join("dist/bundle.lua")).unwrap();
+            assert!(content.contains("print('cli', 7)"), "{content}");
+        }
+
+        #[test]
+        fn missing_input() {
+            let dir = project_dir("cli_project_missing_input");
+
+            let out = Command::new(cli_binary())
+                .args(["build", "--out-dir", "dist"])
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+
+            assert!(!out.status.success());
+            assert!(String::from_utf8_lossy(&out.stderr).contains("No input"));
+        }
+    }
+
+    mod init_cmd {
+        use super::*;
+
+        #[test]
+        fn detects_rojo_project() {
+            let dir = output_dir().join("cli_init");
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("default.project.json"), "{}").unwrap();
+
+            let out = Command::new(cli_binary()).arg("init").arg(&dir).output().unwrap();
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+
+            let contents = std::fs::read_to_string(dir.join("rbxts-bundler.toml")).unwrap();
+            assert!(contents.contains("input = \"default.project.json\""));
+            toml::from_str::<toml::Table>(&contents).unwrap();
+        }
+
+        #[test]
+        fn refuses_to_overwrite() {
+            let dir = output_dir().join("cli_init_existing");
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("rbxts-bundler.toml"), "# keep\n").unwrap();
+
+            let out = Command::new(cli_binary()).arg("init").arg(&dir).output().unwrap();
+            assert!(!out.status.success());
+            assert_eq!(std::fs::read_to_string(dir.join("rbxts-bundler.toml")).unwrap(), "# keep\n");
+
+            let out = Command::new(cli_binary()).args(["init", "--force"]).arg(&dir).output().unwrap();
+            assert!(out.status.success());
+        }
+    }
+
+    mod watch_cmd {
+        use super::*;
+        use std::process::Stdio;
+        use std::time::{Duration, Instant};
+
+        /// Waits until the file exists and contains `needle`.
+        fn wait_for(path: &std::path::Path, needle: &str) -> bool {
+            let deadline = Instant::now() + Duration::from_secs(20);
+            while Instant::now() < deadline {
+                if std::fs::read_to_string(path).is_ok_and(|content| content.contains(needle)) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            false
+        }
+
+        #[test]
+        fn rebuilds_on_change() {
+            let dir = output_dir().join("cli_watch");
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let model = |source: &str| {
+                let path = write_model("watch.rbxm", vec![
+                    InstanceBuilder::new("Folder")
+                        .with_name("Root")
+                        .with_child(local_script("Main", source)),
+                ]);
+                std::fs::rename(&path, dir.join("watch.rbxm")).unwrap();
+            };
+            model("print(\"first\")");
+
+            let mut child = Command::new(cli_binary())
+                .args(["build", "watch.rbxm", "--out-dir", "dist", "--watch", "-q"])
+                .current_dir(&dir)
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+
+            let output = dir.join("dist/watch.debug.lua");
+            let first = wait_for(&output, "first");
+            if first {
+                // Make sure the rewrite gets a newer modification time
+                std::thread::sleep(Duration::from_millis(50));
+                model("print(\"second\")");
+            }
+            let second = first && wait_for(&output, "second");
+
+            child.kill().unwrap();
+            child.wait().unwrap();
+            assert!(first, "initial build was not written");
+            assert!(second, "change was not rebuilt");
+        }
+    }
+
+    mod serve_cmd {
+        use super::*;
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::process::Stdio;
+        use std::time::{Duration, Instant};
+
+        /// Fetches a path, returning the raw response once the server answers with `200 OK`.
+        fn fetch(port: u16, path: &str) -> Option<String> {
+            let deadline = Instant::now() + Duration::from_secs(20);
+            while Instant::now() < deadline {
+                if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+                    let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+                    let mut response = String::new();
+                    if stream.write_all(request.as_bytes()).is_ok()
+                        && stream.read_to_string(&mut response).is_ok()
+                        && response.starts_with("HTTP/1.1 200")
+                    {
+                        return Some(response);
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            None
+        }
+
+        #[test]
+        fn serves_built_targets() {
+            let input = write_model("serve.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "print(\"served\")")),
+            ]);
+            let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+
+            let mut child = Command::new(cli_binary())
+                .arg("serve")
+                .arg(&input)
+                .args(["--port", &port.to_string(), "-q"])
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+
+            let bundle = fetch(port, "/dev.lua");
+            let loader = fetch(port, "/dev/loader.lua");
+
+            child.kill().unwrap();
+            child.wait().unwrap();
+            let bundle = bundle.expect("bundle was not served");
+            assert!(bundle.contains("served"), "{bundle}");
+            assert!(loader.unwrap().contains(&format!("http://127.0.0.1:{port}/dev.lua")));
+        }
+    }
+
+    mod symbolicate_cmd {
+        use super::*;
+
+        #[test]
+        fn rewrites_trace_file() {
+            let input = write_model("symbolicate.rbxm", vec![
+                InstanceBuilder::new("Folder")
+                    .with_name("Root")
+                    .with_child(local_script("Main", "local value = 1\nerror(\"boom\")")),
+            ]);
+            let result = build(&BuildConfig::new(input, output_dir())).unwrap();
+
+            let trace = output_dir().join("symbolicate.trace.txt");
+            std::fs::write(&trace, "[string \"Root.Main\"]:3: boom\nStack Begin\n").unwrap();
+
+            let out = Command::new(cli_binary())
+                .arg("symbolicate")
+                .arg(&result.target_results[0].source_map_file)
+                .arg(&trace)
+                .output()
+                .unwrap();
+
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+            assert_eq!(String::from_utf8_lossy(&out.stdout), "Root.Main:2: boom\nStack Begin\n");
+        }
+
+        #[test]
+        fn missing_map() {
+            let out = Command::new(cli_binary())
+                .args(["symbolicate", "nonexistent.lua.map"])
+                .output()
+                .unwrap();
+
+            assert!(!out.status.success());
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            assert!(stderr.contains("Failed to access nonexistent.lua.map: "), "{stderr}");
+            assert_eq!(stderr.matches("(os error").count(), 1, "{stderr}");
         }
     }
 
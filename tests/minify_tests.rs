@@ -2,6 +2,7 @@
 
 use rbxts_bundler::assets::DARKLUA_REL;
 use rbxts_bundler::bundler::minify::minify;
+use rbxts_bundler::bundler::BundlerError;
 
 mod success {
     use super::*;
@@ -79,4 +80,10 @@ mod errors {
         let result = minify("local x = 1", "{ invalid json }");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn syntax_error() {
+        let err = minify("local x = 1\nlocal = 2", DARKLUA_REL).unwrap_err();
+        assert!(matches!(&err, BundlerError::Minify { module: None, message } if message.contains("line 2")), "{err}");
+    }
 }
@@ -0,0 +1,190 @@
+//! Tests for `rbxts-bundler.toml` project files.
+
+use std::path::Path;
+
+use rbxts_bundler::bundler::{DarkluaOverride, DefineValue, ProjectConfig, StartPolicy, Target, TreeShaking};
+
+fn parse(contents: &str) -> ProjectConfig {
+    toml::from_str(contents).unwrap()
+}
+
+mod parsing {
+    use super::*;
+
+    #[test]
+    fn empty_file() {
+        let project = parse("");
+        assert!(project.input.is_none());
+        assert!(project.targets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn targets_and_start_policy() {
+        let project = parse("targets = [\"dev-inline\", \"rel-compat\"]\nstart = \"server\"\n");
+        assert_eq!(project.targets().unwrap(), vec![Target::DevInline, Target::RelCompat]);
+        assert_eq!(project.start_policy().unwrap(), Some(StartPolicy::Server));
+    }
+
+    #[test]
+    fn unknown_target() {
+        let err = parse("targets = [\"debug\"]").targets().unwrap_err();
+        assert!(err.to_string().contains("dev-compat"), "{err}");
+    }
+
+    #[test]
+    fn require_checks_can_be_disabled() {
+        let paths = "input = \"model.rbxm\"\nout-dir = \"dist\"\n";
+        assert!(parse(paths).to_build_config().unwrap().check_requires);
+        let disabled = parse(&format!("{paths}check-requires = false\n"));
+        assert!(!disabled.to_build_config().unwrap().check_requires);
+    }
+
+    #[test]
+    fn tree_shaking_mode() {
+        let paths = "input = \"model.rbxm\"\nout-dir = \"dist\"\n";
+        let config = parse(&format!("{paths}tree-shake = \"keep-instances\"\n")).to_build_config().unwrap();
+        assert_eq!(config.tree_shaking, TreeShaking::KeepInstances);
+
+        let err = parse(&format!("{paths}tree-shake = \"all\"\n")).to_build_config().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid `tree-shake`"), "{err}");
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert!(toml::from_str::<ProjectConfig>("inputs = \"model.rbxm\"").is_err());
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(ProjectConfig::load(Path::new("nonexistent/rbxts-bundler.toml")).is_err());
+    }
+}
+
+mod defines {
+    use super::*;
+
+    #[test]
+    fn scalar_values() {
+        let project = parse("[defines]\nA = true\nB = 2\nC = 0.5\nD = \"text\"\n");
+        let defines = project.defines().unwrap();
+
+        assert_eq!(defines["A"], DefineValue::Bool(true));
+        assert_eq!(defines["B"], DefineValue::Number(2.0));
+        assert_eq!(defines["C"], DefineValue::Number(0.5));
+        assert_eq!(defines["D"], DefineValue::String("text".to_string()));
+    }
+
+    #[test]
+    fn tables_are_rejected() {
+        let err = parse("[defines]\nA = { b = 1 }\n").defines().unwrap_err();
+        assert!(err.to_string().contains("'A'"), "{err}");
+    }
+
+    #[test]
+    fn inexact_integers_are_rejected() {
+        assert_eq!(parse("[defines]\nA = 9007199254740992\n").defines().unwrap()["A"], DefineValue::Number(9007199254740992.0));
+        let err = parse("[defines]\nA = 9007199254740993\n").defines().unwrap_err();
+        assert!(err.to_string().contains("'A' is too large"), "{err}");
+    }
+}
+
+mod custom_targets {
+    use super::*;
+    use rbxts_bundler::bundler::Mode;
+
+    #[test]
+    fn derived_from_base() {
+        let project = parse(
+            "[custom-targets.rel-lua51]\nbase = \"rel-compat\"\nsuffix = \"release.lua51\"\n[custom-targets.rel-lua51.defines]\nLUA51 = true\n",
+        );
+        let target = project.target("rel-lua51").unwrap();
+
+        assert_eq!(target.to_string(), "rel-lua51");
+        assert_eq!(target.mode(), Mode::Production);
+        assert!(target.compat());
+        assert_eq!(target.file_suffix(), "release.lua51");
+        let Target::Custom(custom) = target else {
+            panic!("expected a custom target");
+        };
+        assert_eq!(custom.defines["LUA51"], DefineValue::Bool(true));
+    }
+
+    #[test]
+    fn overrides_base_settings() {
+        let project = parse("[custom-targets.staging]\nbase = \"dev\"\nmode = \"production\"\nloadstring = false\n");
+        let target = project.target("staging").unwrap();
+
+        assert_eq!(target.mode(), Mode::Production);
+        assert!(!target.compat());
+        assert!(!target.uses_loadstring());
+        assert_eq!(target.file_suffix(), "staging");
+    }
+
+    #[test]
+    fn listed_in_unknown_target_errors() {
+        let project = parse("[custom-targets.staging]\nbase = \"rel\"\n");
+        let err = project.target("stage").unwrap_err();
+        assert!(err.to_string().contains("staging"), "{err}");
+    }
+
+    #[test]
+    fn invalid_base() {
+        let project = parse("[custom-targets.staging]\nbase = \"release\"\n");
+        let err = project.target("staging").unwrap_err();
+        assert!(format!("{err:#}").contains("`base`"), "{err:#}");
+    }
+}
+
+mod darklua {
+    use super::*;
+
+    #[test]
+    fn all_targets_come_first() {
+        let dir = std::env::temp_dir().join("rbxts_bundler_project_darklua");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("all.json"), "{}").unwrap();
+        std::fs::write(dir.join("rel.json"), "{ \"rules\": [] }").unwrap();
+        std::fs::write(
+            dir.join("rbxts-bundler.toml"),
+            "[darklua.rel]\nextend = \"rel.json\"\n[darklua.all]\nconfig = \"all.json\"\n",
+        )
+        .unwrap();
+
+        let project = ProjectConfig::load(&dir.join("rbxts-bundler.toml")).unwrap();
+        let overrides = project.darklua_overrides(&[Target::Dev, Target::Rel]).unwrap();
+
+        assert_eq!(overrides, vec![
+            (Target::Dev, DarkluaOverride::Replace("{}".to_string())),
+            (Target::Rel, DarkluaOverride::Replace("{}".to_string())),
+            (Target::Rel, DarkluaOverride::Extend("{ \"rules\": [] }".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn custom_target_files_are_read_on_load() {
+        let dir = std::env::temp_dir().join("rbxts_bundler_project_custom_darklua");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("staging.json"), "{}").unwrap();
+        std::fs::write(
+            dir.join("rbxts-bundler.toml"),
+            "[custom-targets.staging]\nbase = \"rel\"\n[custom-targets.staging.darklua]\nconfig = \"staging.json\"\n",
+        )
+        .unwrap();
+
+        let project = ProjectConfig::load(&dir.join("rbxts-bundler.toml")).unwrap();
+        std::fs::remove_file(dir.join("staging.json")).unwrap();
+        let Target::Custom(custom) = project.target("staging").unwrap() else {
+            panic!("expected a custom target");
+        };
+
+        assert_eq!(custom.darklua_overrides, vec![DarkluaOverride::Replace("{}".to_string())]);
+    }
+
+    #[test]
+    fn unknown_target_table() {
+        let err = parse("[darklua.release]\nextend = \"rel.json\"\n")
+            .darklua_overrides(&[Target::Rel])
+            .unwrap_err();
+        assert!(err.to_string().contains("[darklua.release]"), "{err}");
+    }
+}
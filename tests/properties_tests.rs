@@ -0,0 +1,136 @@
+//! Tests for converting property values into Luau constructors.
+
+use rbx_dom_weak::types::{
+    BrickColor, CFrame, Color3, Color3uint8, ColorSequence, ColorSequenceKeypoint, Enum, Faces,
+    Matrix3, NumberSequence, NumberSequenceKeypoint, Ref, UDim, UDim2, Variant, Vector3,
+};
+use rbxts_bundler::bundler::properties::append_variant;
+
+fn luau(value: impl Into<Variant>) -> String {
+    luau_enum(value, None)
+}
+
+fn luau_enum(value: impl Into<Variant>, enum_name: Option<&str>) -> String {
+    let mut out = String::new();
+    assert!(append_variant(&value.into(), enum_name, &mut out), "unsupported value");
+    out
+}
+
+mod primitives {
+    use super::*;
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(luau("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn booleans() {
+        assert_eq!(luau(true), "true");
+        assert_eq!(luau(false), "false");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(luau(42i32), "42");
+        assert_eq!(luau(0.5f32), "0.5");
+        assert_eq!(luau(-3.25f64), "-3.25");
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        assert_eq!(luau(f64::INFINITY), "math.huge");
+        assert_eq!(luau(f32::NEG_INFINITY), "-math.huge");
+        assert_eq!(luau(f64::NAN), "0/0");
+    }
+}
+
+mod datatypes {
+    use super::*;
+
+    #[test]
+    fn vector3() {
+        assert_eq!(luau(Vector3::new(1.0, 2.5, -3.0)), "Vector3.new(1, 2.5, -3)");
+    }
+
+    #[test]
+    fn cframe() {
+        let cf = CFrame::new(Vector3::new(1.0, 2.0, 3.0), Matrix3::identity());
+        assert_eq!(luau(cf), "CFrame.new(1, 2, 3, 1, 0, 0, 0, 1, 0, 0, 0, 1)");
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(luau(Color3::new(1.0, 0.5, 0.0)), "Color3.new(1, 0.5, 0)");
+        assert_eq!(luau(Color3uint8::new(255, 128, 0)), "Color3.fromRGB(255, 128, 0)");
+    }
+
+    #[test]
+    fn brick_color() {
+        assert_eq!(luau(BrickColor::from_number(194).unwrap()), "BrickColor.new(194)");
+    }
+
+    #[test]
+    fn udim2() {
+        let value = UDim2::new(UDim::new(0.5, 10), UDim::new(1.0, -4));
+        assert_eq!(luau(value), "UDim2.new(0.5, 10, 1, -4)");
+    }
+
+    #[test]
+    fn sequences() {
+        let numbers = NumberSequence {
+            keypoints: vec![
+                NumberSequenceKeypoint::new(0.0, 1.0, 0.0),
+                NumberSequenceKeypoint::new(1.0, 0.0, 0.0),
+            ],
+        };
+        assert_eq!(
+            luau(numbers),
+            "NumberSequence.new({ NumberSequenceKeypoint.new(0, 1, 0), NumberSequenceKeypoint.new(1, 0, 0) })"
+        );
+
+        let colors = ColorSequence {
+            keypoints: vec![ColorSequenceKeypoint::new(0.0, Color3::new(1.0, 0.0, 0.0))],
+        };
+        assert_eq!(
+            luau(colors),
+            "ColorSequence.new({ ColorSequenceKeypoint.new(0, Color3.new(1, 0, 0)) })"
+        );
+    }
+
+    #[test]
+    fn faces() {
+        let faces = Faces::from_bits(Faces::TOP.bits() | Faces::FRONT.bits()).unwrap();
+        assert_eq!(luau(faces), "Faces.new(Enum.NormalId.Top, Enum.NormalId.Front)");
+    }
+}
+
+mod enums {
+    use super::*;
+
+    #[test]
+    fn resolves_item_name() {
+        assert_eq!(
+            luau_enum(Enum::from_u32(256), Some("Material")),
+            "Enum.Material.Plastic"
+        );
+    }
+
+    #[test]
+    fn requires_enum_name() {
+        let mut out = String::new();
+        assert!(!append_variant(&Variant::Enum(Enum::from_u32(0)), None, &mut out));
+        assert!(out.is_empty());
+    }
+}
+
+mod unsupported {
+    use super::*;
+
+    #[test]
+    fn refs_are_not_values() {
+        let mut out = String::from("prefix");
+        assert!(!append_variant(&Variant::Ref(Ref::new()), None, &mut out));
+        assert_eq!(out, "prefix");
+    }
+}
@@ -0,0 +1,295 @@
+//! Tests for the static analysis of require calls.
+
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbxts_bundler::bundler::requires::{RequireTarget, ScriptNode};
+use rbxts_bundler::bundler::{RequireGraph, RequireIssue};
+
+fn script(class: &str, name: &str, source: &str) -> InstanceBuilder {
+    InstanceBuilder::new(class)
+        .with_name(name)
+        .with_property("Source", source)
+}
+
+fn module(name: &str, source: &str) -> InstanceBuilder {
+    script("ModuleScript", name, source)
+}
+
+fn folder(name: &str) -> InstanceBuilder {
+    InstanceBuilder::new("Folder").with_name(name)
+}
+
+fn analyze(roots: Vec<InstanceBuilder>) -> (WeakDom, RequireGraph) {
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root = dom.root_ref();
+    for builder in roots {
+        dom.insert(root, builder);
+    }
+    let graph = RequireGraph::analyze(&dom).unwrap();
+    (dom, graph)
+}
+
+fn node<'a>(graph: &'a RequireGraph, path: &str) -> &'a ScriptNode {
+    graph.scripts().iter().find(|script| script.path == path).unwrap()
+}
+
+/// Returns the paths of the instances a script requires, `None` for dynamic requires.
+fn required_paths(dom: &WeakDom, graph: &RequireGraph, path: &str) -> Vec<Option<String>> {
+    node(graph, path)
+        .requires
+        .iter()
+        .map(|require| match &require.target {
+            RequireTarget::Instance(target) => {
+                let found = graph.script(*target).map(|script| script.path.clone());
+                Some(found.unwrap_or_else(|| dom.get_by_ref(*target).unwrap().name.clone()))
+            }
+            RequireTarget::Missing { path, .. } => Some(format!("missing {path}")),
+            RequireTarget::External => Some("external".to_string()),
+            RequireTarget::Dynamic => None,
+        })
+        .collect()
+}
+
+mod resolution {
+    use super::*;
+
+    #[test]
+    fn paths_from_script() {
+        let source = r#"
+local a = require(script.Parent.A)
+local b = require(script.Parent:FindFirstChild("B"))
+local c = require(script.Parent:WaitForChild("Nested", 5)["C"])
+local d = require((script:FindFirstAncestor("Root").A))
+"#;
+        let (dom, graph) = analyze(vec![folder("Root")
+            .with_child(script("LocalScript", "Main", source))
+            .with_child(module("A", "return 1"))
+            .with_child(module("B", "return 2"))
+            .with_child(folder("Nested").with_child(module("C", "return 3")))]);
+
+        assert_eq!(
+            required_paths(&dom, &graph, "Root.Main"),
+            [
+                Some("Root.A".to_string()),
+                Some("Root.B".to_string()),
+                Some("Root.Nested.C".to_string()),
+                Some("Root.A".to_string()),
+            ]
+        );
+        assert!(graph.issues().is_empty(), "{:?}", graph.issues());
+    }
+
+    #[test]
+    fn roblox_ts_imports() {
+        let source = r#"
+local TS = require(script.Parent.include.RuntimeLib)
+local Util = TS.import(script, script.Parent, "shared", "Util").Util
+local Roact = TS.import(script, TS.getModule(script, "@rbxts", "roact").src)
+"#;
+        let (dom, graph) = analyze(vec![folder("Project")
+            .with_child(script("LocalScript", "main", source))
+            .with_child(folder("shared").with_child(module("Util", "return {}")))
+            .with_child(
+                folder("include")
+                    .with_child(module("RuntimeLib", "return {}"))
+                    .with_child(folder("node_modules").with_child(
+                        folder("@rbxts").with_child(folder("roact").with_child(module("src", "return {}"))),
+                    )),
+            )]);
+
+        assert_eq!(
+            required_paths(&dom, &graph, "Project.main"),
+            [
+                Some("Project.include.RuntimeLib".to_string()),
+                Some("Project.shared.Util".to_string()),
+                Some("Project.include.node_modules.@rbxts.roact.src".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_static_requires_are_left_to_the_runtime() {
+        let source = r#"
+local Players = require(game:GetService("ReplicatedStorage").Shared)
+local Asset = require(123456)
+local Shared = require(workspace.Shared)
+local target = script.Parent
+target = target.Parent
+local Other = require(target)
+local Deep = require(script.Parent:FindFirstChild("Deep", true))
+"#;
+        let (dom, graph) = analyze(vec![folder("Root").with_child(script("LocalScript", "Main", source))]);
+
+        let external = Some("external".to_string());
+        assert_eq!(required_paths(&dom, &graph, "Root.Main"), [external.clone(), external.clone(), external, None, None]);
+        assert!(graph.issues().is_empty(), "{:?}", graph.issues());
+    }
+
+    #[test]
+    fn local_aliases() {
+        let source = r#"
+local Shared = script.Parent.Parent.Shared
+local Util = Shared:WaitForChild("Util")
+local ReplicatedStorage = game:GetService("ReplicatedStorage")
+local Foo = require(Shared.Foo)
+local Helper = require(Util.Helper)
+local Remote = require(ReplicatedStorage.Remote)
+local function load(Module)
+	return require(Module.Foo)
+end
+do
+	local Scoped = script.Parent
+end
+local Global = require(Scoped)
+"#;
+        let (dom, graph) = analyze(vec![folder("Game")
+            .with_child(folder("Client").with_child(script("LocalScript", "Main", source)))
+            .with_child(
+                folder("Shared")
+                    .with_child(module("Foo", "return 1"))
+                    .with_child(folder("Util").with_child(module("Helper", "return 2"))),
+            )]);
+
+        assert_eq!(
+            required_paths(&dom, &graph, "Game.Client.Main"),
+            [
+                Some("Game.Shared.Foo".to_string()),
+                Some("Game.Shared.Util.Helper".to_string()),
+                Some("external".to_string()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_and_laziness() {
+        let source = "local A = require(script.A)\n\nlocal function load()\n\treturn require(script.A)\nend\n";
+        let (_, graph) = analyze(vec![module("Main", source).with_child(module("A", "return 1"))]);
+
+        let requires = &node(&graph, "Main").requires;
+        assert_eq!((requires[0].line, requires[0].lazy), (1, false));
+        assert_eq!((requires[1].line, requires[1].lazy), (4, true));
+    }
+
+    #[test]
+    fn unparsable_scripts_have_no_requires() {
+        let (_, graph) = analyze(vec![module("Broken", "local = require(script.Missing)")]);
+
+        let broken = node(&graph, "Broken");
+        assert!(broken.parse_error.is_some());
+        assert!(broken.requires.is_empty());
+        assert!(graph.issues().is_empty());
+    }
+}
+
+mod issues {
+    use super::*;
+
+    #[test]
+    fn missing_child() {
+        let (_, graph) = analyze(vec![folder("Root").with_child(script(
+            "LocalScript",
+            "Main",
+            "print('hi')\nlocal Config = require(script.Parent.Config)",
+        ))]);
+
+        assert_eq!(
+            graph.issues(),
+            [RequireIssue::Unresolved {
+                script: "Root.Main".to_string(),
+                line: 2,
+                path: "script.Parent.Config".to_string(),
+                reason: "Root has no child named 'Config'".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parent_of_top_level_instance() {
+        let (_, graph) = analyze(vec![script("LocalScript", "Main", "require(script.Parent.Parent.Shared)")]);
+
+        let [RequireIssue::Unresolved { reason, .. }] = graph.issues() else {
+            panic!("{:?}", graph.issues());
+        };
+        assert_eq!(reason, "Main is a top-level instance with no parent");
+    }
+
+    #[test]
+    fn missing_package() {
+        let source = r#"local t = TS.import(script, TS.getModule(script, "@rbxts", "t").lib.ts)"#;
+        let (_, graph) = analyze(vec![folder("Project").with_child(script("LocalScript", "main", source))]);
+
+        let [RequireIssue::Unresolved { path, reason, .. }] = graph.issues() else {
+            panic!("{:?}", graph.issues());
+        };
+        assert_eq!(path, r#"TS.getModule(script, "@rbxts", "t").lib.ts"#);
+        assert_eq!(reason, "package @rbxts/t is not in any node_modules");
+    }
+
+    #[test]
+    fn not_a_module() {
+        let (_, graph) = analyze(vec![folder("Root")
+            .with_child(script("LocalScript", "Main", "require(script.Parent.Config)\nrequire(script.Parent.Other)"))
+            .with_child(folder("Config"))
+            .with_child(script("Script", "Other", ""))]);
+
+        assert_eq!(
+            graph.issues(),
+            [
+                RequireIssue::NotAModule {
+                    script: "Root.Main".to_string(),
+                    line: 1,
+                    target: "Root.Config".to_string(),
+                    class: "Folder".to_string(),
+                },
+                RequireIssue::NotAModule {
+                    script: "Root.Main".to_string(),
+                    line: 2,
+                    target: "Root.Other".to_string(),
+                    class: "Script".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn load_time_cycle() {
+        let (_, graph) = analyze(vec![folder("Root")
+            .with_child(module("A", "local B = require(script.Parent.B)\nreturn {}"))
+            .with_child(module("B", "local C = require(script.Parent.C)\nreturn {}"))
+            .with_child(module("C", "local A = require(script.Parent.A)\nreturn {}"))]);
+
+        assert_eq!(
+            graph.issues(),
+            [RequireIssue::Cycle {
+                chain: ["Root.A", "Root.B", "Root.C", "Root.A"].map(String::from).to_vec(),
+            }]
+        );
+        assert_eq!(
+            graph.issues()[0].to_string(),
+            "circular require when loading: Root.A → Root.B → Root.C → Root.A"
+        );
+    }
+
+    #[test]
+    fn lazy_cycles_are_allowed() {
+        let (_, graph) = analyze(vec![folder("Root")
+            .with_child(module("A", "local B = require(script.Parent.B)\nreturn {}"))
+            .with_child(module(
+                "B",
+                "return { get = function() return require(script.Parent.A) end }",
+            ))]);
+
+        assert!(graph.issues().is_empty(), "{:?}", graph.issues());
+    }
+
+    #[test]
+    fn self_require() {
+        let (_, graph) = analyze(vec![module("Loop", "return require(script)")]);
+
+        assert_eq!(
+            graph.issues(),
+            [RequireIssue::Cycle { chain: vec!["Loop".to_string(), "Loop".to_string()] }]
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Tests for serving bundles over HTTP.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rbxts_bundler::bundler::{CustomTarget, MemoryBuildResult, SourceMap, Target, TargetBundle};
+use rbxts_bundler::serve::BundleServer;
+
+/// A response as status code, headers (lowercase names) and body.
+struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Reply {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(field, _)| field == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Sends a request over a fresh connection and reads the whole response.
+fn request(server: &BundleServer, method: &str, path: &str, headers: &[(&str, &str)]) -> Reply {
+    let address = server.url().trim_start_matches("http://");
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(60))).unwrap();
+
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: {address}\r\nConnection: close\r\n");
+    for (field, value) in headers {
+        head.push_str(&format!("{field}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    let mut lines = head.lines();
+    let status = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+    let headers = lines
+        .filter_map(|line| line.split_once(": "))
+        .map(|(field, value)| (field.to_ascii_lowercase(), value.to_string()))
+        .collect();
+
+    Reply { status, headers, body: body.to_string() }
+}
+
+fn get(server: &BundleServer, path: &str) -> Reply {
+    request(server, "GET", path, &[])
+}
+
+fn start() -> BundleServer {
+    BundleServer::start("127.0.0.1:0").unwrap()
+}
+
+fn bundle(target: Target, source: Result<&str, &str>) -> TargetBundle {
+    let file_name = format!("test.{}.lua", target.file_suffix());
+    TargetBundle {
+        source_map: SourceMap::new(file_name.clone(), Vec::new()),
+        file_name,
+        source: source.unwrap_or_default().to_string(),
+        size_report: None,
+        analyzer: None,
+        diagnostics: Vec::new(),
+        success: source.is_ok(),
+        error_message: source.err().map(str::to_string),
+        duration: Duration::ZERO,
+        target,
+    }
+}
+
+fn result(bundles: Vec<TargetBundle>) -> MemoryBuildResult {
+    MemoryBuildResult {
+        input_path: PathBuf::from("test.rbxm"),
+        bundles,
+        diagnostics: Vec::new(),
+        tree_shaking: None,
+        duration: Duration::ZERO,
+    }
+}
+
+mod bundles {
+    use super::*;
+
+    #[test]
+    fn served_with_hash() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("print('dev')"))]));
+
+        let reply = get(&server, "/dev.lua");
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.body, "print('dev')");
+        let hash = reply.header("x-bundle-hash").unwrap();
+        assert_eq!(reply.header("etag"), Some(format!("\"{hash}\"").as_str()));
+    }
+
+    #[test]
+    fn not_modified_with_matching_etag() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("print('dev')"))]));
+        let etag = get(&server, "/dev.lua").header("etag").unwrap().to_string();
+
+        let reply = request(&server, "GET", "/dev.lua", &[("If-None-Match", &etag)]);
+        assert_eq!(reply.status, 304);
+        assert!(reply.body.is_empty());
+    }
+
+    #[test]
+    fn failed_build_keeps_previous_bundle() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("print('first')"))]));
+        server.publish(&result(vec![bundle(Target::Dev, Err("syntax error"))]));
+
+        assert_eq!(get(&server, "/dev.lua").body, "print('first')");
+        assert!(get(&server, "/").body.contains("syntax error"));
+    }
+
+    #[test]
+    fn never_built() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Rel, Err("syntax error"))]));
+
+        let reply = get(&server, "/rel.lua");
+        assert_eq!(reply.status, 503);
+        assert_eq!(reply.body, "syntax error");
+    }
+
+    #[test]
+    fn unknown_target() {
+        let server = start();
+        assert_eq!(get(&server, "/dev.lua").status, 404);
+    }
+
+    #[test]
+    fn source_map() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("print('dev')"))]));
+
+        let reply = get(&server, "/dev.lua.map");
+        assert_eq!(reply.status, 200);
+        let map: SourceMap = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(map.file, "test.debug.lua");
+    }
+
+    #[test]
+    fn percent_encoded_names() {
+        let server = start();
+        let target = Target::custom(CustomTarget::new("qa build".to_string(), &Target::Dev));
+        server.publish(&result(vec![bundle(target, Ok("print('qa')"))]));
+
+        assert_eq!(server.bundle_url("qa build"), format!("{}/qa%20build.lua", server.url()));
+        assert_eq!(get(&server, "/qa%20build.lua").body, "print('qa')");
+        assert_eq!(get(&server, "/qa%2.lua").status, 400);
+    }
+
+    #[test]
+    fn only_get_and_head() {
+        let server = start();
+        assert_eq!(request(&server, "POST", "/", &[]).status, 405);
+    }
+}
+
+mod index {
+    use super::*;
+
+    #[test]
+    fn lists_targets() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("a")), bundle(Target::Rel, Ok("b"))]));
+
+        let index: serde_json::Value = serde_json::from_str(&get(&server, "/").body).unwrap();
+        let targets = index["targets"].as_array().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0]["name"], "dev");
+        assert_eq!(targets[0]["url"], server.bundle_url("dev"));
+        assert_eq!(targets[0]["loader"], server.loader_url("dev"));
+    }
+}
+
+mod loader {
+    use super::*;
+
+    #[test]
+    fn points_at_server() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("a"))]));
+
+        let reply = get(&server, "/dev/loader.lua");
+        assert_eq!(reply.status, 200);
+        assert!(reply.body.contains(&server.bundle_url("dev")), "{}", reply.body);
+        assert!(reply.body.contains(&format!("{}/dev/poll", server.url())), "{}", reply.body);
+        assert!(!reply.body.contains("{{"), "{}", reply.body);
+    }
+
+    #[test]
+    fn escapes_target_names() {
+        let server = start();
+        let target = Target::custom(CustomTarget::new("qa \"x\"\nend".to_string(), &Target::Dev));
+        server.publish(&result(vec![bundle(target, Ok("a"))]));
+
+        let reply = get(&server, "/qa%20%22x%22%0Aend/loader.lua");
+        assert_eq!(reply.status, 200);
+        assert!(reply.body.contains(r#"local BUNDLE_URL = ""#), "{}", reply.body);
+        assert!(reply.body.contains("/qa%20%22x%22%0Aend.lua\""), "{}", reply.body);
+        assert!(reply.body.contains(r#"loadstring(source, "=qa \"x\"\nend")"#), "{}", reply.body);
+        assert!(reply.body.starts_with(r#"-- Live loader for the "qa \"x\"\nend" bundle"#), "{}", reply.body);
+    }
+}
+
+mod poll {
+    use super::*;
+
+    #[test]
+    fn returns_immediately_when_hash_differs() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("a"))]));
+        let hash = get(&server, "/dev.lua").header("x-bundle-hash").unwrap().to_string();
+
+        let reply = get(&server, "/dev/poll?hash=stale");
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.body, hash);
+    }
+
+    #[test]
+    fn waits_for_rebuild() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("a"))]));
+        let hash = get(&server, "/dev.lua").header("x-bundle-hash").unwrap().to_string();
+
+        let reply = std::thread::scope(|scope| {
+            let pending = scope.spawn(|| get(&server, &format!("/dev/poll?hash={hash}")));
+            std::thread::sleep(Duration::from_millis(200));
+            server.publish(&result(vec![bundle(Target::Dev, Ok("b"))]));
+            pending.join().unwrap()
+        });
+
+        assert_eq!(reply.status, 200);
+        assert_ne!(reply.body, hash);
+        assert_eq!(get(&server, "/dev.lua").header("x-bundle-hash"), Some(reply.body.as_str()));
+    }
+
+    #[test]
+    fn pending_polls_do_not_block_other_requests() {
+        let server = start();
+        server.publish(&result(vec![bundle(Target::Dev, Ok("a"))]));
+        let hash = get(&server, "/dev.lua").header("x-bundle-hash").unwrap().to_string();
+
+        // More pending polls than the server has worker threads
+        let address = server.url().trim_start_matches("http://").to_string();
+        let mut polls: Vec<TcpStream> = (0..32)
+            .map(|_| {
+                let mut stream = TcpStream::connect(&address).unwrap();
+                let head = format!("GET /dev/poll?hash={hash} HTTP/1.1\r\nHost: {address}\r\nConnection: close\r\n\r\n");
+                stream.write_all(head.as_bytes()).unwrap();
+                stream
+            })
+            .collect();
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(get(&server, "/dev.lua").body, "a");
+
+        server.publish(&result(vec![bundle(Target::Dev, Ok("b"))]));
+        let new_hash = get(&server, "/dev.lua").header("x-bundle-hash").unwrap().to_string();
+        for stream in &mut polls {
+            stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            let mut raw = String::new();
+            stream.read_to_string(&mut raw).unwrap();
+            assert!(raw.starts_with("HTTP/1.1 200"), "{raw}");
+            assert!(raw.ends_with(&new_hash), "{raw}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Tests for `SourceMap` lookups and traceback symbolication.
+
+use rbxts_bundler::bundler::{BundlerError, ModuleMapping, SourceMap};
+
+fn mapping(path: &str, chunk: Option<&str>, start_line: usize, end_line: usize) -> ModuleMapping {
+    ModuleMapping {
+        id: 1,
+        path: path.to_string(),
+        class: "ModuleScript".to_string(),
+        chunk: chunk.map(str::to_string),
+        start_line,
+        start_column: 1,
+        end_line,
+        end_column: 80,
+        original_line: Some(1),
+    }
+}
+
+fn map() -> SourceMap {
+    SourceMap::new(
+        "model.release.lua".to_string(),
+        vec![
+            mapping("App.Main", None, 120, 140),
+            mapping("App.Util", None, 150, 170),
+            mapping("App.Debug", Some("App.Debug"), 2, 10),
+        ],
+    )
+}
+
+/// A release map of scripts sharing the bundle's only line.
+fn dense_map() -> SourceMap {
+    let mut main = mapping("App.Main", None, 1, 1);
+    (main.start_column, main.end_column, main.original_line) = (400, 900, None);
+    let mut util = mapping("App.Util", None, 1, 1);
+    (util.start_column, util.end_column, util.original_line) = (901, 1500, None);
+    SourceMap::new("model.release.lua".to_string(), vec![main, util])
+}
+
+mod resolve {
+    use super::*;
+
+    #[test]
+    fn inline_lines() {
+        let map = map();
+        let (module, line) = map.resolve("bundle", 155, None).unwrap();
+        assert_eq!(module.path, "App.Util");
+        assert_eq!(line, Some(6));
+    }
+
+    #[test]
+    fn chunk_lines() {
+        let map = map();
+        let (module, line) = map.resolve("App.Debug", 2, None).unwrap();
+        assert_eq!(module.path, "App.Debug");
+        assert_eq!(line, Some(1));
+    }
+
+    #[test]
+    fn outside_any_script() {
+        let map = map();
+        assert!(map.resolve("bundle", 10, None).is_none());
+        assert!(map.resolve("App.Debug", 1, None).is_none());
+    }
+
+    #[test]
+    fn columns_on_a_shared_line() {
+        let map = dense_map();
+        let (module, line) = map.resolve("bundle", 1, Some(1000)).unwrap();
+        assert_eq!(module.path, "App.Util");
+        assert_eq!(line, None);
+        assert!(map.resolve("bundle", 1, Some(20)).is_none());
+    }
+
+    #[test]
+    fn shared_line_without_column_is_ambiguous() {
+        assert!(dense_map().resolve("bundle", 1, None).is_none());
+    }
+}
+
+mod symbolicate {
+    use super::*;
+
+    #[test]
+    fn string_chunk_with_column() {
+        let trace = "[string \"bundle\"]:130:48213: attempt to index nil";
+        assert_eq!(map().symbolicate(trace), "App.Main:11: attempt to index nil");
+    }
+
+    #[test]
+    fn bare_chunk_names() {
+        let trace = "Players.Player.PlayerScripts.Bundle:151 function get";
+        assert_eq!(map().symbolicate(trace), "App.Util:2 function get");
+    }
+
+    #[test]
+    fn traceback_lines() {
+        let trace = "[string \"App.Debug\"]:4: oops\nStack Begin\nScript '[string \"App.Debug\"]', Line 4\n[string \"bundle\"]:121\nStack End";
+        assert_eq!(
+            map().symbolicate(trace),
+            "App.Debug:3: oops\nStack Begin\nScript '[string \"App.Debug\"]', Line 4\nApp.Main:2\nStack End"
+        );
+    }
+
+    #[test]
+    fn unknown_original_lines_keep_the_path() {
+        let trace = "[string \"bundle\"]:1:48213: boom\n[string \"bundle\"]:1:450: boom";
+        assert_eq!(dense_map().symbolicate(trace), "[string \"bundle\"]:1:48213: boom\nApp.Main: boom");
+    }
+
+    #[test]
+    fn leaves_unknown_locations() {
+        let trace = "Workspace.Script:5: runtime error\nat 12:30";
+        assert_eq!(map().symbolicate(trace), trace);
+    }
+}
+
+mod serialization {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/output/roundtrip.lua.map");
+        let original = map();
+        original.save(&path).unwrap();
+
+        let loaded = SourceMap::load(&path).unwrap();
+        assert_eq!(loaded, original);
+        assert_eq!(loaded.version, 2);
+    }
+
+    #[test]
+    fn load_errors_are_typed() {
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/output");
+        let missing = SourceMap::load(&dir.join("missing.lua.map")).unwrap_err();
+        assert!(matches!(missing, BundlerError::Io { .. }), "{missing:?}");
+        assert!(std::error::Error::source(&missing).is_some());
+
+        let path = dir.join("invalid.lua.map");
+        std::fs::write(&path, "{}").unwrap();
+        let invalid = SourceMap::load(&path).unwrap_err();
+        assert!(matches!(invalid, BundlerError::Decode { .. }), "{invalid:?}");
+        assert!(invalid.to_string().contains("invalid.lua.map"), "{invalid}");
+    }
+}
@@ -1,7 +1,7 @@
-//! Tests for `BundlerContext`.
+//! Tests for `BundlerContext` and `StartPolicy`.
 
 use rbxts_bundler::bundler::types::BundlerContext;
-use rbxts_bundler::bundler::Mode;
+use rbxts_bundler::bundler::{Mode, RunContext, StartPolicy};
 use std::path::Path;
 
 fn ctx(path: &str) -> BundlerContext<'_> {
@@ -73,3 +73,45 @@ mod templates {
         assert_eq!(result.matches("rbxts-bundler").count(), 3);
     }
 }
+
+mod start_policy {
+    use super::*;
+
+    #[test]
+    fn client_launches_local_and_client_scripts() {
+        let policy = StartPolicy::Client;
+        assert!(policy.launches("LocalScript", RunContext::Legacy));
+        assert!(policy.launches("Script", RunContext::Client));
+        assert!(!policy.launches("Script", RunContext::Legacy));
+        assert!(!policy.launches("Script", RunContext::Server));
+    }
+
+    #[test]
+    fn server_launches_legacy_and_server_scripts() {
+        let policy = StartPolicy::Server;
+        assert!(policy.launches("Script", RunContext::Legacy));
+        assert!(policy.launches("Script", RunContext::Server));
+        assert!(!policy.launches("LocalScript", RunContext::Legacy));
+    }
+
+    #[test]
+    fn never_launches_modules_or_plugin_scripts() {
+        for policy in [StartPolicy::Client, StartPolicy::Server, StartPolicy::All] {
+            assert!(!policy.launches("ModuleScript", RunContext::Legacy));
+            assert!(!policy.launches("Script", RunContext::Plugin));
+        }
+    }
+
+    #[test]
+    fn none_launches_nothing() {
+        assert!(!StartPolicy::None.launches("LocalScript", RunContext::Legacy));
+    }
+
+    #[test]
+    fn run_context_from_raw_value() {
+        assert_eq!(RunContext::from_u32(0), RunContext::Legacy);
+        assert_eq!(RunContext::from_u32(1), RunContext::Server);
+        assert_eq!(RunContext::from_u32(2), RunContext::Client);
+        assert_eq!(RunContext::from_u32(3), RunContext::Plugin);
+    }
+}
@@ -0,0 +1,134 @@
+//! Tests for watch mode change detection.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rbxts_bundler::bundler::{BuildConfig, Target, WatchedPath, Watcher};
+
+/// Creates an empty directory for a test.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::path::absolute(std::env::temp_dir().join("rbxts_bundler_watch").join(name)).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a file with a modification time in the future, so the change is
+/// detected regardless of the file system's timestamp resolution.
+fn touch(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+    let file = fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+}
+
+mod changes {
+    use super::*;
+
+    #[test]
+    fn modified_input() {
+        let dir = test_dir("modified_input");
+        let input = dir.join("model.rbxm");
+        touch(&input, "a");
+
+        let mut watcher = Watcher::new(&BuildConfig::new(input.clone(), dir.join("dist")), Vec::new()).unwrap();
+        assert!(watcher.poll().is_empty());
+
+        touch(&input, "b");
+        assert_eq!(watcher.poll(), vec![input]);
+        assert!(watcher.poll().is_empty());
+    }
+
+    #[test]
+    fn deleted_input() {
+        let dir = test_dir("deleted_input");
+        let input = dir.join("model.rbxm");
+        touch(&input, "a");
+
+        let mut watcher = Watcher::new(&BuildConfig::new(input.clone(), dir.join("dist")), Vec::new()).unwrap();
+        fs::remove_file(&input).unwrap();
+        assert_eq!(watcher.poll(), vec![input]);
+    }
+}
+
+mod rojo {
+    use super::*;
+
+    fn project(name: &str) -> PathBuf {
+        let dir = test_dir(name);
+        touch(&dir.join("default.project.json"), r#"{ "name": "Project", "tree": { "$path": "." } }"#);
+        touch(&dir.join("src/main.client.lua"), "print(1)");
+        dir
+    }
+
+    #[test]
+    fn watches_project_paths() {
+        let dir = project("rojo_paths");
+        let watcher = Watcher::new(&BuildConfig::new(dir.clone(), dir.join("dist")), Vec::new()).unwrap();
+
+        let paths: Vec<&Path> = watcher.paths().iter().map(|watched| watched.path.as_path()).collect();
+        assert_eq!(paths, [dir.join("default.project.json").as_path(), dir.as_path()]);
+    }
+
+    #[test]
+    fn skips_output_hidden_and_node_modules() {
+        let dir = project("rojo_skipped");
+        let mut watcher = Watcher::new(&BuildConfig::new(dir.clone(), dir.join("dist")), Vec::new()).unwrap();
+
+        touch(&dir.join("dist/project.debug.lua"), "");
+        touch(&dir.join(".git/index"), "");
+        touch(&dir.join("node_modules/typescript/index.js"), "");
+        assert!(watcher.poll().is_empty());
+
+        touch(&dir.join("node_modules/@rbxts/services/init.lua"), "");
+        touch(&dir.join("src/main.client.lua"), "print(2)");
+        assert_eq!(watcher.poll(), vec![
+            dir.join("node_modules/@rbxts/services/init.lua"),
+            dir.join("src/main.client.lua"),
+        ]);
+    }
+}
+
+mod affected_targets {
+    use super::*;
+
+    fn watcher(name: &str) -> (PathBuf, Watcher) {
+        let dir = test_dir(name);
+        touch(&dir.join("model.rbxm"), "");
+        touch(&dir.join("darklua.rel.json"), "{}");
+
+        let extra = vec![WatchedPath {
+            path: dir.join("darklua.rel.json"),
+            target: Some(Target::Rel),
+        }];
+        let watcher = Watcher::new(&BuildConfig::new(dir.join("model.rbxm"), dir.join("dist")), extra).unwrap();
+        (dir, watcher)
+    }
+
+    #[test]
+    fn scoped_file_rebuilds_its_target() {
+        let (dir, watcher) = watcher("scoped");
+        let targets = [Target::Dev, Target::Rel];
+
+        let affected = watcher.affected_targets(&[dir.join("darklua.rel.json")], &targets);
+        assert_eq!(affected, vec![Target::Rel]);
+    }
+
+    #[test]
+    fn input_rebuilds_every_target() {
+        let (dir, watcher) = watcher("input");
+        let targets = [Target::Dev, Target::Rel];
+
+        let affected = watcher.affected_targets(&[dir.join("model.rbxm"), dir.join("darklua.rel.json")], &targets);
+        assert_eq!(affected, targets);
+    }
+
+    #[test]
+    fn scoped_target_no_longer_built() {
+        let (dir, watcher) = watcher("unbuilt");
+
+        let affected = watcher.affected_targets(&[dir.join("darklua.rel.json")], &[Target::Dev]);
+        assert!(affected.is_empty());
+    }
+}